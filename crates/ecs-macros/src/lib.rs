@@ -1,15 +1,473 @@
 use proc_macro::TokenStream;
-use quote::quote;
-use syn::{parse_macro_input, DeriveInput};
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, Attribute, Data, DeriveInput, Fields, Index, LitInt, Path};
 
-#[proc_macro_derive(Component)]
+// flags set with `#[component(...)]` or `#[resource(...)]`
+#[derive(Default)]
+struct Attributes {
+  serializable: bool,
+  // the type implements Clone, components can be snapshot
+  clone: bool,
+  // the type implements Debug, components are shown by `World::dump`
+  debug: bool,
+  // schema version of a serializable component
+  version: Option<u32>,
+  // functions called when the component is added to or removed from an entity
+  on_add: Option<Path>,
+  on_remove: Option<Path>,
+}
+
+fn parse_attributes(attrs: &[Attribute], attr_name: &str) -> syn::Result<Attributes> {
+  let mut attributes = Attributes::default();
+  for attr in attrs.iter().filter(|attr| attr.path().is_ident(attr_name)) {
+    attr.parse_nested_meta(|meta| {
+      if meta.path.is_ident("serializable") {
+        attributes.serializable = true;
+        Ok(())
+      } else if meta.path.is_ident("clone") && attr_name == "component" {
+        attributes.clone = true;
+        Ok(())
+      } else if meta.path.is_ident("debug") && attr_name == "component" {
+        attributes.debug = true;
+        Ok(())
+      } else if meta.path.is_ident("on_add") && attr_name == "component" {
+        attributes.on_add = Some(meta.value()?.parse()?);
+        Ok(())
+      } else if meta.path.is_ident("on_remove") && attr_name == "component" {
+        attributes.on_remove = Some(meta.value()?.parse()?);
+        Ok(())
+      } else if meta.path.is_ident("version") && attr_name == "component" {
+        let version: LitInt = meta.value()?.parse()?;
+        attributes.version = Some(version.base10_parse()?);
+        Ok(())
+      } else {
+        Err(meta.error(format!("unknown {} attribute", attr_name)))
+      }
+    })?;
+  }
+  Ok(attributes)
+}
+
+#[proc_macro_derive(Component, attributes(component, reflect))]
 pub fn component_derive(input: TokenStream) -> TokenStream {
   let input = parse_macro_input!(input as DeriveInput);
   let name = &input.ident;
+  let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+  let attributes = match parse_attributes(&input.attrs, "component") {
+    Ok(attributes) => attributes,
+    Err(error) => return error.to_compile_error().into(),
+  };
+  if attributes.version.is_some() && !attributes.serializable {
+    return syn::Error::new_spanned(name, "`version` requires `serializable`")
+      .to_compile_error()
+      .into();
+  }
+  let fields = match component_fields(&input) {
+    Ok(fields) => fields,
+    Err(error) => return error.to_compile_error().into(),
+  };
+  let entity_fields = fields.entity;
+  let reflect = reflect_impl(&input);
+
+  let serializer = if attributes.serializable {
+    let version = attributes.version.unwrap_or(1);
+    quote! {
+      fn serializer() -> Option<ecs::serialization::ComponentSerializer> {
+        Some(ecs::serialization::ComponentSerializer::of::<Self>(#version))
+      }
+    }
+  } else {
+    quote! {}
+  };
+
+  let cloner = if attributes.clone {
+    quote! {
+      fn cloner() -> Option<fn(&Self) -> Self> {
+        Some(<Self as Clone>::clone)
+      }
+    }
+  } else {
+    quote! {}
+  };
+
+  let formatter = if attributes.debug {
+    quote! {
+      fn formatter() -> Option<fn(&Self) -> String> {
+        Some(|component| format!("{:?}", component))
+      }
+    }
+  } else {
+    quote! {}
+  };
+
+  let on_add = match &attributes.on_add {
+    Some(hook) => quote! {
+      fn on_add() -> Option<ecs::component::ComponentHook> {
+        Some(#hook)
+      }
+    },
+    None => quote! {},
+  };
+
+  let on_remove = match &attributes.on_remove {
+    Some(hook) => quote! {
+      fn on_remove() -> Option<ecs::component::ComponentHook> {
+        Some(#hook)
+      }
+    },
+    None => quote! {},
+  };
+
+  let map_entities = if entity_fields.is_empty() {
+    quote! {}
+  } else {
+    quote! {
+      fn map_entities(&mut self, mapper: &ecs::transfer::EntityMapper) {
+        #(ecs::transfer::MapEntities::map_entities(&mut self.#entity_fields, mapper);)*
+      }
+    }
+  };
+
+  let (indexer, indexed) = match fields.index {
+    Some(index) => {
+      let IndexedField {
+        member,
+        ty,
+        ordered,
+        unique,
+      } = index;
+      let kind = if ordered {
+        quote! { ecs::index::IndexKind::Ordered }
+      } else {
+        quote! { ecs::index::IndexKind::Hash }
+      };
+      (
+        quote! {
+          fn indexer() -> Option<ecs::index::NewIndexer<Self>> {
+            Some(ecs::index::indexer::<Self>)
+          }
+        },
+        quote! {
+          impl #impl_generics ecs::index::Indexed for #name #ty_generics #where_clause {
+            type Key = #ty;
+            const KIND: ecs::index::IndexKind = #kind;
+            const UNIQUE: bool = #unique;
+
+            fn key(&self) -> &Self::Key {
+              &self.#member
+            }
+          }
+        },
+      )
+    }
+    None => (quote! {}, quote! {}),
+  };
+
+  let expanded = quote! {
+    impl #impl_generics ecs::component::Component for #name #ty_generics #where_clause {
+      #serializer
+      #cloner
+      #formatter
+      #indexer
+      #on_add
+      #on_remove
+      #map_entities
+    }
+
+    #indexed
+
+    #reflect
+  };
+
+  TokenStream::from(expanded)
+}
+
+// the field used by `#[component(index)]`
+struct IndexedField {
+  member: proc_macro2::TokenStream,
+  ty: syn::Type,
+  ordered: bool,
+  unique: bool,
+}
+
+// fields marked with `#[component(...)]`
+#[derive(Default)]
+struct ComponentFields {
+  // `#[component(entity)]`, ids of other entities
+  entity: Vec<proc_macro2::TokenStream>,
+  // `#[component(index)]`, `#[component(index = "ordered")]` or `#[component(unique)]`
+  index: Option<IndexedField>,
+}
+
+fn component_fields(input: &DeriveInput) -> syn::Result<ComponentFields> {
+  let mut fields = ComponentFields::default();
+  let Data::Struct(data) = &input.data else {
+    return Ok(fields);
+  };
+  for (index, field) in data.fields.iter().enumerate() {
+    let member = match &field.ident {
+      Some(ident) => quote! { #ident },
+      None => {
+        let index = Index::from(index);
+        quote! { #index }
+      }
+    };
+    let mut is_entity = false;
+    let mut indexed = false;
+    let mut ordered = false;
+    let mut unique = false;
+    for attr in field.attrs.iter().filter(|attr| attr.path().is_ident("component")) {
+      attr.parse_nested_meta(|meta| {
+        if meta.path.is_ident("entity") {
+          is_entity = true;
+          Ok(())
+        } else if meta.path.is_ident("index") {
+          indexed = true;
+          if meta.input.peek(syn::Token![=]) {
+            let kind: syn::LitStr = meta.value()?.parse()?;
+            match kind.value().as_str() {
+              "hash" => ordered = false,
+              "ordered" => ordered = true,
+              _ => return Err(meta.error("expected \"hash\" or \"ordered\"")),
+            }
+          }
+          Ok(())
+        } else if meta.path.is_ident("unique") {
+          indexed = true;
+          unique = true;
+          Ok(())
+        } else {
+          Err(meta.error("unknown component field attribute"))
+        }
+      })?;
+    }
+    if is_entity {
+      fields.entity.push(member.clone());
+    }
+    if indexed {
+      if fields.index.is_some() {
+        return Err(syn::Error::new_spanned(
+          field,
+          "a component can only have one indexed field",
+        ));
+      }
+      fields.index = Some(IndexedField {
+        member,
+        ty: field.ty.clone(),
+        ordered,
+        unique,
+      });
+    }
+  }
+  Ok(fields)
+}
+
+#[proc_macro_derive(Resource, attributes(resource))]
+pub fn resource_derive(input: TokenStream) -> TokenStream {
+  let input = parse_macro_input!(input as DeriveInput);
+  let name = &input.ident;
+  let name_str = name.to_string();
+  let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+  let attributes = match parse_attributes(&input.attrs, "resource") {
+    Ok(attributes) => attributes,
+    Err(error) => return error.to_compile_error().into(),
+  };
+
+  let serializer = if attributes.serializable {
+    quote! {
+      fn serializer() -> Option<ecs::serialization::ResourceSerializer> {
+        Some(ecs::serialization::ResourceSerializer::of::<Self>())
+      }
+    }
+  } else {
+    quote! {}
+  };
 
   let expanded = quote! {
-    impl ecs::component::Component for #name {}
+    impl #impl_generics ecs::resource::Resource for #name #ty_generics #where_clause {
+      fn name() -> &'static str {
+        #name_str
+      }
+
+      #serializer
+    }
   };
 
   TokenStream::from(expanded)
-}
\ No newline at end of file
+}
+
+#[proc_macro_derive(Reflect, attributes(reflect))]
+pub fn reflect_derive(input: TokenStream) -> TokenStream {
+  let input = parse_macro_input!(input as DeriveInput);
+  TokenStream::from(reflect_impl(&input))
+}
+
+fn reflect_impl(input: &DeriveInput) -> proc_macro2::TokenStream {
+  let name = &input.ident;
+  let name_str = match reflect_name(input) {
+    Ok(reflect_name) => reflect_name.unwrap_or_else(|| name.to_string()),
+    Err(error) => return error.to_compile_error(),
+  };
+  let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+  // only structs have fields, enums are described by their name only
+  let mut field_names = Vec::new();
+  let mut field_accessors = Vec::new();
+  let mut field_types = Vec::new();
+  if let Data::Struct(data) = &input.data {
+    for (index, field) in data.fields.iter().enumerate() {
+      match reflect_ignored(field) {
+        Ok(true) => continue,
+        Ok(false) => {}
+        Err(error) => return error.to_compile_error(),
+      }
+      match &field.ident {
+        Some(ident) => {
+          field_names.push(ident.to_string());
+          field_accessors.push(quote! { #ident });
+        }
+        None => {
+          let index = Index::from(index);
+          field_names.push(index.index.to_string());
+          field_accessors.push(quote! { #index });
+        }
+      }
+      field_types.push(&field.ty);
+    }
+  }
+
+  let hash = match hash_body(input) {
+    Ok(hash) => hash,
+    Err(error) => return error.to_compile_error(),
+  };
+
+  quote! {
+    impl #impl_generics ecs::reflect::Reflect for #name #ty_generics #where_clause {
+      fn type_info() -> ecs::reflect::TypeInfo {
+        ecs::reflect::TypeInfo::of::<Self>(
+          #name_str,
+          vec![
+            #(ecs::reflect::FieldInfo {
+              name: #field_names,
+              type_name: std::any::type_name::<#field_types>(),
+            }),*
+          ],
+        )
+      }
+
+      fn field(&self, name: &str) -> Option<&dyn ecs::reflect::Reflect> {
+        match name {
+          #(#field_names => Some(&self.#field_accessors),)*
+          _ => None,
+        }
+      }
+
+      fn field_mut(&mut self, name: &str) -> Option<&mut dyn ecs::reflect::Reflect> {
+        match name {
+          #(#field_names => Some(&mut self.#field_accessors),)*
+          _ => None,
+        }
+      }
+
+      #[allow(unused_variables)]
+      fn reflect_hash(&self, state: &mut dyn std::hash::Hasher) {
+        #hash
+      }
+    }
+  }
+}
+
+// `#[reflect(name = "...")]` on the type replaces its short name, the key in the saves,
+// the prefabs and the scripts (the built-in components are namespaced with it)
+fn reflect_name(input: &DeriveInput) -> syn::Result<Option<String>> {
+  let mut name = None;
+  for attr in input.attrs.iter().filter(|attr| attr.path().is_ident("reflect")) {
+    attr.parse_nested_meta(|meta| {
+      if meta.path.is_ident("name") {
+        let value: syn::LitStr = meta.value()?.parse()?;
+        name = Some(value.value());
+        Ok(())
+      } else {
+        Err(meta.error("unknown reflect attribute"))
+      }
+    })?;
+  }
+  Ok(name)
+}
+
+// `#[reflect(ignore)]` leaves a field out of the reflection and the hash,
+// used for the fields whose type does not implement Reflect
+fn reflect_ignored(field: &syn::Field) -> syn::Result<bool> {
+  let mut ignored = false;
+  for attr in field.attrs.iter().filter(|attr| attr.path().is_ident("reflect")) {
+    attr.parse_nested_meta(|meta| {
+      if meta.path.is_ident("ignore") {
+        ignored = true;
+        Ok(())
+      } else {
+        Err(meta.error("unknown reflect field attribute"))
+      }
+    })?;
+  }
+  Ok(ignored)
+}
+
+// hash every field in declaration order, enums hash the variant index first
+fn hash_body(input: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+  match &input.data {
+    Data::Struct(data) => {
+      let mut accessors = Vec::new();
+      for (index, field) in data.fields.iter().enumerate() {
+        if reflect_ignored(field)? {
+          continue;
+        }
+        accessors.push(match &field.ident {
+          Some(ident) => quote! { #ident },
+          None => {
+            let index = Index::from(index);
+            quote! { #index }
+          }
+        });
+      }
+      Ok(quote! {
+        #(ecs::reflect::Reflect::reflect_hash(&self.#accessors, state);)*
+      })
+    }
+    Data::Enum(data) if !data.variants.is_empty() => {
+      let mut arms = Vec::new();
+      for (index, variant) in data.variants.iter().enumerate() {
+        let variant_name = &variant.ident;
+        let index = index as u32;
+        let bindings: Vec<_> = (0..variant.fields.len())
+          .map(|i| format_ident!("__field{}", i))
+          .collect();
+        let mut hashed = Vec::new();
+        for (binding, field) in bindings.iter().zip(variant.fields.iter()) {
+          if !reflect_ignored(field)? {
+            hashed.push(binding);
+          }
+        }
+        let pattern = match &variant.fields {
+          Fields::Named(fields) => {
+            let names = fields.named.iter().map(|field| &field.ident);
+            quote! { { #(#names: #bindings),* } }
+          }
+          Fields::Unnamed(_) => quote! { ( #(#bindings),* ) },
+          Fields::Unit => quote! {},
+        };
+        arms.push(quote! {
+          Self::#variant_name #pattern => {
+            state.write_u32(#index);
+            #(ecs::reflect::Reflect::reflect_hash(#hashed, state);)*
+          }
+        });
+      }
+      Ok(quote! {
+        match self {
+          #(#arms)*
+        }
+      })
+    }
+    _ => Ok(quote! {}),
+  }
+}
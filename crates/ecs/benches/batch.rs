@@ -0,0 +1,92 @@
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use ecs::world::World;
+use ecs_macros::Component;
+
+#[derive(Component)]
+struct Position {
+    _x: f32,
+    _y: f32,
+}
+
+#[derive(Component)]
+struct Ore {
+    _amount: u32,
+}
+
+fn new_world() -> World {
+    let mut world = World::new();
+    world.register_component::<Position>();
+    world.register_component::<Ore>();
+    world
+}
+
+fn ore_field(count: usize) -> impl Iterator<Item = (Position, Ore)> {
+    (0..count).map(|i| {
+        (
+            Position {
+                _x: i as f32,
+                _y: 0.0,
+            },
+            Ore { _amount: 100 },
+        )
+    })
+}
+
+fn spawn(c: &mut Criterion) {
+    let mut group = c.benchmark_group("spawn");
+    group.sample_size(10);
+
+    // one by one spawning moves each entity between groups, keep it small
+    for count in [1_000, 10_000] {
+        group.bench_with_input(BenchmarkId::new("single", count), &count, |b, &count| {
+            b.iter(|| {
+                let mut world = new_world();
+                for (position, ore) in ore_field(count) {
+                    let entity = world.create_entity();
+                    world.add_component_to_entity(entity, position);
+                    world.add_component_to_entity(entity, ore);
+                }
+                world
+            })
+        });
+    }
+
+    for count in [1_000, 10_000, 100_000, 1_000_000] {
+        group.bench_with_input(BenchmarkId::new("batch", count), &count, |b, &count| {
+            b.iter(|| {
+                let mut world = new_world();
+                world.spawn_batch(ore_field(count));
+                world
+            })
+        });
+    }
+
+    group.finish();
+}
+
+fn despawn(c: &mut Criterion) {
+    let mut group = c.benchmark_group("despawn");
+    group.sample_size(10);
+
+    for count in [10_000, 100_000, 1_000_000] {
+        group.bench_with_input(BenchmarkId::new("batch", count), &count, |b, &count| {
+            b.iter_batched(
+                || {
+                    let mut world = new_world();
+                    let entities = world.spawn_batch(ore_field(count));
+                    (world, entities)
+                },
+                |(mut world, entities)| {
+                    world.despawn_batch(&entities);
+                    world
+                },
+                criterion::BatchSize::LargeInput,
+            )
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, spawn, despawn);
+criterion_main!(benches);
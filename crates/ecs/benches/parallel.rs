@@ -0,0 +1,56 @@
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use ecs::entity_manager::EntityManager;
+use ecs_macros::Component;
+
+#[derive(Component)]
+struct BeltItem {
+    progress: f32,
+}
+
+#[derive(Component)]
+struct Powered;
+
+fn new_entity_manager(count: usize) -> EntityManager {
+    let mut entity_manager = EntityManager::new();
+    entity_manager.register_component::<BeltItem>();
+    entity_manager.register_component::<Powered>();
+    let entities = entity_manager.spawn_batch((0..count).map(|_| (BeltItem { progress: 0.0 },)));
+    // two signature groups
+    for entity in entities.iter().step_by(2) {
+        entity_manager.add_component_to_entity(*entity, Powered);
+    }
+    entity_manager
+}
+
+// some work for each component, a belt item moving along a curve
+fn advance(item: &mut BeltItem) {
+    for _ in 0..16 {
+        item.progress = (item.progress + 0.1).sin().abs();
+    }
+}
+
+fn for_each(c: &mut Criterion) {
+    let mut group = c.benchmark_group("for_each");
+    group.sample_size(10);
+
+    for count in [10_000, 100_000, 1_000_000] {
+        let mut entity_manager = new_entity_manager(count);
+        group.bench_with_input(BenchmarkId::new("serial", count), &count, |b, _| {
+            b.iter(|| {
+                for (_, item) in entity_manager.iter_components_mut::<BeltItem>() {
+                    advance(item);
+                }
+            })
+        });
+
+        let mut entity_manager = new_entity_manager(count);
+        group.bench_with_input(BenchmarkId::new("parallel", count), &count, |b, _| {
+            b.iter(|| entity_manager.par_for_each::<BeltItem, _>(|_, item| advance(item)))
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, for_each);
+criterion_main!(benches);
@@ -0,0 +1,87 @@
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use ecs::world::World;
+use ecs_macros::Component;
+
+#[derive(Component)]
+struct Position {
+    _x: f32,
+    _y: f32,
+}
+
+#[derive(Component)]
+struct Burning;
+
+fn new_world(count: usize) -> (World, Vec<usize>) {
+    let mut world = World::new();
+    world.register_component::<Position>();
+    world.register_component::<Burning>();
+    let entities = world.spawn_batch((0..count).map(|i| {
+        (Position {
+            _x: i as f32,
+            _y: 0.0,
+        },)
+    }));
+    (world, entities)
+}
+
+// every entity moves to another signature group and back
+fn churn(c: &mut Criterion) {
+    let mut group = c.benchmark_group("churn");
+    group.sample_size(10);
+
+    for count in [10_000, 100_000] {
+        group.bench_with_input(
+            BenchmarkId::new("add_component", count),
+            &count,
+            |b, &count| {
+                b.iter_batched(
+                    || new_world(count),
+                    |(mut world, entities)| {
+                        for entity in entities {
+                            world.add_component_to_entity(entity, Burning);
+                        }
+                        world
+                    },
+                    criterion::BatchSize::LargeInput,
+                )
+            },
+        );
+
+        group.bench_with_input(
+            BenchmarkId::new("remove_entity", count),
+            &count,
+            |b, &count| {
+                b.iter_batched(
+                    || new_world(count),
+                    |(mut world, entities)| {
+                        for entity in entities {
+                            world.remove_entity(entity);
+                        }
+                        world
+                    },
+                    criterion::BatchSize::LargeInput,
+                )
+            },
+        );
+    }
+
+    group.finish();
+}
+
+// a tag is switched on and off on every machine, like `Powered` each tick
+fn toggle_tag(c: &mut Criterion) {
+    let (mut world, entities) = new_world(50_000);
+    c.bench_function("toggle_tag/50000", |b| {
+        b.iter(|| {
+            for entity in entities.iter() {
+                world.add_component_to_entity(*entity, Burning);
+            }
+            for entity in entities.iter() {
+                world.remove_component_from_entity::<Burning>(*entity);
+            }
+        })
+    });
+}
+
+criterion_group!(benches, churn, toggle_tag);
+criterion_main!(benches);
@@ -0,0 +1,104 @@
+use std::any::TypeId;
+use std::collections::HashMap;
+
+use crate::component::Component;
+use crate::component_manager::{ComponentManager, ComponentManagerTrait, cast_manager_mut};
+use crate::index::IndexError;
+use crate::query_manager::QueryManager;
+
+/// A set of components added together to an entity, see `EntityManager::spawn_batch`
+/// Implemented for tuples of up to 4 components: `(Position,)`, `(Position, Ore)`...
+pub trait Bundle: 'static {
+    /// The signature of an entity with only these components
+    /// Will panic if a component is not registered
+    fn bitmask(query_manager: &QueryManager) -> u128;
+
+    /// Whether the bundles can be added to the entities without breaking a unique index
+    fn check(
+        bundles: &[Self],
+        entities_ids: &[usize],
+        managers: &mut HashMap<TypeId, Box<dyn ComponentManagerTrait>>,
+    ) -> Result<(), IndexError>
+    where
+        Self: Sized;
+
+    /// Make room for `additional` more components in each manager
+    fn reserve(managers: &mut HashMap<TypeId, Box<dyn ComponentManagerTrait>>, additional: usize);
+
+    fn add(self, entity_id: usize, managers: &mut HashMap<TypeId, Box<dyn ComponentManagerTrait>>);
+}
+
+// None for the tags, they are only a bit of the signature
+fn manager_mut<T: 'static + Component>(
+    managers: &mut HashMap<TypeId, Box<dyn ComponentManagerTrait>>,
+) -> Option<&mut ComponentManager<T>> {
+    let manager = managers.get_mut(&TypeId::of::<T>())?;
+    cast_manager_mut::<T>(manager.as_mut())
+}
+
+macro_rules! impl_bundle {
+    ($(($component:ident, $index:tt)),+) => {
+        impl<$($component: 'static + Component),+> Bundle for ($($component,)+) {
+            fn bitmask(query_manager: &QueryManager) -> u128 {
+                let mut bitmask = 0;
+                $(
+                    bitmask |= query_manager
+                        .get_bit_for_component::<$component>()
+                        .unwrap_or_else(|| {
+                            panic!(
+                                "Component not found for type: {}",
+                                std::any::type_name::<$component>()
+                            )
+                        });
+                )+
+                bitmask
+            }
+
+            fn check(
+                bundles: &[Self],
+                entities_ids: &[usize],
+                managers: &mut HashMap<TypeId, Box<dyn ComponentManagerTrait>>,
+            ) -> Result<(), IndexError> {
+                $(
+                    if let Some(manager) = manager_mut::<$component>(managers) {
+                        let components: Vec<(usize, &$component)> = entities_ids
+                            .iter()
+                            .copied()
+                            .zip(bundles.iter().map(|bundle| &bundle.$index))
+                            .collect();
+                        manager.check_new(&components)?;
+                    }
+                )+
+                Ok(())
+            }
+
+            fn reserve(
+                managers: &mut HashMap<TypeId, Box<dyn ComponentManagerTrait>>,
+                additional: usize,
+            ) {
+                $(
+                    if let Some(manager) = manager_mut::<$component>(managers) {
+                        manager.reserve(additional);
+                    }
+                )+
+            }
+
+            fn add(
+                self,
+                entity_id: usize,
+                managers: &mut HashMap<TypeId, Box<dyn ComponentManagerTrait>>,
+            ) {
+                $(
+                    if let Some(manager) = manager_mut::<$component>(managers) {
+                        manager.add(entity_id, self.$index);
+                    }
+                )+
+            }
+        }
+    };
+}
+
+impl_bundle!((A, 0));
+impl_bundle!((A, 0), (B, 1));
+impl_bundle!((A, 0), (B, 1), (C, 2));
+impl_bundle!((A, 0), (B, 1), (C, 2), (D, 3));
@@ -1 +1,49 @@
-pub trait Component: Sized {}
\ No newline at end of file
+use crate::entity_manager::EntityManager;
+use crate::index::NewIndexer;
+use crate::reflect::Reflect;
+use crate::serialization::ComponentSerializer;
+use crate::transfer::EntityMapper;
+
+/// Called with the id of the entity after a component is added to it,
+/// or before it is removed (the component can still be borrowed)
+pub type ComponentHook = fn(&mut EntityManager, usize);
+
+pub trait Component: Reflect + Sized {
+    /// Set by `#[component(serializable)]`, components without it are not saved
+    fn serializer() -> Option<ComponentSerializer> {
+        None
+    }
+
+    /// Set by `#[component(clone)]`, needed to snapshot the component
+    fn cloner() -> Option<fn(&Self) -> Self> {
+        None
+    }
+
+    /// Set by `#[component(debug)]`, shows the value of the component in `World::dump`
+    fn formatter() -> Option<fn(&Self) -> String> {
+        None
+    }
+
+    /// Set by `#[component(index)]` or `#[component(unique)]` on a field,
+    /// the index is kept by the `ComponentManager`
+    fn indexer() -> Option<NewIndexer<Self>> {
+        None
+    }
+
+    /// Set by `#[component(on_add = function)]`, also called when the component is
+    /// replaced (`replace_component_for_entity`) and for every component after a load
+    /// or a restore
+    fn on_add() -> Option<ComponentHook> {
+        None
+    }
+
+    /// Set by `#[component(on_remove = function)]`, also called when the component
+    /// is replaced, when its entity is removed or moved to another world
+    fn on_remove() -> Option<ComponentHook> {
+        None
+    }
+
+    /// Set by the `#[component(entity)]` fields, remap the ids of the related
+    /// entities when the component is moved to another world or copied
+    fn map_entities(&mut self, _mapper: &EntityMapper) {}
+}
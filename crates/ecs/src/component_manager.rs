@@ -1,18 +1,64 @@
 use std::{
     any::{Any, TypeId},
     collections::HashMap,
+    hash::Hasher,
+    sync::Arc,
 };
 
 use crate::component::Component;
+use crate::index::{ComponentIndex, ComponentIndexer, IndexError, Indexed};
+use crate::reflect::Reflect;
+use crate::transfer::EntityMapper;
 
 // store all the components T
 pub struct ComponentManager<T: Component> {
+    // shared with the snapshots of the world, copied on the first write (see `storage_mut`)
+    storage: Arc<ComponentStorage<T>>,
+    // used to copy a shared storage, None if T is not clonable
+    clone_component: Option<fn(&T) -> T>,
+    // used by the dumps, None if T is not debuggable
+    format_component: Option<fn(&T) -> String>,
+}
+
+struct ComponentStorage<T> {
     // all the components structures
     components: Vec<T>,
     // all the entities ids
     entities_ids: Vec<usize>,
     // map the entity id to the component index
     entity_to_component_index: HashMap<usize, usize>,
+    // the index of the `#[component(index)]` field, None if T has none
+    index: Option<Box<dyn ComponentIndexer<T>>>,
+    // the entities whose component was borrowed mutably since the index was refreshed
+    dirty: Vec<usize>,
+    // every component was borrowed mutably
+    all_dirty: bool,
+}
+
+/// The components of a `ComponentManager` by owner, see `ComponentManager::lookup`
+pub struct ComponentLookup<'a, T> {
+    components: &'a [T],
+    entity_to_component_index: &'a HashMap<usize, usize>,
+}
+
+impl<'a, T> ComponentLookup<'a, T> {
+    pub fn get(&self, entity_id: usize) -> Option<&'a T> {
+        let component_index = self.entity_to_component_index.get(&entity_id)?;
+        Some(&self.components[*component_index])
+    }
+}
+
+impl<T: Component> ComponentStorage<T> {
+    fn new() -> Self {
+        Self {
+            components: Vec::new(),
+            entities_ids: Vec::new(),
+            entity_to_component_index: HashMap::new(),
+            index: T::indexer().map(|indexer| indexer()),
+            dirty: Vec::new(),
+            all_dirty: false,
+        }
+    }
 }
 
 pub trait ComponentManagerTrait {
@@ -21,7 +67,40 @@ pub trait ComponentManagerTrait {
 
     fn has(&self, entity_id: usize) -> bool;
     fn remove(&mut self, entity_id: usize);
+    /// Remove the component of an entity and return it boxed
+    fn take_any(&mut self, entity_id: usize) -> Option<Box<dyn Any>>;
+    /// A copy of the component of an entity, None if the component is not clonable
+    fn clone_any(&self, entity_id: usize) -> Option<Box<dyn Any>>;
+    /// Add a boxed component to an entity
+    /// Will panic if the box does not hold the component type of the manager
+    fn add_any(&mut self, entity_id: usize, component: Box<dyn Any>);
+    /// Remap the relations of the component of an entity, see `MapEntities`
+    fn map_entities(&mut self, entity_id: usize, mapper: &EntityMapper);
+    fn clear(&mut self);
     fn get_type_id(&self) -> TypeId;
+
+    fn reflect(&self, entity_id: usize) -> Option<&dyn Reflect>;
+    fn reflect_mut(&mut self, entity_id: usize) -> Option<&mut dyn Reflect>;
+
+    /// Hash every component along with its owner, in entity id order
+    fn hash_components(&self, state: &mut dyn Hasher);
+    /// The value of the component of an entity, None if the component is not debuggable
+    fn format(&self, entity_id: usize) -> Option<String>;
+
+    fn len(&self) -> usize;
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    fn is_clonable(&self) -> bool;
+    /// Whether a unique index rejects the copies of the components
+    fn has_unique_index(&self) -> bool;
+    /// Whether the components of new entities can be added without breaking a
+    /// unique index, see `ComponentManager::check_new`
+    /// Will panic if a component is not of the type of the manager
+    fn check_new_any(&mut self, components: &[(usize, &dyn Any)]) -> Result<(), IndexError>;
+    /// A manager sharing the same storage, it is copied when one of them is modified
+    /// Will panic if the components are not clonable
+    fn share(&self) -> Box<dyn ComponentManagerTrait>;
 }
 
 impl<T: 'static + Component> ComponentManagerTrait for ComponentManager<T> {
@@ -43,9 +122,109 @@ impl<T: 'static + Component> ComponentManagerTrait for ComponentManager<T> {
         manager.remove(entity_id)
     }
 
+    fn clear(&mut self) {
+        let manager = cast_manager_mut::<T>(self).unwrap();
+        manager.clear()
+    }
+
+    fn take_any(&mut self, entity_id: usize) -> Option<Box<dyn Any>> {
+        self.take(entity_id)
+            .map(|component| Box::new(component) as Box<dyn Any>)
+    }
+
+    fn clone_any(&self, entity_id: usize) -> Option<Box<dyn Any>> {
+        let clone_component = self.clone_component?;
+        self.borrow_component_for_entity(entity_id)
+            .map(|component| Box::new(clone_component(component)) as Box<dyn Any>)
+    }
+
+    fn add_any(&mut self, entity_id: usize, component: Box<dyn Any>) {
+        let component = component
+            .downcast::<T>()
+            .unwrap_or_else(|_| panic!("Component is not of type: {}", std::any::type_name::<T>()));
+        self.add(entity_id, *component);
+    }
+
+    fn map_entities(&mut self, entity_id: usize, mapper: &EntityMapper) {
+        if let Some(component) = self.borrow_component_mut(entity_id) {
+            component.map_entities(mapper);
+        }
+    }
+
     fn get_type_id(&self) -> TypeId {
         TypeId::of::<T>()
     }
+
+    fn reflect(&self, entity_id: usize) -> Option<&dyn Reflect> {
+        self.borrow_component_for_entity(entity_id)
+            .map(|component| component as &dyn Reflect)
+    }
+
+    fn reflect_mut(&mut self, entity_id: usize) -> Option<&mut dyn Reflect> {
+        self.borrow_component_mut(entity_id)
+            .map(|component| component as &mut dyn Reflect)
+    }
+
+    fn hash_components(&self, state: &mut dyn Hasher) {
+        let storage = &self.storage;
+        // storage order depends on the add/remove history, not the entity ids
+        let mut indices: Vec<usize> = (0..storage.entities_ids.len()).collect();
+        indices.sort_by_key(|index| storage.entities_ids[*index]);
+
+        for index in indices {
+            state.write_u64(storage.entities_ids[index] as u64);
+            storage.components[index].reflect_hash(state);
+        }
+    }
+
+    fn format(&self, entity_id: usize) -> Option<String> {
+        let format_component = self.format_component?;
+        self.borrow_component_for_entity(entity_id)
+            .map(format_component)
+    }
+
+    fn len(&self) -> usize {
+        self.storage.components.len()
+    }
+
+    fn is_clonable(&self) -> bool {
+        self.clone_component.is_some()
+    }
+
+    fn has_unique_index(&self) -> bool {
+        self.storage
+            .index
+            .as_ref()
+            .is_some_and(|index| index.is_unique())
+    }
+
+    fn check_new_any(&mut self, components: &[(usize, &dyn Any)]) -> Result<(), IndexError> {
+        let components: Vec<(usize, &T)> = components
+            .iter()
+            .map(|(entity_id, component)| {
+                let component = component.downcast_ref::<T>().unwrap_or_else(|| {
+                    panic!("Component is not of type: {}", std::any::type_name::<T>())
+                });
+                (*entity_id, component)
+            })
+            .collect();
+        self.check_new(&components)
+    }
+
+    fn share(&self) -> Box<dyn ComponentManagerTrait> {
+        if self.clone_component.is_none() {
+            panic!(
+                "Cannot share the storage of a non clonable component: {}",
+                std::any::type_name::<T>()
+            );
+        }
+
+        Box::new(ComponentManager {
+            storage: self.storage.clone(),
+            clone_component: self.clone_component,
+            format_component: self.format_component,
+        })
+    }
 }
 
 pub fn cast_manager<T: 'static + Component>(
@@ -60,48 +239,182 @@ pub fn cast_manager_mut<T: 'static + Component>(
     manager.as_any_mut().downcast_mut::<ComponentManager<T>>()
 }
 
+impl<T: 'static + Component> Default for ComponentManager<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<T: 'static + Component> ComponentManager<T> {
     pub fn new() -> Self {
         ComponentManager {
-            components: Vec::new(),
-            entities_ids: Vec::new(),
-            entity_to_component_index: HashMap::new(),
+            storage: Arc::new(ComponentStorage::new()),
+            clone_component: T::cloner(),
+            format_component: T::formatter(),
         }
     }
 
+    // copy the storage if it is shared with a snapshot
+    fn storage_mut(&mut self) -> &mut ComponentStorage<T> {
+        if Arc::get_mut(&mut self.storage).is_none() {
+            // only clonable storages are shared, see `share`
+            let clone_component = self.clone_component.unwrap();
+            self.storage = Arc::new(ComponentStorage {
+                components: self
+                    .storage
+                    .components
+                    .iter()
+                    .map(clone_component)
+                    .collect(),
+                entities_ids: self.storage.entities_ids.clone(),
+                entity_to_component_index: self.storage.entity_to_component_index.clone(),
+                index: self.storage.index.as_ref().map(|index| index.box_clone()),
+                dirty: self.storage.dirty.clone(),
+                all_dirty: self.storage.all_dirty,
+            });
+        }
+
+        Arc::get_mut(&mut self.storage).unwrap()
+    }
+
     pub fn has(&self, entity_id: usize) -> bool {
-        self.entity_to_component_index.contains_key(&entity_id)
+        self.storage
+            .entity_to_component_index
+            .contains_key(&entity_id)
     }
 
+    /// Will panic if the component breaks a unique index, see `try_add`
     pub fn add(&mut self, entity_id: usize, component: T) {
+        if let Err(error) = self.try_add(entity_id, component) {
+            panic!("{}", error);
+        }
+    }
+
+    /// Add the component unless its key is already used in a unique index
+    /// Nothing is done if the entity already has a component
+    pub fn try_add(&mut self, entity_id: usize, component: T) -> Result<(), IndexError> {
         if self.has(entity_id) {
-            return;
+            return Ok(());
         }
 
-        self.components.push(component);
-        self.entities_ids.push(entity_id);
+        self.refresh_index()?;
+        let storage = self.storage_mut();
+        if let Some(index) = storage.index.as_mut() {
+            index.insert(entity_id, &component)?;
+        }
+        storage.components.push(component);
+        storage.entities_ids.push(entity_id);
 
-        let component_index = self.components.len() - 1;
-        self.entity_to_component_index
+        let component_index = storage.components.len() - 1;
+        storage
+            .entity_to_component_index
             .insert(entity_id, component_index);
+        Ok(())
+    }
+
+    /// Whether the component can be added to (or replace the one of) the entity
+    /// without breaking a unique index
+    pub fn check(&mut self, entity_id: usize, component: &T) -> Result<(), IndexError> {
+        self.refresh_index()?;
+        match self.storage.index.as_ref() {
+            Some(index) => index.check(entity_id, component),
+            None => Ok(()),
+        }
+    }
+
+    /// Whether the components of entities that are not in the manager yet
+    /// (moved from another world or spawned in a batch) can all be added without
+    /// breaking a unique index
+    pub fn check_new(&mut self, components: &[(usize, &T)]) -> Result<(), IndexError> {
+        self.refresh_index()?;
+        match self.storage.index.as_ref() {
+            Some(index) => index.check_new(components),
+            None => Ok(()),
+        }
+    }
+
+    /// Replace the component of an entity, or add it
+    /// Fails without changing anything if the new key is used by another entity
+    pub fn try_replace(&mut self, entity_id: usize, component: T) -> Result<(), IndexError> {
+        if !self.has(entity_id) {
+            return self.try_add(entity_id, component);
+        }
+
+        self.refresh_index()?;
+        let storage = self.storage_mut();
+        if let Some(index) = storage.index.as_mut() {
+            index.insert(entity_id, &component)?;
+        }
+        let component_index = storage.entity_to_component_index[&entity_id];
+        storage.components[component_index] = component;
+        Ok(())
+    }
+
+    /// Make room for `additional` more components
+    pub fn reserve(&mut self, additional: usize) {
+        let storage = self.storage_mut();
+        storage.components.reserve(additional);
+        storage.entities_ids.reserve(additional);
+        storage.entity_to_component_index.reserve(additional);
     }
 
     pub fn remove(&mut self, entity_id: usize) {
+        self.take(entity_id);
+    }
+
+    /// Remove the component of an entity and return it
+    pub fn take(&mut self, entity_id: usize) -> Option<T> {
         if !self.has(entity_id) {
-            return;
+            return None;
         }
 
-        let component_index = self.entity_to_component_index.remove(&entity_id).unwrap();
+        let storage = self.storage_mut();
+        let component_index = storage
+            .entity_to_component_index
+            .remove(&entity_id)
+            .unwrap();
         // give component_index place to the last entity
         // that way we can use swap_remove to remove the last element
-        self.entity_to_component_index
-            .insert(*self.entities_ids.last().unwrap(), component_index);
+        storage
+            .entity_to_component_index
+            .insert(*storage.entities_ids.last().unwrap(), component_index);
 
-        self.components.swap_remove(component_index);
-        self.entities_ids.swap_remove(component_index);
+        let component = storage.components.swap_remove(component_index);
+        storage.entities_ids.swap_remove(component_index);
 
         // remove the entity id from the map because it's not in the components anymore
-        self.entity_to_component_index.remove(&entity_id);
+        storage.entity_to_component_index.remove(&entity_id);
+        if let Some(index) = storage.index.as_mut() {
+            index.remove(entity_id);
+        }
+
+        Some(component)
+    }
+
+    pub fn clear(&mut self) {
+        match Arc::get_mut(&mut self.storage) {
+            Some(storage) => {
+                storage.components.clear();
+                storage.entities_ids.clear();
+                storage.entity_to_component_index.clear();
+                if let Some(index) = storage.index.as_mut() {
+                    index.clear();
+                }
+                storage.dirty.clear();
+                storage.all_dirty = false;
+            }
+            // no need to copy a shared storage to clear it
+            None => self.storage = Arc::new(ComponentStorage::new()),
+        }
+    }
+
+    /// Read the components by owner, the lookup can be shared between threads
+    /// (unlike the manager, its index is left out)
+    pub fn lookup(&self) -> ComponentLookup<'_, T> {
+        ComponentLookup {
+            components: &self.storage.components,
+            entity_to_component_index: &self.storage.entity_to_component_index,
+        }
     }
 
     pub fn borrow_component_for_entity(&self, entity_id: usize) -> Option<&T> {
@@ -109,8 +422,12 @@ impl<T: 'static + Component> ComponentManager<T> {
             return None;
         }
 
-        let component_index = self.entity_to_component_index.get(&entity_id).unwrap();
-        Some(&self.components[*component_index])
+        let component_index = self
+            .storage
+            .entity_to_component_index
+            .get(&entity_id)
+            .unwrap();
+        Some(&self.storage.components[*component_index])
     }
 
     pub fn borrow_component_mut(&mut self, entity_id: usize) -> Option<&mut T> {
@@ -118,15 +435,126 @@ impl<T: 'static + Component> ComponentManager<T> {
             return None;
         }
 
-        let component_index = self.entity_to_component_index.get(&entity_id).unwrap();
-        Some(&mut self.components[*component_index])
+        let storage = self.storage_mut();
+        // the list is bounded, once it is as long as the storage every component
+        // is indexed again
+        if storage.index.is_some() && !storage.all_dirty {
+            if storage.dirty.len() >= storage.components.len() {
+                storage.dirty.clear();
+                storage.all_dirty = true;
+            } else {
+                storage.dirty.push(entity_id);
+            }
+        }
+        let component_index = storage.entity_to_component_index.get(&entity_id).unwrap();
+        Some(&mut storage.components[*component_index])
     }
 
     pub fn borrow_components(&self) -> &Vec<T> {
-        &self.components
+        &self.storage.components
     }
 
     pub fn borrow_components_mut(&mut self) -> &mut Vec<T> {
-        &mut self.components
+        let storage = self.storage_mut();
+        storage.all_dirty = storage.index.is_some();
+        &mut storage.components
+    }
+
+    /// The owner of each component, in the same order as `borrow_components`
+    /// The order changes when a component is removed
+    pub fn entities(&self) -> &[usize] {
+        &self.storage.entities_ids
+    }
+
+    /// Iterate over the components along with their owner
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &T)> {
+        self.storage
+            .entities_ids
+            .iter()
+            .copied()
+            .zip(self.storage.components.iter())
+    }
+
+    /// The row of each entity and the components borrowed mutably,
+    /// to share the storage between threads
+    pub fn rows_and_components_mut(&mut self) -> (&HashMap<usize, usize>, &mut [T]) {
+        let storage = self.storage_mut();
+        storage.all_dirty = storage.index.is_some();
+        (&storage.entity_to_component_index, &mut storage.components)
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (usize, &mut T)> {
+        let storage = self.storage_mut();
+        storage.all_dirty = storage.index.is_some();
+        storage
+            .entities_ids
+            .iter()
+            .copied()
+            .zip(storage.components.iter_mut())
+    }
+
+    // index again the components borrowed mutably
+    // Fails without changing the index if a component modified in place breaks
+    // a unique index, the components stay dirty until they are fixed
+    fn refresh_index(&mut self) -> Result<(), IndexError> {
+        if !self.storage.all_dirty && self.storage.dirty.is_empty() {
+            return Ok(());
+        }
+
+        let storage = self.storage_mut();
+        let Some(index) = storage.index.as_mut() else {
+            return Ok(());
+        };
+        let reindexed: Vec<(usize, &T)> = if storage.all_dirty {
+            storage
+                .entities_ids
+                .iter()
+                .copied()
+                .zip(storage.components.iter())
+                .collect()
+        } else {
+            storage.dirty.sort_unstable();
+            storage.dirty.dedup();
+            storage
+                .dirty
+                .iter()
+                .filter_map(|entity_id| {
+                    let component_index = storage.entity_to_component_index.get(entity_id)?;
+                    Some((*entity_id, &storage.components[*component_index]))
+                })
+                .collect()
+        };
+        index.check_all(&reindexed)?;
+
+        if storage.all_dirty {
+            index.clear();
+        } else {
+            for (entity_id, _) in reindexed.iter() {
+                index.remove(*entity_id);
+            }
+        }
+        for (entity_id, component) in reindexed {
+            index
+                .insert(entity_id, component)
+                .expect("the keys were checked");
+        }
+        storage.dirty.clear();
+        storage.all_dirty = false;
+        Ok(())
+    }
+}
+
+impl<T: 'static + Indexed> ComponentManager<T> {
+    /// The index of the `#[component(index)]` field, up to date with the changes
+    /// made in place
+    /// Fails if a component modified in place breaks a unique index
+    pub fn index(&mut self) -> Result<&ComponentIndex<T::Key>, IndexError> {
+        self.refresh_index()?;
+        Ok(self
+            .storage
+            .index
+            .as_ref()
+            .and_then(|index| index.as_any().downcast_ref::<ComponentIndex<T::Key>>())
+            .unwrap())
     }
 }
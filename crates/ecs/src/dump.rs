@@ -0,0 +1,104 @@
+use std::fmt;
+
+use serde::Serialize;
+
+/// A report of the content of a world, see `World::dump`
+/// Printed as text with `Display` or as JSON with `to_json`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WorldDump {
+    pub entities: Vec<EntityDump>,
+    pub groups: Vec<GroupDump>,
+    /// The ids of the removed entities, reused by the next created entities
+    pub free_ids: Vec<usize>,
+    pub systems: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EntityDump {
+    pub id: usize,
+    pub generation: u32,
+    pub signature: u128,
+    /// Hidden from the queries, see `World::disable_entity`
+    pub disabled: bool,
+    pub components: Vec<ComponentDump>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ComponentDump {
+    pub name: String,
+    /// Only for the components marked with `#[component(debug)]`
+    pub value: Option<String>,
+}
+
+/// The entities sharing a signature in the `QueryManager`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GroupDump {
+    pub signature: u128,
+    pub components: Vec<String>,
+    pub entities: Vec<usize>,
+}
+
+impl WorldDump {
+    pub fn entity(&self, entity_id: usize) -> Option<&EntityDump> {
+        self.entities.iter().find(|entity| entity.id == entity_id)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+}
+
+impl EntityDump {
+    pub fn component(&self, name: &str) -> Option<&ComponentDump> {
+        self.components
+            .iter()
+            .find(|component| component.name == name)
+    }
+}
+
+impl fmt::Display for WorldDump {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "entities ({}):", self.entities.len())?;
+        for entity in self.entities.iter() {
+            let names: Vec<&str> = entity
+                .components
+                .iter()
+                .map(|component| component.name.as_str())
+                .collect();
+            writeln!(
+                f,
+                "  #{} (generation {}{}) {:#b} [{}]",
+                entity.id,
+                entity.generation,
+                if entity.disabled { ", disabled" } else { "" },
+                entity.signature,
+                names.join(", ")
+            )?;
+            for component in entity.components.iter() {
+                match &component.value {
+                    Some(value) => writeln!(f, "    {}: {}", component.name, value)?,
+                    None => writeln!(f, "    {}: ..", component.name)?,
+                }
+            }
+        }
+
+        writeln!(f, "groups ({}):", self.groups.len())?;
+        for group in self.groups.iter() {
+            writeln!(
+                f,
+                "  {:#b} [{}]: {:?}",
+                group.signature,
+                group.components.join(", "),
+                group.entities
+            )?;
+        }
+
+        writeln!(f, "free ids: {:?}", self.free_ids)?;
+
+        writeln!(f, "systems ({}):", self.systems.len())?;
+        for system in self.systems.iter() {
+            writeln!(f, "  {}", system)?;
+        }
+        Ok(())
+    }
+}
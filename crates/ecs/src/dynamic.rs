@@ -0,0 +1,316 @@
+use std::fmt;
+use std::hash::Hasher;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Number};
+
+use crate::component::Component;
+use crate::reflect::{Reflect, ReflectError, TypeInfo, Value};
+
+/// Identify a component defined at runtime, see `EntityManager::register_dynamic_component`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ComponentId(pub(crate) u32);
+
+/// The type of a field of a dynamic component
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FieldKind {
+    Bool,
+    Int,
+    UInt,
+    Float,
+    String,
+}
+
+impl FieldKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            FieldKind::Bool => "bool",
+            FieldKind::Int => "int",
+            FieldKind::UInt => "uint",
+            FieldKind::Float => "float",
+            FieldKind::String => "string",
+        }
+    }
+
+    pub fn default_value(&self) -> Value {
+        match self {
+            FieldKind::Bool => Value::Bool(false),
+            FieldKind::Int => Value::Int(0),
+            FieldKind::UInt => Value::UInt(0),
+            FieldKind::Float => Value::Float(0.0),
+            FieldKind::String => Value::String(String::new()),
+        }
+    }
+
+    fn of(value: &Value) -> Self {
+        match value {
+            Value::Bool(_) => FieldKind::Bool,
+            Value::Int(_) => FieldKind::Int,
+            Value::UInt(_) => FieldKind::UInt,
+            Value::Float(_) => FieldKind::Float,
+            Value::String(_) => FieldKind::String,
+        }
+    }
+
+    /// Convert a value to this kind, integers are accepted by float fields
+    pub fn convert(&self, value: Value) -> Result<Value, ReflectError> {
+        let converted = match (self, &value) {
+            (FieldKind::Bool, Value::Bool(_)) | (FieldKind::String, Value::String(_)) => {
+                return Ok(value);
+            }
+            (FieldKind::Int, Value::Int(v)) => Some(Value::Int(*v)),
+            (FieldKind::Int, Value::UInt(v)) => i64::try_from(*v).ok().map(Value::Int),
+            (FieldKind::UInt, Value::UInt(v)) => Some(Value::UInt(*v)),
+            (FieldKind::UInt, Value::Int(v)) => u64::try_from(*v).ok().map(Value::UInt),
+            (FieldKind::Float, Value::Float(v)) => Some(Value::Float(*v)),
+            (FieldKind::Float, Value::Int(v)) => Some(Value::Float(*v as f64)),
+            (FieldKind::Float, Value::UInt(v)) => Some(Value::Float(*v as f64)),
+            _ => None,
+        };
+        converted.ok_or(ReflectError::TypeMismatch {
+            expected: self.name(),
+            found: value,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DynamicField {
+    pub name: String,
+    pub kind: FieldKind,
+}
+
+/// The schema of a component defined at runtime (by a mod or a script)
+/// ```json
+/// { "name": "Overclock", "fields": [{ "name": "speed", "kind": "float" }] }
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DynamicComponentInfo {
+    pub name: String,
+    #[serde(default)]
+    pub fields: Vec<DynamicField>,
+}
+
+impl DynamicComponentInfo {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            fields: Vec::new(),
+        }
+    }
+
+    pub fn with_field(mut self, name: &str, kind: FieldKind) -> Self {
+        self.fields.push(DynamicField {
+            name: name.to_string(),
+            kind,
+        });
+        self
+    }
+
+    pub fn field_index(&self, name: &str) -> Option<usize> {
+        self.fields.iter().position(|field| field.name == name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DynamicError {
+    /// A static or dynamic component already has this name
+    DuplicateName(String),
+}
+
+impl fmt::Display for DynamicError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DynamicError::DuplicateName(name) => {
+                write!(f, "component {} is already registered", name)
+            }
+        }
+    }
+}
+
+impl std::error::Error for DynamicError {}
+
+/// An instance of a dynamic component, its fields are primitive `Value`s
+/// Created with default values by `EntityManager::new_dynamic_component`
+#[derive(Debug, Clone)]
+pub struct DynamicComponent {
+    id: ComponentId,
+    info: Arc<DynamicComponentInfo>,
+    values: Vec<Value>,
+}
+
+impl PartialEq for DynamicComponent {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id && self.values == other.values
+    }
+}
+
+impl DynamicComponent {
+    pub(crate) fn new(id: ComponentId, info: Arc<DynamicComponentInfo>) -> Self {
+        let values = info
+            .fields
+            .iter()
+            .map(|field| field.kind.default_value())
+            .collect();
+        Self { id, info, values }
+    }
+
+    // the same values with the id of the component in another world
+    pub(crate) fn rebind(self, id: ComponentId, info: Arc<DynamicComponentInfo>) -> Self {
+        Self {
+            id,
+            info,
+            values: self.values,
+        }
+    }
+
+    pub fn id(&self) -> ComponentId {
+        self.id
+    }
+
+    pub fn info(&self) -> &DynamicComponentInfo {
+        &self.info
+    }
+
+    pub fn get(&self, field: &str) -> Option<&Value> {
+        self.values.get(self.info.field_index(field)?)
+    }
+
+    pub fn set(&mut self, field: &str, value: Value) -> Result<(), ReflectError> {
+        let index = self
+            .info
+            .field_index(field)
+            .ok_or_else(|| ReflectError::FieldNotFound(field.to_string()))?;
+        self.values[index] = self.info.fields[index].kind.convert(value)?;
+        Ok(())
+    }
+
+    pub fn with(mut self, field: &str, value: Value) -> Result<Self, ReflectError> {
+        self.set(field, value)?;
+        Ok(self)
+    }
+
+    /// The fields as a JSON object, used by the saves
+    /// Whether every float field can be saved, JSON writes NaN and the infinities as null
+    pub(crate) fn check_finite(&self) -> Result<(), String> {
+        for (field, value) in self.info.fields.iter().zip(self.values.iter()) {
+            match value {
+                Value::Float(v) if !v.is_finite() => {
+                    return Err(format!("{} can not be saved in field {}", v, field.name));
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn to_json(&self) -> serde_json::Value {
+        let mut object = Map::new();
+        for (field, value) in self.info.fields.iter().zip(self.values.iter()) {
+            let value = match value {
+                Value::Bool(v) => serde_json::Value::Bool(*v),
+                Value::Int(v) => serde_json::Value::from(*v),
+                Value::UInt(v) => serde_json::Value::from(*v),
+                Value::Float(v) => Number::from_f64(*v)
+                    .map(serde_json::Value::Number)
+                    .unwrap_or(serde_json::Value::Null),
+                Value::String(v) => serde_json::Value::String(v.clone()),
+            };
+            object.insert(field.name.clone(), value);
+        }
+        serde_json::Value::Object(object)
+    }
+
+    /// Read the fields of a JSON object, missing fields keep their default value
+    pub(crate) fn from_json(
+        id: ComponentId,
+        info: Arc<DynamicComponentInfo>,
+        json: serde_json::Value,
+    ) -> Result<Self, String> {
+        let mut component = Self::new(id, info);
+        let serde_json::Value::Object(object) = json else {
+            return Err(format!("expected an object, found {}", json));
+        };
+        for (name, json) in object {
+            let value = match json {
+                serde_json::Value::Bool(v) => Value::Bool(v),
+                serde_json::Value::String(v) => Value::String(v),
+                serde_json::Value::Number(v) => match (v.as_u64(), v.as_i64(), v.as_f64()) {
+                    (Some(v), _, _) => Value::UInt(v),
+                    (_, Some(v), _) => Value::Int(v),
+                    (_, _, Some(v)) => Value::Float(v),
+                    _ => return Err(format!("invalid number for field {}", name)),
+                },
+                json => return Err(format!("invalid value for field {}: {}", name, json)),
+            };
+            component
+                .set(&name, value)
+                .map_err(|error| error.to_string())?;
+        }
+        Ok(component)
+    }
+}
+
+impl Component for DynamicComponent {
+    fn cloner() -> Option<fn(&Self) -> Self> {
+        Some(<Self as Clone>::clone)
+    }
+
+    fn formatter() -> Option<fn(&Self) -> String> {
+        Some(|component| component.to_json().to_string())
+    }
+}
+
+impl Reflect for DynamicComponent {
+    fn type_info() -> TypeInfo {
+        TypeInfo::of::<Self>("DynamicComponent", Vec::new())
+    }
+
+    fn field(&self, name: &str) -> Option<&dyn Reflect> {
+        self.values
+            .get(self.info.field_index(name)?)
+            .map(|value| value as &dyn Reflect)
+    }
+
+    fn field_mut(&mut self, name: &str) -> Option<&mut dyn Reflect> {
+        let index = self.info.field_index(name)?;
+        self.values
+            .get_mut(index)
+            .map(|value| value as &mut dyn Reflect)
+    }
+
+    fn reflect_hash(&self, state: &mut dyn Hasher) {
+        for value in self.values.iter() {
+            value.reflect_hash(state);
+        }
+    }
+}
+
+// a field of a dynamic component, its kind is the kind of its current value
+impl Reflect for Value {
+    fn type_info() -> TypeInfo {
+        TypeInfo::of::<Self>("Value", Vec::new())
+    }
+
+    fn get_value(&self) -> Option<Value> {
+        Some(self.clone())
+    }
+
+    fn set_value(&mut self, value: Value) -> Result<(), ReflectError> {
+        *self = FieldKind::of(self).convert(value)?;
+        Ok(())
+    }
+
+    // hashed like the primitive types
+    fn reflect_hash(&self, state: &mut dyn Hasher) {
+        match self {
+            Value::Bool(v) => v.reflect_hash(state),
+            Value::Int(v) => v.reflect_hash(state),
+            Value::UInt(v) => v.reflect_hash(state),
+            Value::Float(v) => v.reflect_hash(state),
+            Value::String(v) => v.reflect_hash(state),
+        }
+    }
+}
@@ -1,21 +1,59 @@
+#[derive(Clone)]
 pub struct Entity {
     is_alive: bool,
+    // incremented every time the id is reused
+    generation: u32,
+    // hidden from the queries, see `EntityManager::disable_entity`
+    disabled: bool,
+}
+
+impl Default for Entity {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Entity {
     pub fn new() -> Self {
-        Self { is_alive: true }
+        Self {
+            is_alive: true,
+            generation: 0,
+            disabled: false,
+        }
+    }
+
+    pub fn from_parts(is_alive: bool, generation: u32, disabled: bool) -> Self {
+        Self {
+            is_alive,
+            generation,
+            disabled,
+        }
     }
 
     pub fn is_alive(&self) -> bool {
         self.is_alive
     }
 
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+
+    pub fn is_disabled(&self) -> bool {
+        self.disabled
+    }
+
+    pub fn set_disabled(&mut self, disabled: bool) {
+        self.disabled = disabled;
+    }
+
     pub fn kill(&mut self) {
         self.is_alive = false;
+        self.disabled = false;
     }
 
     pub fn reset(&mut self) {
         self.is_alive = true;
+        self.disabled = false;
+        self.generation = self.generation.wrapping_add(1);
     }
 }
@@ -1,65 +1,801 @@
-use crate::component::Component;
+use crate::bundle::Bundle;
+use crate::component::{Component, ComponentHook};
 use crate::component_manager::{
     ComponentManager, ComponentManagerTrait, cast_manager, cast_manager_mut,
 };
+use crate::dump::{ComponentDump, EntityDump, GroupDump, WorldDump};
+use crate::dynamic::{ComponentId, DynamicComponent, DynamicComponentInfo, DynamicError};
 use crate::entity::Entity;
+use crate::index::{ComponentIndex, IndexError, Indexed};
+use crate::migration::Migrations;
+use crate::prefab::{PrefabError, Prefabs};
+use crate::profiler::QueryStats;
 use crate::query_manager::QueryManager;
-use std::any::TypeId;
-use std::collections::HashMap;
-use std::mem::transmute;
+use crate::random::Random;
+use crate::reflect::{Reflect, TypeInfo};
+use crate::resource::{Resource, Resources};
+use crate::serialization::{ComponentData, EntityData, SAVE_FORMAT_VERSION, SaveData, SaveError};
+use crate::snapshot::{Snapshot, SnapshotError};
+use crate::state_hash::{StateHash, StateHasher};
+use crate::transfer::{
+    CloneError, EntityBundle, EntityMap, EntityMapper, NO_ENTITY, TransferError,
+};
+use crate::type_registry::{RegisterError, TypeRegistry};
+use std::any::{Any, TypeId};
+use std::cell::Cell;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::hash::Hasher;
+use std::sync::Arc;
+use std::time::Instant;
 
 pub struct EntityManager {
-    entities: Entities,
+    // entities and query_manager are shared with the snapshots, copied on write
+    entities: Arc<Entities>,
     components_managers: HashMap<TypeId, Box<dyn ComponentManagerTrait>>,
-    query_manager: QueryManager,
+    // the storages of the components defined at runtime, see `register_dynamic_component`
+    dynamic_managers: HashMap<ComponentId, Box<dyn ComponentManagerTrait>>,
+    // the zero-sized components have no storage, they are only a bit of the signatures
+    tags: HashSet<TypeId>,
+    // the components with an `on_add` or `on_remove` hook
+    hooks: HashMap<TypeId, ComponentHooks>,
+    query_manager: Arc<QueryManager>,
+    type_registry: TypeRegistry,
+    resources: Resources,
+    migrations: Migrations,
+    // the cost of the queries, recorded while the world profiler is enabled
+    profile_queries: bool,
+    query_stats: Cell<QueryStats>,
+}
+
+impl Default for EntityManager {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl EntityManager {
     pub fn new() -> Self {
         EntityManager {
-            entities: Entities::new(),
+            entities: Arc::new(Entities::new()),
             components_managers: HashMap::new(),
-            query_manager: QueryManager::new(),
+            dynamic_managers: HashMap::new(),
+            tags: HashSet::new(),
+            hooks: HashMap::new(),
+            query_manager: Arc::new(QueryManager::new()),
+            type_registry: TypeRegistry::new(),
+            resources: Resources::new(),
+            migrations: Migrations::new(),
+            profile_queries: false,
+            query_stats: Cell::new(QueryStats::default()),
         }
     }
 
     pub fn create_entity(&mut self) -> usize {
-        self.entities.create()
+        self.entities_mut().create()
+    }
+
+    /// Remove an entity and all its components, its id will be reused
+    /// The `Random` streams of the entity are dropped
+    pub fn remove_entity(&mut self, entity_id: usize) {
+        if !self.entities.has(entity_id) {
+            return;
+        }
+
+        self.run_hooks(entity_id, Hook::Remove);
+        for manager in self.all_managers_mut() {
+            manager.remove(entity_id);
+        }
+        self.query_manager_mut().remove_entity(entity_id);
+        self.entities_mut().remove(entity_id);
+        if let Some(random) = self.resources.borrow_mut::<Random>() {
+            random.forget_entity(entity_id);
+        }
+    }
+
+    /// Create an entity for each bundle of components
+    /// Ids are assigned in bulk and the query signatures are updated once per batch,
+    /// much faster than `create_entity` + `add_component_to_entity` for large batches
+    /// Will panic if a component is not registered or breaks a unique index,
+    /// see `try_spawn_batch`
+    pub fn spawn_batch<B, I>(&mut self, bundles: I) -> Vec<usize>
+    where
+        B: Bundle,
+        I: IntoIterator<Item = B>,
+    {
+        match self.try_spawn_batch(bundles) {
+            Ok(entities_ids) => entities_ids,
+            Err(error) => panic!("{}", error),
+        }
+    }
+
+    /// Create an entity for each bundle of components, see `spawn_batch`
+    /// Nothing is created if a component uses a key already used in a unique index
+    /// (by an entity or by another bundle of the batch)
+    /// Will panic if a component is not registered
+    pub fn try_spawn_batch<B, I>(&mut self, bundles: I) -> Result<Vec<usize>, IndexError>
+    where
+        B: Bundle,
+        I: IntoIterator<Item = B>,
+    {
+        let bundles: Vec<B> = bundles.into_iter().collect();
+        let bitmask = B::bitmask(&self.query_manager);
+        // the keys are checked with the ids the entities will get
+        let next_ids = self.entities.next_batch(bundles.len());
+        B::check(&bundles, &next_ids, &mut self.components_managers)?;
+        B::reserve(&mut self.components_managers, bundles.len());
+
+        let entities_ids = self.entities_mut().create_batch(bundles.len());
+        for (entity_id, bundle) in entities_ids.iter().zip(bundles) {
+            bundle.add(*entity_id, &mut self.components_managers);
+        }
+        self.query_manager_mut()
+            .add_entities(&entities_ids, bitmask);
+        for entity_id in entities_ids.iter() {
+            self.run_hooks(*entity_id, Hook::Add);
+        }
+
+        Ok(entities_ids)
+    }
+
+    /// Remove several entities and all their components, see `remove_entity`
+    pub fn despawn_batch(&mut self, entities_ids: &[usize]) {
+        let entities_ids: Vec<usize> = entities_ids
+            .iter()
+            .copied()
+            .filter(|entity_id| self.entities.has(*entity_id))
+            .collect();
+
+        for entity_id in entities_ids.iter() {
+            self.run_hooks(*entity_id, Hook::Remove);
+        }
+        for manager in self.all_managers_mut() {
+            for entity_id in entities_ids.iter() {
+                manager.remove(*entity_id);
+            }
+        }
+        self.query_manager_mut().remove_entities(&entities_ids);
+        let entities = self.entities_mut();
+        for entity_id in entities_ids.iter() {
+            entities.remove(*entity_id);
+        }
+        if let Some(random) = self.resources.borrow_mut::<Random>() {
+            for entity_id in entities_ids {
+                random.forget_entity(entity_id);
+            }
+        }
+    }
+
+    /// Remove an entity and return all its components
+    pub fn take_entity(&mut self, entity_id: usize) -> Option<EntityBundle> {
+        if !self.entities.has(entity_id) {
+            return None;
+        }
+
+        self.run_hooks(entity_id, Hook::Remove);
+        let mut components = Vec::new();
+        for (type_id, manager) in self.components_managers.iter_mut() {
+            if let Some(component) = manager.take_any(entity_id) {
+                let name = self.type_registry.get(*type_id).unwrap().name;
+                components.push((*type_id, name, component));
+            }
+        }
+        let mut dynamic_components = Vec::new();
+        for manager in self.dynamic_managers.values_mut() {
+            if let Some(component) = manager.take_any(entity_id) {
+                dynamic_components.push(*component.downcast::<DynamicComponent>().unwrap());
+            }
+        }
+        let tags = self
+            .tags_of(entity_id)
+            .map(|type_id| (type_id, self.type_registry.get(type_id).unwrap().name))
+            .collect();
+        let disabled = self.entities.is_disabled(entity_id);
+        self.query_manager_mut().remove_entity(entity_id);
+        self.entities_mut().remove(entity_id);
+        if let Some(random) = self.resources.borrow_mut::<Random>() {
+            random.forget_entity(entity_id);
+        }
+
+        Some(EntityBundle {
+            components,
+            dynamic_components,
+            tags,
+            disabled,
+        })
+    }
+
+    /// Create an entity with the components of a bundle taken from another manager
+    /// The relations of the components are not remapped, see `move_entities`
+    /// Nothing is created if the target has not registered a component of the bundle
+    /// or if a component uses a key already used in a unique index
+    pub fn spawn_entity_bundle(&mut self, bundle: EntityBundle) -> Result<usize, TransferError> {
+        self.check_registered(&bundle)?;
+        for (type_id, _, component) in bundle.components.iter() {
+            self.components_managers
+                .get_mut(type_id)
+                .unwrap()
+                .check_new_any(&[(NO_ENTITY, component.as_ref())])
+                .map_err(TransferError::Index)?;
+        }
+        Ok(self.spawn_entity_bundle_unchecked(bundle))
+    }
+
+    /// Move entities with all their components to another manager
+    /// Returns the new id of each entity. The relations (`#[component(entity)]`
+    /// fields) between the moved entities are remapped to the new ids, the
+    /// relations to the entities left behind are set to `NO_ENTITY`.
+    /// Nothing is moved if an entity does not exist, if the target has not
+    /// registered one of the components or if a component uses a key already
+    /// used in a unique index of the target.
+    pub fn move_entities(
+        &mut self,
+        target: &mut EntityManager,
+        entities_ids: &[usize],
+    ) -> Result<EntityMap, TransferError> {
+        for entity_id in entities_ids {
+            if !self.entities.has(*entity_id) {
+                return Err(TransferError::UnknownEntity(*entity_id));
+            }
+        }
+        for (type_id, manager) in self.components_managers.iter() {
+            let components: Vec<(usize, &dyn Any)> = entities_ids
+                .iter()
+                .filter_map(|entity_id| {
+                    let component = manager.reflect(*entity_id)?;
+                    Some((*entity_id, component as &dyn Any))
+                })
+                .collect();
+            if components.is_empty() {
+                continue;
+            }
+            let Some(target_manager) = target.components_managers.get_mut(type_id) else {
+                let name = self.type_registry.get(*type_id).unwrap().name;
+                return Err(TransferError::UnregisteredComponent(name));
+            };
+            // the keys of the unique indexes must be free in the target
+            target_manager
+                .check_new_any(&components)
+                .map_err(TransferError::Index)?;
+        }
+        for type_id in self.tags.iter() {
+            let used = entities_ids
+                .iter()
+                .any(|entity_id| self.tags_of(*entity_id).any(|tag| tag == *type_id));
+            if used && !target.tags.contains(type_id) {
+                let name = self.type_registry.get(*type_id).unwrap().name;
+                return Err(TransferError::UnregisteredComponent(name));
+            }
+        }
+        for (id, manager) in self.dynamic_managers.iter() {
+            let used = entities_ids.iter().any(|entity_id| manager.has(*entity_id));
+            let info = self.type_registry.get_dynamic(*id).unwrap();
+            if used && target.find_dynamic_component(info).is_none() {
+                return Err(TransferError::UnregisteredDynamicComponent(
+                    info.name.clone(),
+                ));
+            }
+        }
+
+        let mut entity_map = EntityMap::new();
+        let mut new_ids = Vec::new();
+        let mut moved_types = Vec::new();
+        for entity_id in entities_ids {
+            // listed twice, already moved
+            let Some(bundle) = self.take_entity(*entity_id) else {
+                continue;
+            };
+            moved_types.extend(bundle.components.iter().map(|(type_id, _, _)| *type_id));
+            let new_id = target.insert_entity_bundle(bundle);
+            entity_map.insert(*entity_id, new_id);
+            new_ids.push(new_id);
+        }
+
+        moved_types.sort();
+        moved_types.dedup();
+        let mapper = EntityMapper::clear_unmapped(&entity_map);
+        for type_id in moved_types {
+            let manager = target.components_managers.get_mut(&type_id).unwrap();
+            for new_id in entity_map.values() {
+                manager.map_entities(*new_id, &mapper);
+            }
+        }
+        // the hooks see the relations of the target
+        for new_id in new_ids {
+            target.run_hooks(new_id, Hook::Add);
+        }
+
+        Ok(entity_map)
+    }
+
+    /// Copy entities with all their components, returns the id of each copy
+    /// The relations between the copied entities (an entity to itself included)
+    /// are remapped to the copies, the relations to other entities are kept.
+    /// Nothing is copied if an entity does not exist or has a component that
+    /// cannot be copied.
+    pub fn clone_entities(&mut self, entities_ids: &[usize]) -> Result<EntityMap, CloneError> {
+        for entity_id in entities_ids {
+            if !self.entities.has(*entity_id) {
+                return Err(CloneError::UnknownEntity(*entity_id));
+            }
+        }
+        for (type_id, manager) in self.components_managers.iter() {
+            if !entities_ids.iter().any(|entity_id| manager.has(*entity_id)) {
+                continue;
+            }
+            let name = self.type_registry.get(*type_id).unwrap().name;
+            if !manager.is_clonable() {
+                return Err(CloneError::NotClonable(name));
+            }
+            if manager.has_unique_index() {
+                return Err(CloneError::UniqueIndex(name));
+            }
+        }
+
+        let mut entity_map = EntityMap::new();
+        let mut new_ids = Vec::new();
+        let mut cloned_types = Vec::new();
+        for entity_id in entities_ids {
+            // listed twice, already cloned
+            if entity_map.contains_key(entity_id) {
+                continue;
+            }
+            let bundle = self.clone_entity_bundle(*entity_id);
+            cloned_types.extend(bundle.components.iter().map(|(type_id, _, _)| *type_id));
+            let new_id = self.insert_entity_bundle(bundle);
+            entity_map.insert(*entity_id, new_id);
+            new_ids.push(new_id);
+        }
+
+        cloned_types.sort();
+        cloned_types.dedup();
+        let mapper = EntityMapper::keep_unmapped(&entity_map);
+        for type_id in cloned_types {
+            let manager = self.components_managers.get_mut(&type_id).unwrap();
+            for new_id in entity_map.values() {
+                manager.map_entities(*new_id, &mapper);
+            }
+        }
+        // the hooks see the relations to the copies
+        for new_id in new_ids {
+            self.run_hooks(new_id, Hook::Add);
+        }
+
+        Ok(entity_map)
+    }
+
+    // the components of the entity must be clonable
+    fn clone_entity_bundle(&self, entity_id: usize) -> EntityBundle {
+        let components = self
+            .components_managers
+            .iter()
+            .filter_map(|(type_id, manager)| {
+                let component = manager.clone_any(entity_id)?;
+                let name = self.type_registry.get(*type_id).unwrap().name;
+                Some((*type_id, name, component))
+            })
+            .collect();
+        let dynamic_components = self
+            .dynamic_managers
+            .values()
+            .filter_map(|manager| manager.clone_any(entity_id))
+            .map(|component| *component.downcast::<DynamicComponent>().unwrap())
+            .collect();
+        let tags = self
+            .tags_of(entity_id)
+            .map(|type_id| (type_id, self.type_registry.get(type_id).unwrap().name))
+            .collect();
+
+        EntityBundle {
+            components,
+            dynamic_components,
+            tags,
+            disabled: self.entities.is_disabled(entity_id),
+        }
+    }
+
+    fn check_registered(&self, bundle: &EntityBundle) -> Result<(), TransferError> {
+        for (type_id, name, _) in bundle.components.iter() {
+            if !self.components_managers.contains_key(type_id) {
+                return Err(TransferError::UnregisteredComponent(name));
+            }
+        }
+        for (type_id, name) in bundle.tags.iter() {
+            if !self.tags.contains(type_id) {
+                return Err(TransferError::UnregisteredComponent(name));
+            }
+        }
+        for component in bundle.dynamic_components.iter() {
+            if self.find_dynamic_component(component.info()).is_none() {
+                return Err(TransferError::UnregisteredDynamicComponent(
+                    component.info().name.clone(),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    // the id of a dynamic component registered with the same schema
+    fn find_dynamic_component(&self, info: &DynamicComponentInfo) -> Option<ComponentId> {
+        self.type_registry
+            .get_dynamic_by_name(&info.name)
+            .filter(|(_, registered)| registered.as_ref() == info)
+            .map(|(id, _)| id)
+    }
+
+    // the components of the bundle must be registered
+    fn spawn_entity_bundle_unchecked(&mut self, bundle: EntityBundle) -> usize {
+        let entity_id = self.insert_entity_bundle(bundle);
+        self.run_hooks(entity_id, Hook::Add);
+
+        entity_id
+    }
+
+    // without running the hooks, for the moved and cloned entities whose relations
+    // are remapped before the hooks see them
+    fn insert_entity_bundle(&mut self, bundle: EntityBundle) -> usize {
+        let entity_id = self.create_entity();
+        let mut bitmask = 0;
+        for (type_id, _, component) in bundle.components {
+            bitmask |= *self.query_manager.get_bit_for_type_id(type_id).unwrap();
+            self.components_managers
+                .get_mut(&type_id)
+                .unwrap()
+                .add_any(entity_id, component);
+        }
+        for (type_id, _) in bundle.tags {
+            bitmask |= *self.query_manager.get_bit_for_type_id(type_id).unwrap();
+        }
+        for component in bundle.dynamic_components {
+            let id = self.find_dynamic_component(component.info()).unwrap();
+            let info = self.type_registry.get_dynamic(id).unwrap().clone();
+            bitmask |= *self
+                .query_manager
+                .get_bit_for_dynamic_component(id)
+                .unwrap();
+            self.dynamic_manager_mut(id)
+                .add(entity_id, component.rebind(id, info));
+        }
+        if bitmask != 0 {
+            self.query_manager_mut().add_entity(entity_id, bitmask);
+        }
+        if bundle.disabled {
+            self.entities_mut().set_disabled(entity_id, true);
+        }
+
+        entity_id
+    }
+
+    pub fn is_alive(&self, entity_id: usize) -> bool {
+        self.entities.has(entity_id)
+    }
+
+    /// Hide an entity from the queries, it keeps its components
+    /// (ghost buildings, items in an inventory, chunks not simulated)
+    pub fn disable_entity(&mut self, entity_id: usize) -> &mut Self {
+        if self.entities.has(entity_id) {
+            self.entities_mut().set_disabled(entity_id, true);
+        }
+        self
+    }
+
+    pub fn enable_entity(&mut self, entity_id: usize) -> &mut Self {
+        if self.entities.has(entity_id) {
+            self.entities_mut().set_disabled(entity_id, false);
+        }
+        self
+    }
+
+    pub fn is_disabled(&self, entity_id: usize) -> bool {
+        self.entities.is_disabled(entity_id)
+    }
+
+    /// The disabled entities, by id
+    pub fn disabled_entities(&self) -> Vec<usize> {
+        (0..self.entities.entities.len())
+            .filter(|entity_id| self.entities.is_disabled(*entity_id))
+            .collect()
     }
 
+    /// The number of times the id of this entity has been reused
+    pub fn entity_generation(&self, entity_id: usize) -> Option<u32> {
+        self.entities
+            .entities
+            .get(entity_id)
+            .map(|entity| entity.generation())
+    }
+
+    /// Zero-sized components (`Powered`, `Selected`...) are registered as tags:
+    /// they have no storage, adding or removing them only changes the entity signature
+    /// Will panic if another component has the same name, see `try_register_component`
     pub fn register_component<T: 'static + Component>(&mut self) -> &mut Self {
-        if !self.has_component_manager::<T>() {
+        if let Err(error) = self.try_register_component::<T>() {
+            panic!("{}", error);
+        }
+        self
+    }
+
+    /// Register a component unless another component type has the same name
+    /// (the name identifies the component in the saves)
+    pub fn try_register_component<T: 'static + Component>(
+        &mut self,
+    ) -> Result<&mut Self, RegisterError> {
+        if !self.is_registered::<T>() {
             let type_id = TypeId::of::<T>();
-            self.query_manager.register_component::<T>();
-            self.components_managers
-                .insert(type_id, Box::new(ComponentManager::<T>::new()));
+            self.type_registry.register::<T>()?;
+            self.query_manager_mut().register_component::<T>();
+            if let Some(serializer) = T::serializer() {
+                self.type_registry.register_serializer::<T>(serializer);
+            }
+            if T::on_add().is_some() || T::on_remove().is_some() {
+                self.hooks.insert(
+                    type_id,
+                    ComponentHooks {
+                        on_add: T::on_add(),
+                        on_remove: T::on_remove(),
+                    },
+                );
+            }
+            if std::mem::size_of::<T>() == 0 {
+                self.tags.insert(type_id);
+            } else {
+                self.components_managers
+                    .insert(type_id, Box::new(ComponentManager::<T>::new()));
+            }
+        }
+
+        Ok(self)
+    }
+
+    pub fn is_tag<T: 'static + Component>(&self) -> bool {
+        self.tags.contains(&TypeId::of::<T>())
+    }
+
+    /// Unregister a component type: its storage is dropped and its bit is
+    /// removed from every entity signature, so it can be reused by the next
+    /// registered component
+    pub fn unregister_component<T: 'static + Component>(&mut self) -> &mut Self {
+        if self.is_registered::<T>() {
+            let type_id = TypeId::of::<T>();
+            if let Some(on_remove) = T::on_remove() {
+                let bit = *self.query_manager.get_bit_for_component::<T>().unwrap();
+                for entity_id in self.query_manager.query(bit).unwrap_or_default() {
+                    on_remove(self, entity_id);
+                }
+            }
+            self.hooks.remove(&type_id);
+            self.query_manager_mut().unregister_component::<T>();
+            self.type_registry.unregister::<T>();
+            self.components_managers.remove(&type_id);
+            self.tags.remove(&type_id);
         }
 
         self
     }
 
+    /// Register a component defined at runtime (by a mod or a script)
+    /// It is stored and queried like the static components
+    pub fn register_dynamic_component(
+        &mut self,
+        info: DynamicComponentInfo,
+    ) -> Result<ComponentId, DynamicError> {
+        let id = self.type_registry.register_dynamic(info)?;
+        self.query_manager_mut().register_dynamic_component(id);
+        self.dynamic_managers
+            .insert(id, Box::new(ComponentManager::<DynamicComponent>::new()));
+        Ok(id)
+    }
+
+    pub fn unregister_dynamic_component(&mut self, id: ComponentId) -> &mut Self {
+        if self.dynamic_managers.remove(&id).is_some() {
+            self.query_manager_mut().unregister_dynamic_component(id);
+            self.type_registry.unregister_dynamic(id);
+        }
+
+        self
+    }
+
+    pub fn dynamic_component_id(&self, name: &str) -> Option<ComponentId> {
+        self.type_registry
+            .get_dynamic_by_name(name)
+            .map(|(id, _)| id)
+    }
+
+    /// A dynamic component with the default value of each field
+    pub fn new_dynamic_component(&self, id: ComponentId) -> Option<DynamicComponent> {
+        let info = self.type_registry.get_dynamic(id)?;
+        Some(DynamicComponent::new(id, info.clone()))
+    }
+
+    /// Will panic if the component is not registered
+    pub fn add_dynamic_component_to_entity(
+        &mut self,
+        entity_id: usize,
+        component: DynamicComponent,
+    ) -> &mut Self {
+        let id = component.id();
+        let Some(bit) = self.query_manager.get_bit_for_dynamic_component(id) else {
+            panic!(
+                "Dynamic component not registered: {}",
+                component.info().name
+            );
+        };
+
+        let bitmask = self.query_manager.get_bitmask_for_entity(entity_id) | bit;
+        self.query_manager_mut().remove_entity(entity_id);
+        self.query_manager_mut().add_entity(entity_id, bitmask);
+        self.dynamic_manager_mut(id).add(entity_id, component);
+
+        self
+    }
+
+    pub fn borrow_dynamic_component_for_entity(
+        &self,
+        entity_id: usize,
+        id: ComponentId,
+    ) -> Option<&DynamicComponent> {
+        let manager = self.dynamic_managers.get(&id)?;
+        cast_manager::<DynamicComponent>(manager.as_ref())
+            .unwrap()
+            .borrow_component_for_entity(entity_id)
+    }
+
+    pub fn borrow_dynamic_component_mut_for_entity(
+        &mut self,
+        entity_id: usize,
+        id: ComponentId,
+    ) -> Option<&mut DynamicComponent> {
+        let manager = self.dynamic_managers.get_mut(&id)?;
+        cast_manager_mut::<DynamicComponent>(manager.as_mut())
+            .unwrap()
+            .borrow_component_mut(entity_id)
+    }
+
+    /// The entities having every dynamic component of `ids`
+    pub fn query_entities_dynamic(&self, ids: &[ComponentId]) -> Option<Vec<usize>> {
+        let bitmask = self.dynamic_bitmask(ids)?;
+        self.run_query(bitmask)
+    }
+
+    /// The entities having the component T and every dynamic component of `ids`
+    pub fn query_entities_with_dynamic<T: 'static + Component>(
+        &self,
+        ids: &[ComponentId],
+    ) -> Option<Vec<usize>> {
+        let bit = self.query_manager.get_bit_for_component::<T>()?;
+        let bitmask = self.dynamic_bitmask(ids)?;
+        self.run_query(bit | bitmask)
+    }
+
+    /// The entities having every component of `names`, static or dynamic
+    /// Returns None if a component is not registered
+    pub fn query_entities_by_name(&self, names: &[&str]) -> Option<Vec<usize>> {
+        let bitmask = names
+            .iter()
+            .try_fold(0, |bitmask, name| Some(bitmask | self.bit_by_name(name)?))?;
+        self.run_query(bitmask)
+    }
+
+    /// Whether the entity has the component (or the tag) `name`, static or dynamic
+    pub fn has_component_by_name(&self, entity_id: usize, name: &str) -> bool {
+        match self.bit_by_name(name) {
+            Some(bit) => self.query_manager.get_bitmask_for_entity(entity_id) & bit != 0,
+            None => false,
+        }
+    }
+
+    fn bit_by_name(&self, name: &str) -> Option<u128> {
+        let bit = match self.type_registry.get_by_name(name) {
+            Some(info) => self.query_manager.get_bit_for_type_id(info.type_id)?,
+            None => {
+                let (id, _) = self.type_registry.get_dynamic_by_name(name)?;
+                self.query_manager.get_bit_for_dynamic_component(id)?
+            }
+        };
+        Some(*bit)
+    }
+
+    fn run_query(&self, bitmask: u128) -> Option<Vec<usize>> {
+        self.run_query_with(bitmask, false)
+    }
+
+    // the disabled entities are skipped unless `include_disabled`
+    fn run_query_with(&self, bitmask: u128, include_disabled: bool) -> Option<Vec<usize>> {
+        if !self.profile_queries {
+            return self.query_with(bitmask, include_disabled);
+        }
+
+        let start = Instant::now();
+        let entities = self.query_with(bitmask, include_disabled);
+        let mut stats = self.query_stats.get();
+        stats.queries += 1;
+        stats.entities += entities.as_ref().map_or(0, Vec::len);
+        stats.time += start.elapsed();
+        self.query_stats.set(stats);
+        entities
+    }
+
+    fn query_with(&self, bitmask: u128, include_disabled: bool) -> Option<Vec<usize>> {
+        let mut entities = self.query_manager.query(bitmask)?;
+        if !include_disabled && self.entities.disabled > 0 {
+            entities.retain(|entity_id| !self.entities.is_disabled(*entity_id));
+        }
+        Some(entities)
+    }
+
+    pub(crate) fn set_query_profiling(&mut self, enabled: bool) {
+        self.profile_queries = enabled;
+        self.query_stats.take();
+    }
+
+    // the cost of the queries since the last call
+    pub(crate) fn take_query_stats(&self) -> QueryStats {
+        self.query_stats.take()
+    }
+
+    fn dynamic_bitmask(&self, ids: &[ComponentId]) -> Option<u128> {
+        ids.iter().try_fold(0, |bitmask, id| {
+            Some(bitmask | self.query_manager.get_bit_for_dynamic_component(*id)?)
+        })
+    }
+
+    /// Tags have no storage and are never borrowed, see `has_component`
     pub fn borrow_component_for_entity<T: 'static + Component>(
         &self,
         entity_id: usize,
     ) -> Option<&T> {
+        if self.is_tag::<T>() {
+            return None;
+        }
+
         self.borrow_component_manager::<T>()
             .borrow_component_for_entity(entity_id)
     }
 
+    /// Whether the entity has the component T (or the tag T)
+    pub fn has_component<T: 'static + Component>(&self, entity_id: usize) -> bool {
+        match self.query_manager.get_bit_for_component::<T>() {
+            Some(bit) => self.query_manager.get_bitmask_for_entity(entity_id) & bit != 0,
+            None => false,
+        }
+    }
+
+    /// Will panic if the component breaks a unique index, see `try_add_component_to_entity`
     pub fn add_component_to_entity<T: 'static + Component>(
         &mut self,
         entity_id: usize,
         component: T,
     ) -> &mut Self {
-        if !self.has_component_manager::<T>() {
+        if let Err(error) = self.try_add_component_to_entity(entity_id, component) {
+            panic!("{}", error);
+        }
+        self
+    }
+
+    /// Add a component unless its key is already used in a unique index
+    pub fn try_add_component_to_entity<T: 'static + Component>(
+        &mut self,
+        entity_id: usize,
+        component: T,
+    ) -> Result<&mut Self, IndexError> {
+        if !self.is_registered::<T>() {
             panic!(
                 "Component manager not found for type: {}",
                 std::any::type_name::<T>()
             );
         }
 
+        // the first component is kept, see `replace_component_for_entity`
+        if self.has_component::<T>(entity_id) {
+            return Ok(self);
+        }
+
+        if !self.is_tag::<T>() {
+            self.borrow_component_manager_mut::<T>()
+                .try_add(entity_id, component)?;
+        }
+
         let bitmask = self.query_manager.get_bitmask_for_entity(entity_id);
-        self.query_manager.remove_entity(entity_id);
+        self.query_manager_mut().remove_entity(entity_id);
         let component_bitmask =
             if let Some(bitmask) = self.query_manager.get_bit_for_component::<T>() {
                 *bitmask
@@ -71,36 +807,896 @@ impl EntityManager {
             };
 
         let new_bitmask = bitmask | component_bitmask;
-        self.query_manager.add_entity(entity_id, new_bitmask);
+        self.query_manager_mut().add_entity(entity_id, new_bitmask);
+
+        if let Some(on_add) = T::on_add() {
+            on_add(self, entity_id);
+        }
+
+        Ok(self)
+    }
+
+    /// Add a component or replace the one the entity already has,
+    /// the hooks see the old component leave
+    /// Will panic if the component breaks a unique index
+    pub fn replace_component_for_entity<T: 'static + Component>(
+        &mut self,
+        entity_id: usize,
+        component: T,
+    ) -> &mut Self {
+        if let Err(error) = self.try_replace_component_for_entity(entity_id, component) {
+            panic!("{}", error);
+        }
+        self
+    }
+
+    /// Replace a component unless its key is already used by another entity
+    /// in a unique index, nothing is changed (and no hook is called) on error
+    pub fn try_replace_component_for_entity<T: 'static + Component>(
+        &mut self,
+        entity_id: usize,
+        component: T,
+    ) -> Result<&mut Self, IndexError> {
+        if !self.has_component::<T>(entity_id) || self.is_tag::<T>() {
+            return self.try_add_component_to_entity(entity_id, component);
+        }
 
         self.borrow_component_manager_mut::<T>()
-            .add(entity_id, component);
+            .check(entity_id, &component)?;
+        if let Some(on_remove) = T::on_remove() {
+            on_remove(self, entity_id);
+        }
+        self.borrow_component_manager_mut::<T>()
+            .try_replace(entity_id, component)?;
+        if let Some(on_add) = T::on_add() {
+            on_add(self, entity_id);
+        }
+
+        Ok(self)
+    }
+
+    /// The index of the `#[component(index)]` field of T,
+    /// None if T is not registered
+    /// Will panic if a component modified in place breaks a unique index, see `try_index`
+    pub fn index<T: 'static + Indexed>(&mut self) -> Option<&ComponentIndex<T::Key>> {
+        match self.try_index::<T>() {
+            Ok(index) => index,
+            Err(error) => panic!("{}", error),
+        }
+    }
+
+    /// Same as `index`, fails if a component modified in place breaks a unique index
+    /// (the index is left as it was before the changes)
+    pub fn try_index<T: 'static + Indexed>(
+        &mut self,
+    ) -> Result<Option<&ComponentIndex<T::Key>>, IndexError> {
+        if !self.has_component_manager::<T>() {
+            return Ok(None);
+        }
+        self.borrow_component_manager_mut::<T>().index().map(Some)
+    }
+
+    /// Remove the component T (or the tag T) of an entity
+    pub fn remove_component_from_entity<T: 'static + Component>(
+        &mut self,
+        entity_id: usize,
+    ) -> &mut Self {
+        if !self.has_component::<T>(entity_id) {
+            return self;
+        }
+        if let Some(on_remove) = T::on_remove() {
+            on_remove(self, entity_id);
+        }
+
+        let bit = *self.query_manager.get_bit_for_component::<T>().unwrap();
+        let bitmask = self.query_manager.get_bitmask_for_entity(entity_id) & !bit;
+        self.query_manager_mut().remove_entity(entity_id);
+        if bitmask != 0 {
+            self.query_manager_mut().add_entity(entity_id, bitmask);
+        }
+
+        if !self.is_tag::<T>() {
+            self.borrow_component_manager_mut::<T>().remove(entity_id);
+        }
 
         self
     }
 
+    pub fn type_registry(&self) -> &TypeRegistry {
+        &self.type_registry
+    }
+
+    /// Borrow a component without knowing its Rust type
+    /// The component is found by its registered name (see `TypeRegistry::get_by_name`)
+    pub fn reflect_component_for_entity(
+        &self,
+        entity_id: usize,
+        component_name: &str,
+    ) -> Option<&dyn Reflect> {
+        self.manager_by_name(component_name)?.reflect(entity_id)
+    }
+
+    pub fn reflect_component_mut_for_entity(
+        &mut self,
+        entity_id: usize,
+        component_name: &str,
+    ) -> Option<&mut dyn Reflect> {
+        if let Some(info) = self.type_registry.get_by_name(component_name) {
+            return self
+                .components_managers
+                .get_mut(&info.type_id)?
+                .reflect_mut(entity_id);
+        }
+        let (id, _) = self.type_registry.get_dynamic_by_name(component_name)?;
+        self.dynamic_managers.get_mut(&id)?.reflect_mut(entity_id)
+    }
+
+    // static components first, then the dynamic ones
+    fn manager_by_name(&self, component_name: &str) -> Option<&dyn ComponentManagerTrait> {
+        if let Some(info) = self.type_registry.get_by_name(component_name) {
+            return self
+                .components_managers
+                .get(&info.type_id)
+                .map(|manager| manager.as_ref());
+        }
+        let (id, _) = self.type_registry.get_dynamic_by_name(component_name)?;
+        self.dynamic_managers
+            .get(&id)
+            .map(|manager| manager.as_ref())
+    }
+
+    /// All the components of an entity along with their type description
+    pub fn reflect_components_for_entity(
+        &self,
+        entity_id: usize,
+    ) -> Vec<(&TypeInfo, &dyn Reflect)> {
+        self.components_managers
+            .iter()
+            .filter_map(|(type_id, manager)| {
+                let component = manager.reflect(entity_id)?;
+                Some((self.type_registry.get(*type_id)?, component))
+            })
+            .collect()
+    }
+
+    pub fn insert_resource<R: Resource>(&mut self, resource: R) -> &mut Self {
+        self.resources.insert(resource);
+        self
+    }
+
+    pub fn remove_resource<R: Resource>(&mut self) -> Option<R> {
+        self.resources.remove::<R>()
+    }
+
+    pub fn borrow_resource<R: Resource>(&self) -> Option<&R> {
+        self.resources.borrow::<R>()
+    }
+
+    pub fn borrow_resource_mut<R: Resource>(&mut self) -> Option<&mut R> {
+        self.resources.borrow_mut::<R>()
+    }
+
+    /// Capture the entities, the serializable components and resources
+    pub fn save(&self) -> Result<SaveData, SaveError> {
+        let mut components = Vec::new();
+        for (type_id, manager) in self.components_managers.iter() {
+            let Some(serializer) = self.type_registry.serializer(*type_id) else {
+                continue;
+            };
+            let info = self.type_registry.get(*type_id).unwrap();
+            components.push(ComponentData {
+                name: info.name.to_string(),
+                version: serializer.version(),
+                entries: (serializer.serialize)(manager.as_ref())?,
+            });
+        }
+        for (id, manager) in self.dynamic_managers.iter() {
+            let info = self.type_registry.get_dynamic(*id).unwrap();
+            components.push(ComponentData {
+                name: info.name.clone(),
+                version: DYNAMIC_COMPONENT_VERSION,
+                entries: cast_manager::<DynamicComponent>(manager.as_ref())
+                    .unwrap()
+                    .iter()
+                    .map(|(entity_id, component)| {
+                        component.check_finite().map_err(SaveError::Encode)?;
+                        Ok((entity_id, component.to_json()))
+                    })
+                    .collect::<Result<_, SaveError>>()?,
+            });
+        }
+        for type_id in self.tags.iter() {
+            let Some(serializer) = self.type_registry.serializer(*type_id) else {
+                continue;
+            };
+            let info = self.type_registry.get(*type_id).unwrap();
+            components.push(ComponentData {
+                name: info.name.to_string(),
+                version: serializer.version(),
+                entries: self
+                    .tagged_entities(*type_id)
+                    .into_iter()
+                    .map(|entity_id| (entity_id, serde_json::Value::Null))
+                    .collect(),
+            });
+        }
+        // HashMap order is random, sort to get the same file for the same world
+        components.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Ok(SaveData {
+            version: SAVE_FORMAT_VERSION,
+            entities: self
+                .entities
+                .entities
+                .iter()
+                .map(|entity| EntityData {
+                    alive: entity.is_alive(),
+                    generation: entity.generation(),
+                    disabled: entity.is_disabled(),
+                })
+                .collect(),
+            available_ids: self.entities.available_ids.clone(),
+            components,
+            resources: self.resources.save()?,
+        })
+    }
+
+    /// Deterministic hash of the entities, every component and the `Random` streams
+    /// Components are visited in entity id order so two managers with the same content
+    /// have the same hash whatever the order of insertion
+    pub fn state_hash(&self) -> StateHash {
+        let mut hasher = StateHasher::new();
+        for entity in self.entities.entities.iter() {
+            hasher.write_u8(entity.is_alive() as u8);
+            hasher.write_u32(entity.generation());
+            hasher.write_u8(entity.is_disabled() as u8);
+        }
+        let entities = hasher.finish();
+
+        let mut components: BTreeMap<String, u64> = self
+            .components_managers
+            .iter()
+            .map(|(type_id, manager)| {
+                let mut hasher = StateHasher::new();
+                manager.hash_components(&mut hasher);
+                let info = self.type_registry.get(*type_id).unwrap();
+                (info.type_path.to_string(), hasher.finish())
+            })
+            .collect();
+        for (id, manager) in self.dynamic_managers.iter() {
+            let mut hasher = StateHasher::new();
+            manager.hash_components(&mut hasher);
+            let info = self.type_registry.get_dynamic(*id).unwrap();
+            components.insert(info.name.clone(), hasher.finish());
+        }
+        for type_id in self.tags.iter() {
+            let mut hasher = StateHasher::new();
+            for entity_id in self.tagged_entities(*type_id) {
+                hasher.write_u64(entity_id as u64);
+            }
+            let info = self.type_registry.get(*type_id).unwrap();
+            components.insert(info.type_path.to_string(), hasher.finish());
+        }
+        if let Some(random) = self.resources.borrow::<Random>() {
+            let mut hasher = StateHasher::new();
+            random.hash(&mut hasher);
+            components.insert(Random::name().to_string(), hasher.finish());
+        }
+
+        StateHash::new(entities, components)
+    }
+
+    /// A report of the entities, their components and the signature groups
+    /// The systems are owned by the world, see `World::dump`
+    pub fn dump(&self) -> WorldDump {
+        // every registered component with its bit, in bit order
+        // the tags have no manager
+        let mut components: Vec<(u128, String, Option<&dyn ComponentManagerTrait>)> = self
+            .components_managers
+            .iter()
+            .map(|(type_id, manager)| (type_id, Some(manager.as_ref())))
+            .chain(self.tags.iter().map(|type_id| (type_id, None)))
+            .map(|(type_id, manager)| {
+                let bit = *self.query_manager.get_bit_for_type_id(*type_id).unwrap();
+                let info = self.type_registry.get(*type_id).unwrap();
+                (bit, info.name.to_string(), manager)
+            })
+            .collect();
+        for (id, manager) in self.dynamic_managers.iter() {
+            let bit = *self
+                .query_manager
+                .get_bit_for_dynamic_component(*id)
+                .unwrap();
+            let info = self.type_registry.get_dynamic(*id).unwrap();
+            components.push((bit, info.name.clone(), Some(manager.as_ref())));
+        }
+        components.sort_by_key(|(bit, _, _)| *bit);
+
+        let names = |signature: u128| -> Vec<String> {
+            components
+                .iter()
+                .filter(|(bit, _, _)| signature & bit != 0)
+                .map(|(_, name, _)| name.clone())
+                .collect()
+        };
+
+        let entities = (0..self.entities.entities.len())
+            .filter(|entity_id| self.entities.has(*entity_id))
+            .map(|entity_id| {
+                let signature = self.query_manager.get_bitmask_for_entity(entity_id);
+                EntityDump {
+                    id: entity_id,
+                    generation: self.entities.entities[entity_id].generation(),
+                    signature,
+                    disabled: self.entities.is_disabled(entity_id),
+                    components: components
+                        .iter()
+                        .filter(|(bit, _, _)| signature & bit != 0)
+                        .map(|(_, name, manager)| ComponentDump {
+                            name: name.clone(),
+                            value: manager.and_then(|manager| manager.format(entity_id)),
+                        })
+                        .collect(),
+                }
+            })
+            .collect();
+
+        let groups = self
+            .query_manager
+            .groups()
+            .filter(|(_, entities)| !entities.is_empty())
+            .map(|(signature, entities)| GroupDump {
+                signature,
+                components: names(signature),
+                entities: entities.to_vec(),
+            })
+            .collect();
+
+        WorldDump {
+            entities,
+            groups,
+            free_ids: self.entities.available_ids.clone(),
+            systems: Vec::new(),
+        }
+    }
+
+    /// Create an entity from a prefab of the `Prefabs` resource
+    /// The prefab components must be registered and serializable,
+    /// they are deserialized from the prefab values
+    pub fn spawn_prefab(&mut self, name: &str) -> Result<usize, PrefabError> {
+        let components = self
+            .resources
+            .borrow::<Prefabs>()
+            .ok_or_else(|| PrefabError::UnknownPrefab(name.to_string()))?
+            .resolve(name)?;
+
+        // resolve every component before creating the entity
+        let mut resolved = Vec::new();
+        for (component_name, value) in components {
+            let key = self
+                .serializable_component(&component_name)
+                .ok_or_else(|| PrefabError::UnknownComponent(component_name.clone()))?;
+            resolved.push((component_name, key, value));
+        }
+
+        let entity_id = self.create_entity();
+        for (component_name, key, value) in resolved {
+            if let Err(error) = self.add_component_value(entity_id, key, value) {
+                // the components already added are removed with the entity
+                self.remove_entity(entity_id);
+                return Err(match error {
+                    SaveError::Index(error) => PrefabError::Index(error),
+                    SaveError::Decode(error) => PrefabError::InvalidComponent {
+                        component: component_name,
+                        error,
+                    },
+                    error => PrefabError::InvalidComponent {
+                        component: component_name,
+                        error: error.to_string(),
+                    },
+                });
+            }
+        }
+
+        Ok(entity_id)
+    }
+
+    // a serializable static component or a dynamic component
+    fn serializable_component(&self, name: &str) -> Option<ComponentKey> {
+        if let Some(info) = self.type_registry.get_by_name(name) {
+            let key = if self.tags.contains(&info.type_id) {
+                ComponentKey::Tag(info.type_id)
+            } else {
+                ComponentKey::Static(info.type_id)
+            };
+            return self.type_registry.serializer(info.type_id).map(|_| key);
+        }
+        let (id, _) = self.type_registry.get_dynamic_by_name(name)?;
+        Some(ComponentKey::Dynamic(id))
+    }
+
+    // add a serializable component to a manager from its serialized value
+    fn deserialize_component(
+        &self,
+        manager: &mut dyn ComponentManagerTrait,
+        key: ComponentKey,
+        entity_id: usize,
+        value: serde_json::Value,
+    ) -> Result<(), SaveError> {
+        match key {
+            ComponentKey::Static(type_id) => {
+                let serializer = self.type_registry.serializer(type_id).unwrap();
+                (serializer.deserialize)(manager, entity_id, value)
+            }
+            ComponentKey::Tag(_) => unreachable!("tags have no manager"),
+            ComponentKey::Dynamic(id) => {
+                let info = self.type_registry.get_dynamic(id).unwrap().clone();
+                let component =
+                    DynamicComponent::from_json(id, info, value).map_err(SaveError::Decode)?;
+                cast_manager_mut::<DynamicComponent>(manager)
+                    .unwrap()
+                    .add(entity_id, component);
+                Ok(())
+            }
+        }
+    }
+
+    fn new_manager(&self, key: ComponentKey) -> Box<dyn ComponentManagerTrait> {
+        match key {
+            ComponentKey::Static(type_id) => {
+                (self.type_registry.serializer(type_id).unwrap().new_manager)()
+            }
+            ComponentKey::Tag(_) => unreachable!("tags have no manager"),
+            ComponentKey::Dynamic(_) => Box::new(ComponentManager::<DynamicComponent>::new()),
+        }
+    }
+
+    fn component_bit(&self, key: ComponentKey) -> u128 {
+        match key {
+            ComponentKey::Static(type_id) | ComponentKey::Tag(type_id) => {
+                *self.query_manager.get_bit_for_type_id(type_id).unwrap()
+            }
+            ComponentKey::Dynamic(id) => *self
+                .query_manager
+                .get_bit_for_dynamic_component(id)
+                .unwrap(),
+        }
+    }
+
+    // add a serializable component from its serialized value
+    fn add_component_value(
+        &mut self,
+        entity_id: usize,
+        key: ComponentKey,
+        value: serde_json::Value,
+    ) -> Result<(), SaveError> {
+        // the manager is taken out to deserialize with the registry borrowed,
+        // the value of a tag is ignored
+        let manager = match key {
+            ComponentKey::Static(type_id) => self.components_managers.remove(&type_id),
+            ComponentKey::Dynamic(id) => self.dynamic_managers.remove(&id),
+            ComponentKey::Tag(_) => None,
+        };
+        if let Some(mut manager) = manager {
+            let result = self.deserialize_component(manager.as_mut(), key, entity_id, value);
+            match key {
+                ComponentKey::Static(type_id) => self.components_managers.insert(type_id, manager),
+                ComponentKey::Dynamic(id) => self.dynamic_managers.insert(id, manager),
+                ComponentKey::Tag(_) => unreachable!("tags have no manager"),
+            };
+            result?;
+        }
+
+        let bit = self.component_bit(key);
+        let bitmask = self.query_manager.get_bitmask_for_entity(entity_id);
+        self.query_manager_mut().remove_entity(entity_id);
+        self.query_manager_mut()
+            .add_entity(entity_id, bitmask | bit);
+        self.run_component_hooks(entity_id, bit, Hook::Add);
+
+        Ok(())
+    }
+
+    /// Capture the entities, components and `Random` streams, see `Snapshot`
+    /// Every component present on an entity must be clonable
+    pub fn snapshot(&self) -> Result<Snapshot, SnapshotError> {
+        let mut components_managers = HashMap::new();
+        for (type_id, manager) in self.components_managers.iter() {
+            if !manager.is_clonable() {
+                if !manager.is_empty() {
+                    let info = self.type_registry.get(*type_id).unwrap();
+                    return Err(SnapshotError::NotClonable(info.name));
+                }
+                continue;
+            }
+            components_managers.insert(*type_id, manager.share());
+        }
+        let dynamic_managers = self
+            .dynamic_managers
+            .iter()
+            .map(|(id, manager)| (*id, manager.share()))
+            .collect();
+
+        Ok(Snapshot {
+            entities: self.entities.clone(),
+            components_managers,
+            dynamic_managers,
+            query_manager: self.query_manager.clone(),
+            random: self.resources.borrow::<Random>().cloned(),
+        })
+    }
+
+    /// Bring back the entities, components and `Random` streams captured by a snapshot
+    /// The snapshot is left untouched and can be restored again
+    pub fn restore(&mut self, snapshot: &Snapshot) -> Result<(), SnapshotError> {
+        // the bit mapping holds every registered component
+        if self.query_manager.bit_mapping() != snapshot.query_manager.bit_mapping()
+            || self.query_manager.dynamic_bit_mapping()
+                != snapshot.query_manager.dynamic_bit_mapping()
+        {
+            return Err(SnapshotError::RegistrationChanged);
+        }
+
+        self.run_all_hooks(Hook::Remove);
+
+        for (type_id, manager) in self.components_managers.iter_mut() {
+            match snapshot.components_managers.get(type_id) {
+                Some(captured) => *manager = captured.share(),
+                // non clonable components had no instance when the snapshot was taken
+                None => manager.clear(),
+            }
+        }
+        for (id, manager) in self.dynamic_managers.iter_mut() {
+            *manager = snapshot.dynamic_managers[id].share();
+        }
+        self.entities = snapshot.entities.clone();
+        self.query_manager = snapshot.query_manager.clone();
+        self.run_all_hooks(Hook::Add);
+        // after the hooks, the streams are the ones of the snapshot whatever they drew
+        if let Some(random) = &snapshot.random {
+            self.resources.insert(random.clone());
+        }
+
+        Ok(())
+    }
+
+    pub fn migrations_mut(&mut self) -> &mut Migrations {
+        &mut self.migrations
+    }
+
+    /// Replace the content of the manager with a save
+    /// The saved components must be registered (and the saved resources inserted)
+    /// beforehand, old components are upgraded with the registered migrations.
+    /// On error the manager is left untouched.
+    pub fn load(&mut self, data: SaveData) -> Result<(), SaveError> {
+        check_entities(&data.entities, &data.available_ids)?;
+        let components = self.migrations.migrate(data.components, |name| {
+            match self.serializable_component(name)? {
+                ComponentKey::Static(type_id) | ComponentKey::Tag(type_id) => {
+                    Some(self.type_registry.serializer(type_id)?.version())
+                }
+                ComponentKey::Dynamic(_) => Some(DYNAMIC_COMPONENT_VERSION),
+            }
+        })?;
+        for component in components.iter() {
+            check_component_entries(component, &data.entities)?;
+        }
+
+        let mut loaded_managers = HashMap::new();
+        let mut loaded_dynamic_managers = HashMap::new();
+        // the tagged entities, added to the signatures below
+        let mut loaded_tags = Vec::new();
+        for component in components {
+            let key = self
+                .serializable_component(&component.name)
+                .ok_or_else(|| SaveError::UnknownComponent(component.name.clone()))?;
+            if let ComponentKey::Tag(_) = key {
+                let bit = self.component_bit(key);
+                loaded_tags.extend(
+                    component
+                        .entries
+                        .iter()
+                        .map(|(entity_id, _)| (*entity_id, bit)),
+                );
+                continue;
+            }
+
+            let mut manager = self.new_manager(key);
+            for (entity_id, value) in component.entries {
+                self.deserialize_component(manager.as_mut(), key, entity_id, value)
+                    .map_err(|error| match error {
+                        // two entities of the save share a unique key
+                        SaveError::Index(error) => SaveError::Corrupt(error.to_string()),
+                        error => error,
+                    })?;
+            }
+            match key {
+                ComponentKey::Static(type_id) => loaded_managers.insert(type_id, manager),
+                ComponentKey::Dynamic(id) => loaded_dynamic_managers.insert(id, manager),
+                ComponentKey::Tag(_) => unreachable!("tags have no manager"),
+            };
+        }
+        let resources = self.resources.decode(data.resources)?;
+
+        // the hooks see the components leave and come back
+        self.run_all_hooks(Hook::Remove);
+
+        let entities: Vec<Entity> = data
+            .entities
+            .iter()
+            .map(|entity| Entity::from_parts(entity.alive, entity.generation, entity.disabled))
+            .collect();
+        self.entities = Arc::new(Entities {
+            disabled: entities
+                .iter()
+                .filter(|entity| entity.is_disabled())
+                .count(),
+            entities,
+            available_ids: data.available_ids,
+        });
+        for (type_id, manager) in self.components_managers.iter_mut() {
+            match loaded_managers.remove(type_id) {
+                Some(loaded) => *manager = loaded,
+                None => manager.clear(),
+            }
+        }
+        for (id, manager) in self.dynamic_managers.iter_mut() {
+            match loaded_dynamic_managers.remove(id) {
+                Some(loaded) => *manager = loaded,
+                None => manager.clear(),
+            }
+        }
+        self.resources.replace(resources);
+
+        // rebuild the signatures from the loaded components
+        self.query_manager_mut().clear_entities();
+        let mut tags: HashMap<usize, u128> = HashMap::new();
+        for (entity_id, bit) in loaded_tags {
+            *tags.entry(entity_id).or_default() |= bit;
+        }
+        for entity_id in 0..self.entities.entities.len() {
+            let bitmask = self
+                .components_managers
+                .iter()
+                .filter(|(_, manager)| manager.has(entity_id))
+                .map(|(type_id, _)| self.component_bit(ComponentKey::Static(*type_id)))
+                .chain(
+                    self.dynamic_managers
+                        .iter()
+                        .filter(|(_, manager)| manager.has(entity_id))
+                        .map(|(id, _)| self.component_bit(ComponentKey::Dynamic(*id))),
+                )
+                .fold(
+                    tags.get(&entity_id).copied().unwrap_or(0),
+                    |bitmask, bit| bitmask | bit,
+                );
+            if bitmask != 0 {
+                self.query_manager_mut().add_entity(entity_id, bitmask);
+            }
+        }
+        self.run_all_hooks(Hook::Add);
+
+        Ok(())
+    }
+
+    // run the hooks of every component of an entity
+    fn run_hooks(&mut self, entity_id: usize, hook: Hook) {
+        self.run_component_hooks(entity_id, u128::MAX, hook);
+    }
+
+    // run the hooks of the components of an entity that are in `bitmask`
+    fn run_component_hooks(&mut self, entity_id: usize, bitmask: u128, hook: Hook) {
+        if self.hooks.is_empty() {
+            return;
+        }
+
+        let signature = self.query_manager.get_bitmask_for_entity(entity_id) & bitmask;
+        // in bit order, HashMap order is random
+        let mut hooks: Vec<(u128, ComponentHook)> = self
+            .hooks
+            .iter()
+            .map(|(type_id, hooks)| {
+                let bit = *self.query_manager.get_bit_for_type_id(*type_id).unwrap();
+                (bit, hooks)
+            })
+            .filter(|(bit, _)| signature & bit != 0)
+            .filter_map(|(bit, hooks)| match hook {
+                Hook::Add => hooks.on_add.map(|hook| (bit, hook)),
+                Hook::Remove => hooks.on_remove.map(|hook| (bit, hook)),
+            })
+            .collect();
+        hooks.sort_by_key(|(bit, _)| *bit);
+        for (_, hook) in hooks {
+            hook(self, entity_id);
+        }
+    }
+
+    // run the hooks of every entity, when the whole content is replaced
+    fn run_all_hooks(&mut self, hook: Hook) {
+        let hooked = self.hooks.keys().fold(0, |bitmask, type_id| {
+            bitmask | self.query_manager.get_bit_for_type_id(*type_id).unwrap()
+        });
+        if hooked == 0 {
+            return;
+        }
+
+        let entities_ids: Vec<usize> = self
+            .query_manager
+            .groups()
+            .filter(|(signature, _)| signature & hooked != 0)
+            .flat_map(|(_, entities_ids)| entities_ids.iter().copied())
+            .collect();
+        for entity_id in entities_ids {
+            self.run_hooks(entity_id, hook);
+        }
+    }
+
+    // a component with a storage or a tag
+    fn is_registered<T: 'static + Component>(&self) -> bool {
+        self.has_component_manager::<T>() || self.is_tag::<T>()
+    }
+
+    // the tags of an entity, read from its signature
+    fn tags_of(&self, entity_id: usize) -> impl Iterator<Item = TypeId> + '_ {
+        let bitmask = self.query_manager.get_bitmask_for_entity(entity_id);
+        self.tags.iter().copied().filter(move |type_id| {
+            bitmask & self.query_manager.get_bit_for_type_id(*type_id).unwrap() != 0
+        })
+    }
+
+    // the entities having a tag, in id order
+    fn tagged_entities(&self, type_id: TypeId) -> Vec<usize> {
+        let bit = *self.query_manager.get_bit_for_type_id(type_id).unwrap();
+        let mut entities = self.query_manager.query(bit).unwrap_or_default();
+        entities.sort();
+        entities
+    }
+
     fn has_component_manager<T: 'static + Component>(&self) -> bool {
         let type_id = TypeId::of::<T>();
         self.components_managers.contains_key(&type_id)
     }
 
-    pub fn borrow_components<T: 'static + Component>(&self) -> &Vec<T> {
+    /// The storage of T, the components of the disabled entities included
+    /// (use `iter_components` or a query to skip them)
+    /// Tags have no storage, their slice is empty (use `query_entities` to find
+    /// the tagged entities)
+    /// Will panic if T is not registered
+    pub fn borrow_components<T: 'static + Component>(&self) -> &[T] {
+        if self.is_tag::<T>() {
+            return &[];
+        }
         self.borrow_component_manager::<T>().borrow_components()
     }
 
-    pub fn borrow_components_mut<T: 'static + Component>(&mut self) -> &mut Vec<T> {
+    /// Same as `borrow_components`, the disabled entities included
+    pub fn borrow_components_mut<T: 'static + Component>(&mut self) -> &mut [T] {
+        if self.is_tag::<T>() {
+            return &mut [];
+        }
         self.borrow_component_manager_mut::<T>()
             .borrow_components_mut()
     }
 
-    pub fn query_entities<T: 'static + Component>(&self) -> Option<Vec<usize>> {
+    /// The owner of each component of `borrow_components`, in the same order
+    pub fn component_entities<T: 'static + Component>(&self) -> &[usize] {
+        if self.is_tag::<T>() {
+            return &[];
+        }
+        self.borrow_component_manager::<T>().entities()
+    }
+
+    /// Iterate over every component T of the enabled entities along with its owner
+    /// Nothing is yielded for a tag, see `borrow_components`
+    pub fn iter_components<T: 'static + Component>(&self) -> impl Iterator<Item = (usize, &T)> {
+        let manager = (!self.is_tag::<T>()).then(|| self.borrow_component_manager::<T>());
+        let entities = &self.entities;
+        manager
+            .into_iter()
+            .flat_map(|manager| manager.iter())
+            .filter(move |(entity_id, _)| {
+                entities.disabled == 0 || !entities.is_disabled(*entity_id)
+            })
+    }
+
+    /// Same as `iter_components`, the disabled entities are skipped
+    pub fn iter_components_mut<T: 'static + Component>(
+        &mut self,
+    ) -> impl Iterator<Item = (usize, &mut T)> {
+        let is_tag = self.is_tag::<T>();
+        // the storage and the entities are borrowed separately
+        let entities = &self.entities;
+        let manager = if is_tag {
+            None
+        } else {
+            let type_id = TypeId::of::<T>();
+            let manager = self.components_managers.get_mut(&type_id).unwrap();
+            Some(cast_manager_mut::<T>(manager.as_mut()).unwrap())
+        };
+        manager
+            .into_iter()
+            .flat_map(|manager| manager.iter_mut())
+            .filter(move |(entity_id, _)| {
+                entities.disabled == 0 || !entities.is_disabled(*entity_id)
+            })
+    }
+
+    /// Call `f` with every component T of the enabled entities along with its owner,
+    /// spread over the threads of the rayon pool by signature group
+    /// Nothing is done if T is not registered or is a tag
+    #[cfg(feature = "parallel")]
+    pub fn par_for_each<T, F>(&mut self, f: F)
+    where
+        T: 'static + Component + Send,
+        F: Fn(usize, &mut T) + Sync + Send,
+    {
         if !self.has_component_manager::<T>() {
+            return;
+        }
+
+        let bitmask = *self.query_manager.get_bit_for_component::<T>().unwrap();
+        let manager = cast_manager_mut::<T>(
+            self.components_managers
+                .get_mut(&TypeId::of::<T>())
+                .unwrap()
+                .as_mut(),
+        )
+        .unwrap();
+        crate::parallel::par_for_each(&self.query_manager, &self.entities, bitmask, manager, f);
+    }
+
+    /// Same as `par_for_each` for the entities having both T and U, U is only read
+    /// Will panic if T and U are the same component
+    #[cfg(feature = "parallel")]
+    pub fn par_for_each_pair<T, U, F>(&mut self, f: F)
+    where
+        T: 'static + Component + Send,
+        U: 'static + Component + Sync,
+        F: Fn(usize, &mut T, &U) + Sync + Send,
+    {
+        if !self.has_component_manager::<T>() || !self.has_component_manager::<U>() {
+            return;
+        }
+
+        let bitmask = self.query_manager.get_bit_for_component::<T>().unwrap()
+            | self.query_manager.get_bit_for_component::<U>().unwrap();
+        let [Some(manager), Some(other)] = self
+            .components_managers
+            .get_disjoint_mut([&TypeId::of::<T>(), &TypeId::of::<U>()])
+        else {
+            unreachable!()
+        };
+        let manager = cast_manager_mut::<T>(manager.as_mut()).unwrap();
+        let other = cast_manager::<U>(other.as_ref()).unwrap();
+        crate::parallel::par_for_each_pair(
+            &self.query_manager,
+            &self.entities,
+            bitmask,
+            manager,
+            other,
+            f,
+        );
+    }
+
+    pub fn query_entities<T: 'static + Component>(&self) -> Option<Vec<usize>> {
+        if !self.is_registered::<T>() {
+            return None;
+        }
+
+        let component_query = self.query_manager.get_bit_for_component::<T>().unwrap();
+
+        self.run_query(*component_query)
+    }
+
+    /// Same as `query_entities`, the disabled entities included
+    pub fn query_entities_including_disabled<T: 'static + Component>(&self) -> Option<Vec<usize>> {
+        if !self.is_registered::<T>() {
             return None;
         }
 
         let component_query = self.query_manager.get_bit_for_component::<T>().unwrap();
 
-        self.query_manager.query(*component_query)
+        self.run_query_with(*component_query, true)
     }
 
     pub fn borrow_components_for_entity<T: 'static + Component>(
@@ -111,16 +1707,14 @@ impl EntityManager {
             return None;
         }
 
-        let type_id = TypeId::of::<T>();
-
-        let manager = cast_manager_mut_unsafe::<T>(self.components_managers.get(&type_id).unwrap());
-        manager.borrow_component_mut(entity)
+        self.borrow_component_manager_mut::<T>()
+            .borrow_component_mut(entity)
     }
 
     pub fn query_entities_pair<T: 'static + Component, U: 'static + Component>(
         &self,
     ) -> Option<Vec<usize>> {
-        if !self.has_component_manager::<T>() || !self.has_component_manager::<U>() {
+        if !self.is_registered::<T>() || !self.is_registered::<U>() {
             return None;
         }
 
@@ -129,7 +1723,24 @@ impl EntityManager {
 
         let query_bitmask = component_query_t | component_query_u;
 
-        self.query_manager.query(query_bitmask)
+        self.run_query(query_bitmask)
+    }
+
+    /// Same as `query_entities_pair`, the disabled entities included
+    pub fn query_entities_pair_including_disabled<
+        T: 'static + Component,
+        U: 'static + Component,
+    >(
+        &self,
+    ) -> Option<Vec<usize>> {
+        if !self.is_registered::<T>() || !self.is_registered::<U>() {
+            return None;
+        }
+
+        let query_bitmask = self.query_manager.get_bit_for_component::<T>().unwrap()
+            | self.query_manager.get_bit_for_component::<U>().unwrap();
+
+        self.run_query_with(query_bitmask, true)
     }
 
     pub fn borrow_components_pair_for_entity<T: 'static + Component, U: 'static + Component>(
@@ -143,10 +1754,11 @@ impl EntityManager {
         let type_id_t = TypeId::of::<T>();
         let type_id_u = TypeId::of::<U>();
 
-        let manager_t =
-            cast_manager_mut_unsafe::<T>(self.components_managers.get(&type_id_t).unwrap());
-        let manager_u =
-            cast_manager_mut_unsafe::<U>(self.components_managers.get(&type_id_u).unwrap());
+        let [manager_t, manager_u] = self
+            .components_managers
+            .get_disjoint_mut([&type_id_t, &type_id_u]);
+        let manager_t = cast_manager_mut::<T>(manager_t.unwrap().as_mut()).unwrap();
+        let manager_u = cast_manager_mut::<U>(manager_u.unwrap().as_mut()).unwrap();
 
         let component_t = manager_t.borrow_component_mut(entity).unwrap();
         let component_u = manager_u.borrow_component_mut(entity).unwrap();
@@ -163,13 +1775,117 @@ impl EntityManager {
         let type_id = TypeId::of::<T>();
         cast_manager_mut(self.components_managers.get_mut(&type_id).unwrap().as_mut()).unwrap()
     }
+
+    fn dynamic_manager_mut(&mut self, id: ComponentId) -> &mut ComponentManager<DynamicComponent> {
+        cast_manager_mut(self.dynamic_managers.get_mut(&id).unwrap().as_mut()).unwrap()
+    }
+
+    // the static and the dynamic components storages
+    fn all_managers_mut(&mut self) -> impl Iterator<Item = &mut Box<dyn ComponentManagerTrait>> {
+        self.components_managers
+            .values_mut()
+            .chain(self.dynamic_managers.values_mut())
+    }
+
+    fn entities_mut(&mut self) -> &mut Entities {
+        Arc::make_mut(&mut self.entities)
+    }
+
+    fn query_manager_mut(&mut self) -> &mut QueryManager {
+        Arc::make_mut(&mut self.query_manager)
+    }
+}
+
+/// Schema version of the saved dynamic components, they have no migration of their own
+const DYNAMIC_COMPONENT_VERSION: u32 = 1;
+
+#[derive(Clone, Copy)]
+struct ComponentHooks {
+    on_add: Option<ComponentHook>,
+    on_remove: Option<ComponentHook>,
+}
+
+#[derive(Clone, Copy)]
+enum Hook {
+    Add,
+    Remove,
+}
+
+// a static component type, a tag or a dynamic component
+#[derive(Clone, Copy)]
+enum ComponentKey {
+    Static(TypeId),
+    Tag(TypeId),
+    Dynamic(ComponentId),
+}
+
+// the free ids of a save must be dead entities, each listed once
+fn check_entities(entities: &[EntityData], available_ids: &[usize]) -> Result<(), SaveError> {
+    if let Some(id) = entities
+        .iter()
+        .position(|entity| !entity.alive && entity.disabled)
+    {
+        return Err(SaveError::Corrupt(format!("dead entity {} is disabled", id)));
+    }
+
+    let mut free = HashSet::new();
+    for id in available_ids {
+        match entities.get(*id) {
+            None => {
+                return Err(SaveError::Corrupt(format!(
+                    "free id {} is out of range ({} entities)",
+                    id,
+                    entities.len()
+                )));
+            }
+            Some(entity) if entity.alive => {
+                return Err(SaveError::Corrupt(format!(
+                    "free id {} is an alive entity",
+                    id
+                )));
+            }
+            Some(_) => {}
+        }
+        if !free.insert(*id) {
+            return Err(SaveError::Corrupt(format!("free id {} is duplicated", id)));
+        }
+    }
+
+    Ok(())
+}
+
+// the components must belong to alive entities, at most one per entity
+fn check_component_entries(
+    component: &ComponentData,
+    entities: &[EntityData],
+) -> Result<(), SaveError> {
+    let mut owners = HashSet::new();
+    for (entity_id, _) in component.entries.iter() {
+        if !entities.get(*entity_id).is_some_and(|entity| entity.alive) {
+            return Err(SaveError::Corrupt(format!(
+                "component {} belongs to dead entity {}",
+                component.name, entity_id
+            )));
+        }
+        if !owners.insert(*entity_id) {
+            return Err(SaveError::Corrupt(format!(
+                "entity {} has component {} twice",
+                entity_id, component.name
+            )));
+        }
+    }
+
+    Ok(())
 }
 
 // This struct is used to manage the entities.
 // IDs are reused when an entity is removed.
-struct Entities {
+#[derive(Clone)]
+pub(crate) struct Entities {
     entities: Vec<Entity>,
     available_ids: Vec<usize>,
+    // the number of disabled entities, the queries skip the filtering when 0
+    disabled: usize,
 }
 
 impl Entities {
@@ -177,6 +1893,7 @@ impl Entities {
         Self {
             entities: Vec::new(),
             available_ids: Vec::new(),
+            disabled: 0,
         }
     }
 
@@ -184,8 +1901,25 @@ impl Entities {
         entity_id < self.entities.len() && self.entities[entity_id].is_alive()
     }
 
+    pub fn is_disabled(&self, entity_id: usize) -> bool {
+        self.has(entity_id) && self.entities[entity_id].is_disabled()
+    }
+
+    pub fn set_disabled(&mut self, entity_id: usize, disabled: bool) {
+        if !self.has(entity_id) || self.entities[entity_id].is_disabled() == disabled {
+            return;
+        }
+
+        self.entities[entity_id].set_disabled(disabled);
+        if disabled {
+            self.disabled += 1;
+        } else {
+            self.disabled -= 1;
+        }
+    }
+
     pub fn create(&mut self) -> usize {
-        if self.available_ids.len() > 0 {
+        if !self.available_ids.is_empty() {
             let index = self.available_ids.pop().unwrap();
             self.entities[index].reset();
             return index;
@@ -197,20 +1931,45 @@ impl Entities {
         self.entities.len() - 1
     }
 
+    /// The ids `create_batch` would give to `count` entities, nothing is created
+    pub fn next_batch(&self, count: usize) -> Vec<usize> {
+        let reused = count.min(self.available_ids.len());
+        let first_id = self.entities.len();
+        self.available_ids[self.available_ids.len() - reused..]
+            .iter()
+            .rev()
+            .copied()
+            .chain(first_id..first_id + count - reused)
+            .collect()
+    }
+
+    /// Create `count` entities, reusing the available ids first
+    pub fn create_batch(&mut self, count: usize) -> Vec<usize> {
+        let reused = count.min(self.available_ids.len());
+        let mut ids: Vec<usize> = self
+            .available_ids
+            .drain(self.available_ids.len() - reused..)
+            .rev()
+            .collect();
+        for id in ids.iter() {
+            self.entities[*id].reset();
+        }
+
+        let first_id = self.entities.len();
+        let created = count - reused;
+        self.entities.resize_with(first_id + created, Entity::new);
+        ids.extend(first_id..first_id + created);
+
+        ids
+    }
+
     pub fn remove(&mut self, entity_id: usize) {
         if !self.has(entity_id) {
             return;
         }
 
+        self.set_disabled(entity_id, false);
         self.entities[entity_id].kill();
         self.available_ids.push(entity_id);
     }
 }
-
-fn cast_manager_mut_unsafe<T: 'static + Component>(
-    manager: &Box<dyn ComponentManagerTrait>,
-) -> &mut ComponentManager<T> {
-    let ptr = cast_manager(manager.as_ref()).unwrap() as *const ComponentManager<T>
-        as *mut ComponentManager<T>;
-    unsafe { transmute(ptr) }
-}
@@ -0,0 +1,133 @@
+use std::marker::PhantomData;
+
+use crate::entity_manager::EntityManager;
+use crate::resource::Resource;
+use crate::system::System;
+
+/// A queue of events of type E, a resource added by `World::add_event`
+/// Every system sees an event, whatever the order of the systems: the events are
+/// kept until `UpdateEvents` runs in the tick after the one they were sent
+/// A system reads them with its own `EventReader` to see each event once
+pub struct Events<E> {
+    // sent during the previous tick
+    previous: Vec<E>,
+    // sent during this tick
+    current: Vec<E>,
+    // the number of the first event of `previous`
+    previous_start: usize,
+    // the number of events ever sent
+    event_count: usize,
+}
+
+impl<E: 'static> Resource for Events<E> {}
+
+impl<E> Default for Events<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<E> Events<E> {
+    pub fn new() -> Self {
+        Self {
+            previous: Vec::new(),
+            current: Vec::new(),
+            previous_start: 0,
+            event_count: 0,
+        }
+    }
+
+    pub fn send(&mut self, event: E) {
+        self.current.push(event);
+        self.event_count += 1;
+    }
+
+    /// The events of the previous tick then the ones of this tick
+    /// An event is returned by every call until it is dropped, use an `EventReader`
+    /// to see it only once
+    pub fn iter(&self) -> impl Iterator<Item = &E> {
+        self.previous.iter().chain(self.current.iter())
+    }
+
+    /// Take every event, they are not seen by the next readers
+    pub fn drain(&mut self) -> Vec<E> {
+        let mut events = std::mem::take(&mut self.previous);
+        events.append(&mut self.current);
+        self.previous_start = self.event_count;
+        events
+    }
+
+    pub fn len(&self) -> usize {
+        self.previous.len() + self.current.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Drop the events of the previous tick, called once per tick by `UpdateEvents`
+    pub fn update(&mut self) {
+        self.previous_start += self.previous.len();
+        self.previous = std::mem::take(&mut self.current);
+    }
+}
+
+/// The position of a system in an `Events<E>` queue, each event is read once
+/// Kept by the system between the ticks, every reader sees every event
+pub struct EventReader<E> {
+    // the number of events sent before the last read
+    last_event_count: usize,
+    marker: PhantomData<E>,
+}
+
+impl<E> Default for EventReader<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<E> EventReader<E> {
+    /// A reader seeing every event still in the queue
+    pub fn new() -> Self {
+        Self {
+            last_event_count: 0,
+            marker: PhantomData,
+        }
+    }
+
+    /// The events sent since the last read
+    /// The events dropped before this read (the reader skipped a whole tick) are missed
+    pub fn read<'a>(&mut self, events: &'a Events<E>) -> impl Iterator<Item = &'a E> {
+        let seen = self.last_event_count.saturating_sub(events.previous_start);
+        self.last_event_count = events.event_count;
+        events.iter().skip(seen)
+    }
+
+    /// The number of events this reader has not read yet
+    pub fn len(&self, events: &Events<E>) -> usize {
+        events
+            .event_count
+            .saturating_sub(self.last_event_count.max(events.previous_start))
+    }
+
+    pub fn is_empty(&self, events: &Events<E>) -> bool {
+        self.len(events) == 0
+    }
+}
+
+/// Ages the `Events<E>` resource every tick, registered by `World::add_event`
+pub struct UpdateEvents<E>(PhantomData<E>);
+
+impl<E> Default for UpdateEvents<E> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<E: 'static> System for UpdateEvents<E> {
+    fn update(&mut self, _delta_time: f32, entity_manager: &mut EntityManager) {
+        if let Some(events) = entity_manager.borrow_resource_mut::<Events<E>>() {
+            events.update();
+        }
+    }
+}
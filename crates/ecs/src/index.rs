@@ -0,0 +1,337 @@
+use std::{
+    any::Any,
+    borrow::Borrow,
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt,
+    hash::Hash,
+    ops::RangeBounds,
+};
+
+use crate::component::Component;
+
+/// How the entities are found by key
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndexKind {
+    /// Fast lookup of one key, `#[component(index)]`
+    Hash,
+    /// Also iterate the keys in order and look up ranges, `#[component(index = "ordered")]`
+    Ordered,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IndexError {
+    /// A unique index already holds the key for another entity
+    Duplicate {
+        component: &'static str,
+        entity_id: usize,
+        owner: usize,
+    },
+}
+
+impl fmt::Display for IndexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IndexError::Duplicate {
+                component,
+                entity_id,
+                owner,
+            } => write!(
+                f,
+                "Cannot add {} to entity {}: its key is already used by entity {}",
+                component, entity_id, owner
+            ),
+        }
+    }
+}
+
+impl std::error::Error for IndexError {}
+
+/// A component with an indexed field, implemented by `#[component(index)]`
+/// or `#[component(unique)]` on the field
+pub trait Indexed: Component {
+    type Key: Clone + Eq + Hash + Ord + 'static;
+    const KIND: IndexKind;
+    /// Reject the components with the key of another entity
+    const UNIQUE: bool;
+
+    fn key(&self) -> &Self::Key;
+}
+
+#[derive(Clone)]
+enum Entries<K> {
+    Hash(HashMap<K, Vec<usize>>),
+    Ordered(BTreeMap<K, Vec<usize>>),
+}
+
+/// The entities by key of an indexed component, see `World::index`
+#[derive(Clone)]
+pub struct ComponentIndex<K> {
+    entries: Entries<K>,
+    // the key of each entity, to remove it after a change
+    keys: HashMap<usize, K>,
+    unique: bool,
+}
+
+impl<K: Clone + Eq + Hash + Ord> ComponentIndex<K> {
+    pub fn new(kind: IndexKind, unique: bool) -> Self {
+        let entries = match kind {
+            IndexKind::Hash => Entries::Hash(HashMap::new()),
+            IndexKind::Ordered => Entries::Ordered(BTreeMap::new()),
+        };
+        Self {
+            entries,
+            keys: HashMap::new(),
+            unique,
+        }
+    }
+
+    pub fn kind(&self) -> IndexKind {
+        match self.entries {
+            Entries::Hash(_) => IndexKind::Hash,
+            Entries::Ordered(_) => IndexKind::Ordered,
+        }
+    }
+
+    pub fn is_unique(&self) -> bool {
+        self.unique
+    }
+
+    /// The entities with the key, in insertion order
+    pub fn get<Q>(&self, key: &Q) -> &[usize]
+    where
+        K: Borrow<Q>,
+        Q: Hash + Ord + ?Sized,
+    {
+        let entities = match &self.entries {
+            Entries::Hash(entries) => entries.get(key),
+            Entries::Ordered(entries) => entries.get(key),
+        };
+        entities.map_or(&[], |entities| entities)
+    }
+
+    /// The entity with the key, the first one if the index is not unique
+    pub fn get_one<Q>(&self, key: &Q) -> Option<usize>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Ord + ?Sized,
+    {
+        self.get(key).first().copied()
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Ord + ?Sized,
+    {
+        !self.get(key).is_empty()
+    }
+
+    /// The entities with a key in the range, ordered by key
+    /// An ordered index only visits the range, a hash index checks every key
+    pub fn range<Q, R>(&self, range: R) -> Vec<usize>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        match &self.entries {
+            Entries::Ordered(entries) => entries
+                .range::<Q, R>(range)
+                .flat_map(|(_, entities)| entities.iter().copied())
+                .collect(),
+            Entries::Hash(entries) => {
+                let mut matching: Vec<(&K, &Vec<usize>)> = entries
+                    .iter()
+                    .filter(|(key, _)| range.contains((*key).borrow()))
+                    .collect();
+                matching.sort_by_key(|(key, _)| *key);
+                matching
+                    .into_iter()
+                    .flat_map(|(_, entities)| entities.iter().copied())
+                    .collect()
+            }
+        }
+    }
+
+    /// The distinct keys, ordered for an ordered index
+    pub fn keys(&self) -> Vec<&K> {
+        match &self.entries {
+            Entries::Hash(entries) => entries.keys().collect(),
+            Entries::Ordered(entries) => entries.keys().collect(),
+        }
+    }
+
+    /// The key of an entity
+    pub fn key_of(&self, entity_id: usize) -> Option<&K> {
+        self.keys.get(&entity_id)
+    }
+
+    /// The number of indexed entities
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    // the entity already holding the key, if the index is unique
+    fn owner(&self, entity_id: usize, key: &K) -> Option<usize> {
+        if !self.unique {
+            return None;
+        }
+        self.get(key)
+            .iter()
+            .copied()
+            .find(|owner| *owner != entity_id)
+    }
+
+    // the components must not use the key of another entity (the previous keys
+    // of the `ignored` entities do not count) nor share a key
+    fn check_keys<T: Indexed<Key = K>>(
+        &self,
+        components: &[(usize, &T)],
+        ignored: &HashSet<usize>,
+    ) -> Result<(), IndexError> {
+        if !self.unique {
+            return Ok(());
+        }
+
+        let mut new_keys: HashMap<&K, usize> = HashMap::new();
+        for (entity_id, component) in components {
+            let key = component.key();
+            let owner = self
+                .get(key)
+                .iter()
+                .copied()
+                .find(|owner| *owner != *entity_id && !ignored.contains(owner))
+                .or_else(|| {
+                    new_keys
+                        .get(key)
+                        .copied()
+                        .filter(|owner| *owner != *entity_id)
+                });
+            if let Some(owner) = owner {
+                return Err(IndexError::Duplicate {
+                    component: T::type_info().name,
+                    entity_id: *entity_id,
+                    owner,
+                });
+            }
+            new_keys.insert(key, *entity_id);
+        }
+        Ok(())
+    }
+
+    fn insert(&mut self, entity_id: usize, key: K) {
+        self.remove(entity_id);
+        let entities = match &mut self.entries {
+            Entries::Hash(entries) => entries.entry(key.clone()).or_default(),
+            Entries::Ordered(entries) => entries.entry(key.clone()).or_default(),
+        };
+        entities.push(entity_id);
+        self.keys.insert(entity_id, key);
+    }
+
+    fn remove(&mut self, entity_id: usize) {
+        let Some(key) = self.keys.remove(&entity_id) else {
+            return;
+        };
+        let empty = match &mut self.entries {
+            Entries::Hash(entries) => {
+                let entities = entries.get_mut(&key).unwrap();
+                entities.retain(|id| *id != entity_id);
+                entities.is_empty()
+            }
+            Entries::Ordered(entries) => {
+                let entities = entries.get_mut(&key).unwrap();
+                entities.retain(|id| *id != entity_id);
+                entities.is_empty()
+            }
+        };
+        if empty {
+            match &mut self.entries {
+                Entries::Hash(entries) => entries.remove(&key),
+                Entries::Ordered(entries) => entries.remove(&key),
+            };
+        }
+    }
+}
+
+/// The index of a component in its `ComponentManager`, created by `indexer`
+pub trait ComponentIndexer<T> {
+    /// Whether the component can be indexed for the entity
+    fn check(&self, entity_id: usize, component: &T) -> Result<(), IndexError>;
+    /// Whether the components can all be indexed again at once (after they were
+    /// modified in place), their previous keys are ignored
+    fn check_all(&self, components: &[(usize, &T)]) -> Result<(), IndexError>;
+    /// Whether the components of new entities (moved from another world) can all
+    /// be indexed, the ids are only used to report the error
+    fn check_new(&self, components: &[(usize, &T)]) -> Result<(), IndexError>;
+    /// Index the component of an entity, replacing its previous key
+    /// Nothing is changed if the key is already used in a unique index
+    fn insert(&mut self, entity_id: usize, component: &T) -> Result<(), IndexError>;
+    fn remove(&mut self, entity_id: usize);
+    fn clear(&mut self);
+    fn is_unique(&self) -> bool;
+    fn as_any(&self) -> &dyn Any;
+    /// Copy the index along with its storage, see `ComponentManager::storage_mut`
+    fn box_clone(&self) -> Box<dyn ComponentIndexer<T>>;
+}
+
+impl<T: Indexed> ComponentIndexer<T> for ComponentIndex<T::Key> {
+    fn check(&self, entity_id: usize, component: &T) -> Result<(), IndexError> {
+        match self.owner(entity_id, component.key()) {
+            Some(owner) => Err(IndexError::Duplicate {
+                component: T::type_info().name,
+                entity_id,
+                owner,
+            }),
+            None => Ok(()),
+        }
+    }
+
+    fn check_all(&self, components: &[(usize, &T)]) -> Result<(), IndexError> {
+        // the entities indexed again give up their previous key
+        let reindexed = components.iter().map(|(entity_id, _)| *entity_id).collect();
+        self.check_keys(components, &reindexed)
+    }
+
+    fn check_new(&self, components: &[(usize, &T)]) -> Result<(), IndexError> {
+        self.check_keys(components, &HashSet::new())
+    }
+
+    fn insert(&mut self, entity_id: usize, component: &T) -> Result<(), IndexError> {
+        ComponentIndexer::check(self, entity_id, component)?;
+        ComponentIndex::insert(self, entity_id, component.key().clone());
+        Ok(())
+    }
+
+    fn remove(&mut self, entity_id: usize) {
+        ComponentIndex::remove(self, entity_id);
+    }
+
+    fn clear(&mut self) {
+        *self = ComponentIndex::new(self.kind(), self.unique);
+    }
+
+    fn is_unique(&self) -> bool {
+        self.unique
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self as &dyn Any
+    }
+
+    fn box_clone(&self) -> Box<dyn ComponentIndexer<T>> {
+        Box::new(self.clone())
+    }
+}
+
+/// Creates the index of a component, see `Component::indexer`
+pub type NewIndexer<T> = fn() -> Box<dyn ComponentIndexer<T>>;
+
+/// Create the index of a component, returned by `Component::indexer`
+pub fn indexer<T: Indexed>() -> Box<dyn ComponentIndexer<T>> {
+    Box::new(ComponentIndex::<T::Key>::new(T::KIND, T::UNIQUE))
+}
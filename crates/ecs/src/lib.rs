@@ -1,7 +1,34 @@
+// the derive macros generate `ecs::` paths, also used by the components of this crate
+extern crate self as ecs;
+
+pub mod bundle;
 pub mod component;
 pub mod component_manager;
+pub mod dump;
+pub mod dynamic;
 pub mod entity;
 pub mod entity_manager;
+pub mod event;
+pub mod index;
+pub mod migration;
+#[cfg(feature = "parallel")]
+pub mod parallel;
+pub mod plugin;
+pub mod prefab;
+pub mod profiler;
 pub mod query_manager;
+pub mod random;
+pub mod reflect;
+pub mod resource;
+#[cfg(feature = "scripting")]
+pub mod scripting;
+pub mod serialization;
+pub mod snapshot;
+pub mod spatial;
+pub mod state;
+pub mod state_hash;
 pub mod system;
+pub mod time;
+pub mod transfer;
+pub mod type_registry;
 pub mod world;
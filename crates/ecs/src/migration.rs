@@ -0,0 +1,170 @@
+use std::collections::{HashMap, HashSet};
+
+use serde_json::Value;
+
+use crate::serialization::{ComponentData, SaveError};
+
+/// A single saved component, as seen by a migration
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComponentPayload {
+    pub name: String,
+    pub version: u32,
+    pub value: Value,
+}
+
+impl ComponentPayload {
+    pub fn new(name: &str, version: u32, value: Value) -> Self {
+        Self {
+            name: name.to_string(),
+            version,
+            value,
+        }
+    }
+}
+
+/// Upgrade a payload saved at a given version
+/// It can return the same component at the next version, another component (rename),
+/// several components (split) or nothing at all.
+pub type MigrationFn = Box<dyn Fn(ComponentPayload) -> Result<Vec<ComponentPayload>, String>>;
+
+// This struct stores the functions upgrading old saved components to their current schema.
+// Migrations are applied when a save is loaded, before the components are deserialized.
+#[derive(Default)]
+pub struct Migrations {
+    migrations: HashMap<(String, u32), MigrationFn>,
+    removed: HashSet<String>,
+}
+
+impl Migrations {
+    pub fn new() -> Self {
+        Self {
+            migrations: HashMap::new(),
+            removed: HashSet::new(),
+        }
+    }
+
+    /// Register the migration of `component` saved with the schema `from_version`
+    pub fn register<F>(&mut self, component: &str, from_version: u32, migration: F) -> &mut Self
+    where
+        F: Fn(ComponentPayload) -> Result<Vec<ComponentPayload>, String> + 'static,
+    {
+        self.migrations
+            .insert((component.to_string(), from_version), Box::new(migration));
+        self
+    }
+
+    /// Upgrade `component` from `from_version` to `from_version + 1` by editing its value
+    pub fn upgrade<F>(&mut self, component: &str, from_version: u32, upgrade: F) -> &mut Self
+    where
+        F: Fn(Value) -> Result<Value, String> + 'static,
+    {
+        self.register(component, from_version, move |payload| {
+            Ok(vec![ComponentPayload {
+                version: payload.version + 1,
+                value: upgrade(payload.value)?,
+                name: payload.name,
+            }])
+        })
+    }
+
+    /// The component was renamed, `to_version` is the schema of the payload under its new name
+    pub fn rename_component(
+        &mut self,
+        component: &str,
+        from_version: u32,
+        new_name: &str,
+        to_version: u32,
+    ) -> &mut Self {
+        let new_name = new_name.to_string();
+        self.register(component, from_version, move |payload| {
+            Ok(vec![ComponentPayload {
+                name: new_name.clone(),
+                version: to_version,
+                value: payload.value,
+            }])
+        })
+    }
+
+    /// The component does not exist anymore, every saved version of it is dropped
+    pub fn remove_component(&mut self, component: &str) -> &mut Self {
+        self.removed.insert(component.to_string());
+        self
+    }
+
+    /// Bring every saved component to its current schema version
+    /// `current_version` gives the registered version of a component, None if it is unknown
+    pub fn migrate(
+        &self,
+        components: Vec<ComponentData>,
+        current_version: impl Fn(&str) -> Option<u32>,
+    ) -> Result<Vec<ComponentData>, SaveError> {
+        let mut migrated: Vec<ComponentData> = Vec::new();
+
+        for component in components {
+            for (entity_id, value) in component.entries {
+                let mut pending = vec![ComponentPayload {
+                    name: component.name.clone(),
+                    version: component.version,
+                    value,
+                }];
+
+                while let Some(payload) = pending.pop() {
+                    let current = current_version(&payload.name);
+                    match current {
+                        Some(current) if payload.version == current => {
+                            push_entry(&mut migrated, payload, entity_id);
+                            continue;
+                        }
+                        Some(current) if payload.version > current => {
+                            return Err(SaveError::UnsupportedComponentVersion {
+                                name: payload.name,
+                                found: payload.version,
+                                supported: current,
+                            });
+                        }
+                        _ => {}
+                    }
+
+                    if self.removed.contains(&payload.name) {
+                        continue;
+                    }
+
+                    let key = (payload.name.clone(), payload.version);
+                    let Some(migration) = self.migrations.get(&key) else {
+                        return Err(match current {
+                            Some(_) => SaveError::MissingMigration {
+                                name: key.0,
+                                version: key.1,
+                            },
+                            None => SaveError::UnknownComponent(key.0),
+                        });
+                    };
+
+                    let upgraded = migration(payload).map_err(|error| SaveError::Migration {
+                        name: key.0.clone(),
+                        version: key.1,
+                        error,
+                    })?;
+                    pending.extend(upgraded);
+                }
+            }
+        }
+
+        Ok(migrated)
+    }
+}
+
+fn push_entry(migrated: &mut Vec<ComponentData>, payload: ComponentPayload, entity_id: usize) {
+    let index = match migrated.iter().position(|data| data.name == payload.name) {
+        Some(index) => index,
+        None => {
+            migrated.push(ComponentData {
+                name: payload.name,
+                version: payload.version,
+                entries: Vec::new(),
+            });
+            migrated.len() - 1
+        }
+    };
+    migrated[index].entries.push((entity_id, payload.value));
+}
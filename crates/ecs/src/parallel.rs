@@ -0,0 +1,88 @@
+use rayon::prelude::*;
+
+use crate::component::Component;
+use crate::component_manager::ComponentManager;
+use crate::entity_manager::Entities;
+use crate::query_manager::QueryManager;
+
+/// The maximum number of entities given to a thread at once,
+/// a signature group with more entities is split
+pub const BATCH_SIZE: usize = 1024;
+
+// the components of a storage, shared between the threads of a batch iteration
+struct SharedComponents<T> {
+    components: *mut T,
+    len: usize,
+}
+
+// each thread only borrows the components of the entities of its batches
+unsafe impl<T: Send> Sync for SharedComponents<T> {}
+
+impl<T> SharedComponents<T> {
+    // SAFETY: a row must not be borrowed by two threads at once
+    #[allow(clippy::mut_from_ref)]
+    unsafe fn get_mut(&self, row: usize) -> &mut T {
+        assert!(row < self.len);
+        unsafe { &mut *self.components.add(row) }
+    }
+}
+
+// call `f` with the component T of every enabled entity matching the bitmask,
+// on the rayon thread pool, one batch per signature group (split when larger
+// than `BATCH_SIZE`), each component is borrowed by a single thread
+pub(crate) fn par_for_each<T, F>(
+    query_manager: &QueryManager,
+    entities: &Entities,
+    bitmask: u128,
+    manager: &mut ComponentManager<T>,
+    f: F,
+) where
+    T: 'static + Component + Send,
+    F: Fn(usize, &mut T) + Sync + Send,
+{
+    let (rows, components) = manager.rows_and_components_mut();
+    let components = SharedComponents {
+        len: components.len(),
+        components: components.as_mut_ptr(),
+    };
+    query_manager
+        .query_batches(bitmask, BATCH_SIZE)
+        .into_par_iter()
+        .for_each(|batch| {
+            for entity_id in batch {
+                if entities.is_disabled(*entity_id) {
+                    continue;
+                }
+                let Some(row) = rows.get(entity_id) else {
+                    continue;
+                };
+                // SAFETY: an entity is in a single group, once, and owns its own
+                // row: the batches never share a row
+                let component = unsafe { components.get_mut(*row) };
+                f(*entity_id, component);
+            }
+        });
+}
+
+// same as `par_for_each` with a second component U of the entity, only read,
+// the bitmask must contain U
+pub(crate) fn par_for_each_pair<T, U, F>(
+    query_manager: &QueryManager,
+    entities: &Entities,
+    bitmask: u128,
+    manager: &mut ComponentManager<T>,
+    other: &ComponentManager<U>,
+    f: F,
+) where
+    T: 'static + Component + Send,
+    U: 'static + Component + Sync,
+    F: Fn(usize, &mut T, &U) + Sync + Send,
+{
+    let other = other.lookup();
+    par_for_each(query_manager, entities, bitmask, manager, |entity_id, component| {
+        // every entity of the matching groups has U
+        if let Some(other) = other.get(entity_id) {
+            f(entity_id, component, other);
+        }
+    });
+}
@@ -0,0 +1,192 @@
+use std::fmt;
+use std::time::Duration;
+
+use crate::component::Component;
+use crate::resource::Resource;
+use crate::system::System;
+use crate::world::World;
+
+/// A game module (belts, power, crafting...) registering its components,
+/// resources, events and systems in one call, see `App::add_plugin`
+pub trait Plugin {
+    fn build(&self, app: &mut App);
+
+    /// Identify the plugin to detect duplicates, the type name by default
+    fn name(&self) -> &str {
+        std::any::type_name::<Self>()
+    }
+
+    /// The plugins built before this one, unless they were already added
+    fn dependencies(&self) -> Vec<Box<dyn Plugin>> {
+        Vec::new()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PluginError {
+    /// The plugin was already added
+    Duplicate(String),
+    /// The plugins depend on each other, the last one is the first one
+    Cycle(Vec<String>),
+}
+
+impl fmt::Display for PluginError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PluginError::Duplicate(name) => write!(f, "plugin {} is already added", name),
+            PluginError::Cycle(names) => {
+                write!(f, "plugins depend on each other: {}", names.join(" -> "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for PluginError {}
+
+/// A world built from plugins
+pub struct App {
+    world: World,
+    // the names of the added plugins, in build order
+    plugins: Vec<String>,
+    // the plugins whose dependencies are being added
+    building: Vec<String>,
+}
+
+impl Default for App {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl App {
+    pub fn new() -> Self {
+        Self::from_world(World::new())
+    }
+
+    pub fn from_world(world: World) -> Self {
+        Self {
+            world,
+            plugins: Vec::new(),
+            building: Vec::new(),
+        }
+    }
+
+    /// Build a plugin after its dependencies
+    /// Adding the same plugin twice is an error, a dependency already added is skipped
+    pub fn add_plugin<P: Plugin + 'static>(&mut self, plugin: P) -> Result<&mut Self, PluginError> {
+        self.add_boxed_plugin(Box::new(plugin), false)?;
+        Ok(self)
+    }
+
+    fn add_boxed_plugin(
+        &mut self,
+        plugin: Box<dyn Plugin>,
+        dependency: bool,
+    ) -> Result<(), PluginError> {
+        let name = plugin.name().to_string();
+        if self.has_plugin(&name) {
+            if dependency {
+                return Ok(());
+            }
+            return Err(PluginError::Duplicate(name));
+        }
+        if let Some(start) = self.building.iter().position(|building| *building == name) {
+            let mut cycle = self.building[start..].to_vec();
+            cycle.push(name);
+            return Err(PluginError::Cycle(cycle));
+        }
+
+        self.building.push(name.clone());
+        let dependencies = plugin
+            .dependencies()
+            .into_iter()
+            .try_for_each(|dependency| self.add_boxed_plugin(dependency, true));
+        self.building.pop();
+        dependencies?;
+
+        plugin.build(self);
+        self.plugins.push(name);
+        Ok(())
+    }
+
+    pub fn has_plugin(&self, name: &str) -> bool {
+        self.plugins.iter().any(|plugin| plugin == name)
+    }
+
+    /// The names of the added plugins, in build order
+    pub fn plugins(&self) -> &[String] {
+        &self.plugins
+    }
+
+    pub fn register_component<T: 'static + Component>(&mut self) -> &mut Self {
+        self.world.register_component::<T>();
+        self
+    }
+
+    pub fn register_system<T: 'static + System>(&mut self, system: T) -> &mut Self {
+        self.world.register_system(system);
+        self
+    }
+
+    pub fn init_state<S: 'static + Clone + PartialEq>(&mut self, initial: S) -> &mut Self {
+        self.world.init_state(initial);
+        self
+    }
+
+    pub fn register_system_in_state<S, T>(&mut self, state: S, system: T) -> &mut Self
+    where
+        S: 'static + Clone + PartialEq,
+        T: 'static + System,
+    {
+        self.world.register_system_in_state(state, system);
+        self
+    }
+
+    pub fn register_system_on_enter<S, T>(&mut self, state: S, system: T) -> &mut Self
+    where
+        S: 'static + Clone + PartialEq,
+        T: 'static + System,
+    {
+        self.world.register_system_on_enter(state, system);
+        self
+    }
+
+    pub fn register_system_on_exit<S, T>(&mut self, state: S, system: T) -> &mut Self
+    where
+        S: 'static + Clone + PartialEq,
+        T: 'static + System,
+    {
+        self.world.register_system_on_exit(state, system);
+        self
+    }
+
+    pub fn insert_resource<R: Resource>(&mut self, resource: R) -> &mut Self {
+        self.world.insert_resource(resource);
+        self
+    }
+
+    pub fn add_event<E: 'static>(&mut self) -> &mut Self {
+        self.world.add_event::<E>();
+        self
+    }
+
+    pub fn world(&self) -> &World {
+        &self.world
+    }
+
+    pub fn world_mut(&mut self) -> &mut World {
+        &mut self.world
+    }
+
+    pub fn into_world(self) -> World {
+        self.world
+    }
+
+    pub fn update(&mut self) {
+        self.world.update();
+    }
+
+    pub fn run_frame(&mut self, frame_time: Duration) -> u32 {
+        self.world.run_frame(frame_time)
+    }
+}
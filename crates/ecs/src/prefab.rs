@@ -0,0 +1,183 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::index::IndexError;
+use crate::resource::Resource;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PrefabError {
+    /// The prefab file is not valid
+    Parse(String),
+    UnknownPrefab(String),
+    /// The prefab extends itself, directly or not
+    Cycle(String),
+    /// The component is not registered or not `#[component(serializable)]`
+    UnknownComponent(String),
+    /// The component values do not match the component fields
+    InvalidComponent {
+        component: String,
+        error: String,
+    },
+    /// A component uses a key already used in a unique index
+    Index(IndexError),
+}
+
+impl fmt::Display for PrefabError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PrefabError::Parse(error) => write!(f, "cannot parse prefabs: {}", error),
+            PrefabError::UnknownPrefab(name) => write!(f, "unknown prefab: {}", name),
+            PrefabError::Cycle(name) => write!(f, "prefab {} extends itself", name),
+            PrefabError::UnknownComponent(name) => write!(f, "unknown component: {}", name),
+            PrefabError::InvalidComponent { component, error } => {
+                write!(f, "invalid component {}: {}", component, error)
+            }
+            PrefabError::Index(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for PrefabError {}
+
+/// A named set of components with their field values
+/// ```json
+/// {
+///     "assembling-machine-2": {
+///         "extends": "assembling-machine-1",
+///         "components": { "Crafter": { "speed": 0.75 }, "Burner": null }
+///     }
+/// }
+/// ```
+/// Component values are merged field by field with the extended prefab,
+/// a `null` component removes it.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Prefab {
+    #[serde(default)]
+    pub extends: Option<String>,
+    #[serde(default)]
+    pub components: Map<String, Value>,
+}
+
+// store all the prefab definitions, it is a resource of the world
+#[derive(Default)]
+pub struct Prefabs {
+    prefabs: HashMap<String, Prefab>,
+}
+
+impl Resource for Prefabs {
+    fn name() -> &'static str {
+        "Prefabs"
+    }
+}
+
+impl Prefabs {
+    pub fn new() -> Self {
+        Self {
+            prefabs: HashMap::new(),
+        }
+    }
+
+    /// Add the prefabs of a JSON document
+    /// A prefab that is already defined is patched (that's how mods change base prefabs)
+    pub fn load_json(&mut self, text: &str) -> Result<&mut Self, PrefabError> {
+        let prefabs: HashMap<String, Prefab> =
+            serde_json::from_str(text).map_err(|e| PrefabError::Parse(e.to_string()))?;
+        for (name, prefab) in prefabs {
+            self.insert(&name, prefab);
+        }
+        Ok(self)
+    }
+
+    pub fn insert(&mut self, name: &str, prefab: Prefab) -> &mut Self {
+        match self.prefabs.get_mut(name) {
+            Some(existing) => {
+                if prefab.extends.is_some() {
+                    existing.extends = prefab.extends;
+                }
+                patch_components(&mut existing.components, prefab.components);
+            }
+            None => {
+                self.prefabs.insert(name.to_string(), prefab);
+            }
+        }
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Prefab> {
+        self.prefabs.get(name)
+    }
+
+    /// The components of a prefab once merged with the prefabs it extends
+    pub fn resolve(&self, name: &str) -> Result<Map<String, Value>, PrefabError> {
+        // walk up the chain, then apply the prefabs from the root down
+        let mut chain = Vec::new();
+        let mut current = Some(name);
+        while let Some(prefab_name) = current {
+            if chain
+                .iter()
+                .any(|(chain_name, _)| *chain_name == prefab_name)
+            {
+                return Err(PrefabError::Cycle(name.to_string()));
+            }
+            let prefab = self
+                .get(prefab_name)
+                .ok_or_else(|| PrefabError::UnknownPrefab(prefab_name.to_string()))?;
+            chain.push((prefab_name, prefab));
+            current = prefab.extends.as_deref();
+        }
+
+        let mut components = Map::new();
+        for (_, prefab) in chain.into_iter().rev() {
+            merge_components(&mut components, prefab.components.clone());
+        }
+        Ok(components)
+    }
+}
+
+fn merge_components(components: &mut Map<String, Value>, patch: Map<String, Value>) {
+    for (name, value) in patch {
+        if value.is_null() {
+            components.remove(&name);
+            continue;
+        }
+        match components.get_mut(&name) {
+            Some(existing) => merge_value(existing, value),
+            None => {
+                components.insert(name, value);
+            }
+        }
+    }
+}
+
+// like `merge_components` but a `null` component is kept in the patched prefab,
+// it still removes the component inherited from the extended prefab
+fn patch_components(components: &mut Map<String, Value>, patch: Map<String, Value>) {
+    for (name, value) in patch {
+        match components.get_mut(&name) {
+            Some(existing) if !value.is_null() => merge_value(existing, value),
+            _ => {
+                components.insert(name, value);
+            }
+        }
+    }
+}
+
+// objects are merged key by key, anything else is replaced
+fn merge_value(base: &mut Value, patch: Value) {
+    match (base, patch) {
+        (Value::Object(base), Value::Object(patch)) => {
+            for (key, value) in patch {
+                match base.get_mut(&key) {
+                    Some(existing) => merge_value(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, patch) => *base = patch,
+    }
+}
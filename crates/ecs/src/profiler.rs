@@ -0,0 +1,206 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use serde_json::json;
+
+/// The queries run by a system during a tick
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct QueryStats {
+    pub queries: u32,
+    /// The number of entities returned by the queries
+    pub entities: usize,
+    pub time: Duration,
+}
+
+/// The cost of one system during one tick
+#[derive(Debug, Clone, PartialEq)]
+pub struct SystemSample {
+    pub name: String,
+    /// Since the start of the tick
+    pub start: Duration,
+    pub duration: Duration,
+    pub query_stats: QueryStats,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TickSample {
+    pub tick: u64,
+    /// Since the profiler was enabled
+    pub start: Duration,
+    pub duration: Duration,
+    pub systems: Vec<SystemSample>,
+}
+
+/// Rolling statistics of a system (or of the whole tick) over the profiler window
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimingStats {
+    pub name: String,
+    pub samples: usize,
+    pub average: Duration,
+    pub p50: Duration,
+    pub p95: Duration,
+    pub p99: Duration,
+    pub max: Duration,
+    pub average_entities: f64,
+    pub average_queries: f64,
+    pub average_query_time: Duration,
+}
+
+impl TimingStats {
+    fn new(name: &str, samples: &[(Duration, QueryStats)]) -> Self {
+        let mut durations: Vec<Duration> = samples.iter().map(|(duration, _)| *duration).collect();
+        durations.sort();
+        let count = samples.len().max(1);
+        let percentile = |p: usize| {
+            // nearest rank
+            let rank = (p * durations.len()).div_ceil(100).max(1);
+            durations.get(rank - 1).copied().unwrap_or_default()
+        };
+
+        Self {
+            name: name.to_string(),
+            samples: samples.len(),
+            average: durations.iter().sum::<Duration>() / count as u32,
+            p50: percentile(50),
+            p95: percentile(95),
+            p99: percentile(99),
+            max: durations.last().copied().unwrap_or_default(),
+            average_entities: samples.iter().map(|(_, q)| q.entities as f64).sum::<f64>()
+                / count as f64,
+            average_queries: samples.iter().map(|(_, q)| q.queries as f64).sum::<f64>()
+                / count as f64,
+            average_query_time: samples.iter().map(|(_, q)| q.time).sum::<Duration>()
+                / count as u32,
+        }
+    }
+}
+
+/// Record the time spent in each system, see `World::enable_profiler`
+/// Only the last `window` ticks are kept
+pub struct Profiler {
+    window: usize,
+    origin: Instant,
+    next_tick: u64,
+    ticks: VecDeque<TickSample>,
+}
+
+impl Profiler {
+    pub fn new(window: usize) -> Self {
+        Self {
+            window: window.max(1),
+            origin: Instant::now(),
+            next_tick: 0,
+            ticks: VecDeque::new(),
+        }
+    }
+
+    pub(crate) fn record_tick(
+        &mut self,
+        start: Instant,
+        duration: Duration,
+        systems: Vec<SystemSample>,
+    ) {
+        if self.ticks.len() == self.window {
+            self.ticks.pop_front();
+        }
+        self.ticks.push_back(TickSample {
+            tick: self.next_tick,
+            start: start.duration_since(self.origin),
+            duration,
+            systems,
+        });
+        self.next_tick += 1;
+    }
+
+    pub fn ticks(&self) -> impl Iterator<Item = &TickSample> {
+        self.ticks.iter()
+    }
+
+    pub fn last_tick(&self) -> Option<&TickSample> {
+        self.ticks.back()
+    }
+
+    /// Statistics of the whole tick, the queries of every system are summed
+    pub fn tick_stats(&self) -> TimingStats {
+        let samples: Vec<(Duration, QueryStats)> = self
+            .ticks
+            .iter()
+            .map(|tick| {
+                let queries = tick
+                    .systems
+                    .iter()
+                    .fold(QueryStats::default(), |total, system| QueryStats {
+                        queries: total.queries + system.query_stats.queries,
+                        entities: total.entities + system.query_stats.entities,
+                        time: total.time + system.query_stats.time,
+                    });
+                (tick.duration, queries)
+            })
+            .collect();
+        TimingStats::new("tick", &samples)
+    }
+
+    /// Statistics of each system, in registration order
+    pub fn system_stats(&self) -> Vec<TimingStats> {
+        let mut names: Vec<&str> = Vec::new();
+        for tick in self.ticks.iter() {
+            for system in tick.systems.iter() {
+                if !names.contains(&system.name.as_str()) {
+                    names.push(&system.name);
+                }
+            }
+        }
+
+        names
+            .into_iter()
+            .map(|name| {
+                let samples: Vec<(Duration, QueryStats)> = self
+                    .ticks
+                    .iter()
+                    .flat_map(|tick| tick.systems.iter())
+                    .filter(|system| system.name == name)
+                    .map(|system| (system.duration, system.query_stats))
+                    .collect();
+                TimingStats::new(name, &samples)
+            })
+            .collect()
+    }
+
+    /// Export the recorded ticks in the Chrome trace event format,
+    /// open it with `chrome://tracing` or Perfetto
+    pub fn to_chrome_trace(&self) -> String {
+        let micros = |duration: Duration| duration.as_secs_f64() * 1_000_000.0;
+
+        let mut events = Vec::new();
+        for tick in self.ticks.iter() {
+            events.push(json!({
+                "name": "tick",
+                "cat": "tick",
+                "ph": "X",
+                "ts": micros(tick.start),
+                "dur": micros(tick.duration),
+                "pid": 1,
+                "tid": 1,
+                "args": { "tick": tick.tick },
+            }));
+            for system in tick.systems.iter() {
+                events.push(json!({
+                    "name": system.name,
+                    "cat": "system",
+                    "ph": "X",
+                    "ts": micros(tick.start + system.start),
+                    "dur": micros(system.duration),
+                    "pid": 1,
+                    "tid": 1,
+                    "args": {
+                        "entities": system.query_stats.entities,
+                        "queries": system.query_stats.queries,
+                        "query_time_us": micros(system.query_stats.time),
+                    },
+                }));
+            }
+        }
+
+        json!({ "traceEvents": events, "displayTimeUnit": "ms" }).to_string()
+    }
+}
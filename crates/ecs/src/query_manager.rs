@@ -1,27 +1,42 @@
 use std::any::TypeId;
 use std::collections::HashMap;
 
+use crate::dynamic::ComponentId;
+
+#[derive(Clone)]
 pub struct QueryManager {
     /// An entity is represented by a bitmask of components
-    /// The first u128 is the bitmask of the components that the entity has
-    /// The second u128 is the ID of the entity
-    /// if bit_query & bit_entity != 0, then the entity matches the query
+    /// Entities with the same bitmask are stored in the same group
+    /// if bit_query & bit_entity == bit_query, then the entity matches the query
     query_entities: Vec<(u128, Vec<usize>)>,
-    entities_query: HashMap<usize, u128>,
+    // find the group of a bitmask without scanning every group
+    group_index: HashMap<u128, usize>,
+    // the group and the row in the group of each entity
+    entities_query: HashMap<usize, (usize, usize)>,
     bit_mapping: HashMap<TypeId, u128>,
+    dynamic_bit_mapping: HashMap<ComponentId, u128>,
     reusable_bits: Vec<u128>,
     next_bit: u128,
     /// The query cache is a map of bitmask to the entities that match the query
     /// The value is None if the query is not cached, otherwise it is the entities that match the query
+    #[allow(dead_code)]
     query_cache: HashMap<u128, Option<Vec<usize>>>,
 }
 
+impl Default for QueryManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl QueryManager {
     pub fn new() -> Self {
         Self {
             query_entities: Vec::new(),
+            group_index: HashMap::new(),
             entities_query: HashMap::new(),
             bit_mapping: HashMap::new(),
+            dynamic_bit_mapping: HashMap::new(),
             next_bit: 1,
             reusable_bits: Vec::new(),
             query_cache: HashMap::new(),
@@ -33,27 +48,58 @@ impl QueryManager {
     /// (at most 128 bits are allocated TODO: handle this)
     /// Will return the bit for the component
     pub fn register_component<T: 'static>(&mut self) -> &mut Self {
-        let type_id = TypeId::of::<T>();
+        let bit = self.allocate_bit();
+        self.bit_mapping.insert(TypeId::of::<T>(), bit);
+        self
+    }
+
+    /// Unregister a component
+    /// The bit is stripped from every entity signature (entities are regrouped
+    /// accordingly) before being made available for reuse
+    /// Will panic if the component is not registered
+    pub fn unregister_component<T: 'static>(&mut self) -> &mut Self {
+        let bit = self.bit_mapping.remove(&TypeId::of::<T>()).unwrap();
+        self.release_bit(bit);
+        self
+    }
 
-        let bit = if self.reusable_bits.len() > 0 {
+    /// Register a component defined at runtime, it shares the bits with the static ones
+    pub fn register_dynamic_component(&mut self, id: ComponentId) -> &mut Self {
+        let bit = self.allocate_bit();
+        self.dynamic_bit_mapping.insert(id, bit);
+        self
+    }
+
+    /// Will panic if the component is not registered
+    pub fn unregister_dynamic_component(&mut self, id: ComponentId) -> &mut Self {
+        let bit = self.dynamic_bit_mapping.remove(&id).unwrap();
+        self.release_bit(bit);
+        self
+    }
+
+    fn allocate_bit(&mut self) -> u128 {
+        if !self.reusable_bits.is_empty() {
             self.reusable_bits.remove(0)
         } else {
             let old_next_bit = self.next_bit;
             self.next_bit *= 2;
             old_next_bit
-        };
-
-        self.bit_mapping.insert(type_id, bit);
-        self
+        }
     }
 
-    /// Unregister a component
-    /// Will panic if the component is not registered
-    pub fn unregister_component<T: 'static>(&mut self) -> &mut Self {
-        let type_id = TypeId::of::<T>();
-        let bit = self.bit_mapping.remove(&type_id).unwrap();
+    fn release_bit(&mut self, bit: u128) {
+        let query_entities = std::mem::take(&mut self.query_entities);
+        self.group_index.clear();
+        self.entities_query.clear();
+        for (bitmask, ids) in query_entities {
+            let new_bitmask = bitmask & !bit;
+            // the entities with no component left do not belong to any group
+            if new_bitmask != 0 {
+                self.add_entities(&ids, new_bitmask);
+            }
+        }
+
         self.reusable_bits.push(bit);
-        self
     }
 
     /// Get the bits for a component
@@ -63,60 +109,120 @@ impl QueryManager {
         self.bit_mapping.get(&type_id)
     }
 
+    pub fn get_bit_for_type_id(&self, type_id: TypeId) -> Option<&u128> {
+        self.bit_mapping.get(&type_id)
+    }
+
+    pub fn bit_mapping(&self) -> &HashMap<TypeId, u128> {
+        &self.bit_mapping
+    }
+
+    pub fn get_bit_for_dynamic_component(&self, id: ComponentId) -> Option<&u128> {
+        self.dynamic_bit_mapping.get(&id)
+    }
+
+    pub fn dynamic_bit_mapping(&self) -> &HashMap<ComponentId, u128> {
+        &self.dynamic_bit_mapping
+    }
+
     pub fn get_bitmask_for_entity(&self, entity_id: usize) -> u128 {
-        let bitmask = self.entities_query.get(&entity_id);
-        if let Some(bitmask) = bitmask {
-            return bitmask.clone();
+        match self.entities_query.get(&entity_id) {
+            Some((group, _)) => self.query_entities[*group].0,
+            None => 0,
         }
-        0
     }
 
+    /// Remove an entity from its group, the last entity of the group takes its row
     pub fn remove_entity(&mut self, entity_id: usize) -> &mut Self {
-        let index = self
-            .query_entities
-            .iter()
-            .position(|(_, ids)| ids.contains(&entity_id));
-        if let Some(index) = index {
-            self.query_entities[index].1.retain(|id| *id != entity_id);
+        let Some((group, row)) = self.entities_query.remove(&entity_id) else {
+            return self;
+        };
+
+        let ids = &mut self.query_entities[group].1;
+        ids.swap_remove(row);
+        if let Some(moved_id) = ids.get(row) {
+            self.entities_query.insert(*moved_id, (group, row));
         }
 
-        self.entities_query.remove(&entity_id);
+        self
+    }
+
+    pub fn remove_entities(&mut self, entities_ids: &[usize]) -> &mut Self {
+        for entity_id in entities_ids {
+            self.remove_entity(*entity_id);
+        }
+        self
+    }
 
+    /// Forget every entity, registered components keep their bits
+    pub fn clear_entities(&mut self) -> &mut Self {
+        self.query_entities.clear();
+        self.group_index.clear();
+        self.entities_query.clear();
         self
     }
 
+    /// Add an entity to the group of its bitmask
+    /// The entity must not be part of a group already
     pub fn add_entity(&mut self, entity_id: usize, entity_bitmask: u128) -> &mut Self {
-        let index = self
-            .query_entities
-            .iter()
-            .position(|(bitmask, _)| *bitmask == entity_bitmask);
-        if let Some(index) = index {
-            self.query_entities[index].1.push(entity_id);
-        } else {
-            self.query_entities.push((entity_bitmask, vec![entity_id]));
-        }
+        self.add_entities(&[entity_id], entity_bitmask)
+    }
 
-        self.entities_query.insert(entity_id, entity_bitmask);
+    /// Add several entities with the same signature
+    /// The entities must not be part of a group already
+    pub fn add_entities(&mut self, entities_ids: &[usize], entities_bitmask: u128) -> &mut Self {
+        let group = self.group_mut(entities_bitmask);
+        let ids = &mut self.query_entities[group].1;
+        let first_row = ids.len();
+        ids.extend_from_slice(entities_ids);
+
+        self.entities_query.reserve(entities_ids.len());
+        for (row, entity_id) in entities_ids.iter().enumerate() {
+            self.entities_query
+                .insert(*entity_id, (group, first_row + row));
+        }
 
         self
     }
 
+    // the index of the group of a bitmask, created if needed
+    fn group_mut(&mut self, bitmask: u128) -> usize {
+        if let Some(group) = self.group_index.get(&bitmask) {
+            return *group;
+        }
+
+        self.query_entities.push((bitmask, Vec::new()));
+        let group = self.query_entities.len() - 1;
+        self.group_index.insert(bitmask, group);
+        group
+    }
+
+    /// The signature groups, each bitmask with its entities
+    pub fn groups(&self) -> impl Iterator<Item = (u128, &[usize])> {
+        self.query_entities
+            .iter()
+            .map(|(bitmask, entities)| (*bitmask, entities.as_slice()))
+    }
+
+    /// The entities that match the bitmask, group by group,
+    /// the large groups are split in batches of at most `batch_size` entities
+    pub fn query_batches(&self, query_bitmask: u128, batch_size: usize) -> Vec<&[usize]> {
+        self.query_entities
+            .iter()
+            .filter(|(bitmask, _)| *bitmask & query_bitmask == query_bitmask)
+            .flat_map(|(_, ids)| ids.chunks(batch_size.max(1)))
+            .collect()
+    }
+
     /// Query the entities that match the bitmask
     pub fn query(&self, query_bitmask: u128) -> Option<Vec<usize>> {
-        // if self.query_cache.contains_key(&bitmask) {
-        //     return self.query_cache.get(&bitmask).unwrap().clone();
-        // }
-
         let entities = self
             .query_entities
             .iter()
             .filter(|(bitmask, _)| *bitmask & query_bitmask == query_bitmask)
-            .map(|(_, ids)| ids.clone())
-            .flatten()
+            .flat_map(|(_, ids)| ids.clone())
             .collect();
 
-        // self.query_cache.insert(bitmask, Some(entities.clone()));
-
         Some(entities)
     }
 }
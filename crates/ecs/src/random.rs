@@ -0,0 +1,208 @@
+use std::collections::BTreeMap;
+use std::hash::Hasher;
+use std::ops::Range;
+
+use ecs_macros::Resource;
+use serde::{Deserialize, Serialize};
+
+use crate::state_hash::StateHasher;
+
+/// A deterministic random generator (xoshiro256**)
+/// The same seed gives the same numbers on every platform and Rust version
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Rng {
+    state: [u64; 4],
+}
+
+// expand a seed into the generator state, as recommended by the xoshiro authors
+fn split_mix(seed: &mut u64) -> u64 {
+    *seed = seed.wrapping_add(0x9e3779b97f4a7c15);
+    let mut z = *seed;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        let mut seed = seed;
+        Self {
+            state: [
+                split_mix(&mut seed),
+                split_mix(&mut seed),
+                split_mix(&mut seed),
+                split_mix(&mut seed),
+            ],
+        }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let [s0, s1, s2, s3] = &mut self.state;
+        let result = s1.wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let t = *s1 << 17;
+        *s2 ^= *s0;
+        *s3 ^= *s1;
+        *s1 ^= *s2;
+        *s0 ^= *s3;
+        *s2 ^= t;
+        *s3 = s3.rotate_left(45);
+        result
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    /// Between 0 (included) and 1 (excluded)
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 * (1.0 / (1u64 << 53) as f64)
+    }
+
+    /// Between 0 (included) and 1 (excluded)
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 * (1.0 / (1u32 << 24) as f32)
+    }
+
+    /// Between 0 (included) and `n` (excluded), without bias
+    /// Panics if `n` is 0
+    pub fn below(&mut self, n: u64) -> u64 {
+        assert!(n > 0, "Rng::below called with 0");
+        // Lemire's method: reject the few values which would favor the low results
+        let mut m = self.next_u64() as u128 * n as u128;
+        if (m as u64) < n {
+            let threshold = n.wrapping_neg() % n;
+            while (m as u64) < threshold {
+                m = self.next_u64() as u128 * n as u128;
+            }
+        }
+        (m >> 64) as u64
+    }
+
+    /// Panics if the range is empty
+    pub fn range(&mut self, range: Range<i64>) -> i64 {
+        assert!(
+            range.start < range.end,
+            "Rng::range called with an empty range"
+        );
+        let span = range.end.wrapping_sub(range.start) as u64;
+        range.start.wrapping_add(self.below(span) as i64)
+    }
+
+    /// True with the probability `p`, between 0 and 1
+    pub fn chance(&mut self, p: f64) -> bool {
+        self.next_f64() < p
+    }
+
+    pub fn pick<'a, T>(&mut self, items: &'a [T]) -> Option<&'a T> {
+        if items.is_empty() {
+            return None;
+        }
+        items.get(self.below(items.len() as u64) as usize)
+    }
+
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            let j = self.below(i as u64 + 1) as usize;
+            items.swap(i, j);
+        }
+    }
+
+    fn hash(&self, hasher: &mut StateHasher) {
+        for word in self.state {
+            hasher.write_u64(word);
+        }
+    }
+}
+
+/// The random generators of the simulation, a resource inserted by `World::new`
+/// Every system draws from its own stream, named by a label, and can have a stream
+/// per entity: each stream is derived from the seed and its name only, so adding
+/// a system or an entity does not change the numbers seen by the others.
+/// The streams are saved and part of the state hash.
+#[derive(Resource, Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[resource(serializable)]
+pub struct Random {
+    seed: u64,
+    streams: BTreeMap<String, Rng>,
+    // by label, then by entity id
+    entity_streams: BTreeMap<String, BTreeMap<usize, Rng>>,
+}
+
+impl Default for Random {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            streams: BTreeMap::new(),
+            entity_streams: BTreeMap::new(),
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// The stream of a system (`"biter-spawns"`, `"machine-failures"`...),
+    /// created from the seed the first time it is used
+    pub fn stream(&mut self, label: &str) -> &mut Rng {
+        let seed = self.seed;
+        self.streams
+            .entry(label.to_string())
+            .or_insert_with(|| Rng::new(derive_seed(seed, label, None)))
+    }
+
+    /// The stream of an entity for a system, independent of the other entities
+    pub fn entity_stream(&mut self, label: &str, entity_id: usize) -> &mut Rng {
+        let seed = self.seed;
+        self.entity_streams
+            .entry(label.to_string())
+            .or_default()
+            .entry(entity_id)
+            .or_insert_with(|| Rng::new(derive_seed(seed, label, Some(entity_id))))
+    }
+
+    /// Drop the streams of a removed entity, the entity reusing its id
+    /// starts the streams from the beginning
+    pub fn forget_entity(&mut self, entity_id: usize) {
+        for streams in self.entity_streams.values_mut() {
+            streams.remove(&entity_id);
+        }
+        self.entity_streams.retain(|_, streams| !streams.is_empty());
+    }
+
+    pub(crate) fn hash(&self, hasher: &mut StateHasher) {
+        hasher.write_u64(self.seed);
+        // the labels are prefixed with their length, as in `derive_seed`,
+        // so ("ab", "c") and ("a", "bc") hash differently
+        for (label, rng) in self.streams.iter() {
+            hasher.write_usize(label.len());
+            hasher.write(label.as_bytes());
+            rng.hash(hasher);
+        }
+        for (label, streams) in self.entity_streams.iter() {
+            hasher.write_usize(label.len());
+            hasher.write(label.as_bytes());
+            for (entity_id, rng) in streams.iter() {
+                hasher.write_usize(*entity_id);
+                rng.hash(hasher);
+            }
+        }
+    }
+}
+
+// the seed of a stream, stable across platforms
+fn derive_seed(seed: u64, label: &str, entity_id: Option<usize>) -> u64 {
+    let mut hasher = StateHasher::new();
+    hasher.write_u64(seed);
+    hasher.write_usize(label.len());
+    hasher.write(label.as_bytes());
+    if let Some(entity_id) = entity_id {
+        hasher.write_usize(entity_id);
+    }
+    hasher.finish()
+}
@@ -0,0 +1,397 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::Hasher;
+
+use crate::state_hash::StateHasher;
+
+/// Describe a field of a reflected type
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldInfo {
+    pub name: &'static str,
+    pub type_name: &'static str,
+}
+
+/// Describe a reflected type
+/// `name` is the short name of the type (ex: `Position`),
+/// `type_path` is the full path given by `std::any::type_name`
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeInfo {
+    pub name: &'static str,
+    pub type_path: &'static str,
+    pub type_id: TypeId,
+    pub size: usize,
+    pub fields: Vec<FieldInfo>,
+}
+
+impl TypeInfo {
+    pub fn of<T: 'static>(name: &'static str, fields: Vec<FieldInfo>) -> Self {
+        Self {
+            name,
+            type_path: std::any::type_name::<T>(),
+            type_id: TypeId::of::<T>(),
+            size: std::mem::size_of::<T>(),
+            fields,
+        }
+    }
+
+    pub fn field(&self, name: &str) -> Option<&FieldInfo> {
+        self.fields.iter().find(|field| field.name == name)
+    }
+}
+
+/// A primitive value read from or written to a reflected field
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Bool(bool),
+    Int(i64),
+    UInt(u64),
+    Float(f64),
+    String(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReflectError {
+    /// The field does not exist on the type
+    FieldNotFound(String),
+    /// The type is a struct, its fields must be accessed one by one
+    NotAValue(&'static str),
+    /// The value cannot be stored in the field
+    TypeMismatch {
+        expected: &'static str,
+        found: Value,
+    },
+}
+
+impl fmt::Display for ReflectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReflectError::FieldNotFound(path) => write!(f, "field not found: {}", path),
+            ReflectError::NotAValue(type_name) => {
+                write!(f, "type {} is not a primitive value", type_name)
+            }
+            ReflectError::TypeMismatch { expected, found } => {
+                write!(f, "cannot store {:?} in a {}", found, expected)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ReflectError {}
+
+/// Runtime access to the structure of a type
+/// Implemented by `#[derive(Component)]` and `#[derive(Reflect)]`,
+/// the fields marked with `#[reflect(ignore)]` are left out (not accessible and not hashed)
+pub trait Reflect: Any {
+    fn type_info() -> TypeInfo
+    where
+        Self: Sized;
+
+    fn reflect_type_name(&self) -> &'static str {
+        std::any::type_name::<Self>()
+    }
+
+    fn field(&self, _name: &str) -> Option<&dyn Reflect> {
+        None
+    }
+
+    fn field_mut(&mut self, _name: &str) -> Option<&mut dyn Reflect> {
+        None
+    }
+
+    /// Only primitive types have a value, structs return None
+    fn get_value(&self) -> Option<Value> {
+        None
+    }
+
+    fn set_value(&mut self, _value: Value) -> Result<(), ReflectError> {
+        Err(ReflectError::NotAValue(self.reflect_type_name()))
+    }
+
+    /// Feed the value to a hasher, field by field
+    /// The result must not depend on the platform (see `StateHasher`)
+    fn reflect_hash(&self, state: &mut dyn Hasher);
+}
+
+impl dyn Reflect {
+    pub fn downcast_ref<T: Reflect>(&self) -> Option<&T> {
+        (self as &dyn Any).downcast_ref::<T>()
+    }
+
+    pub fn downcast_mut<T: Reflect>(&mut self) -> Option<&mut T> {
+        (self as &mut dyn Any).downcast_mut::<T>()
+    }
+
+    /// Follow a dot separated field path (ex: `position.x`)
+    /// An empty path returns the value itself
+    pub fn path(&self, path: &str) -> Result<&dyn Reflect, ReflectError> {
+        if path.is_empty() {
+            return Ok(self);
+        }
+
+        let mut current = self;
+        for name in path.split('.') {
+            current = current
+                .field(name)
+                .ok_or_else(|| ReflectError::FieldNotFound(path.to_string()))?;
+        }
+        Ok(current)
+    }
+
+    pub fn path_mut(&mut self, path: &str) -> Result<&mut dyn Reflect, ReflectError> {
+        if path.is_empty() {
+            return Ok(self);
+        }
+
+        let mut current = self;
+        for name in path.split('.') {
+            current = current
+                .field_mut(name)
+                .ok_or_else(|| ReflectError::FieldNotFound(path.to_string()))?;
+        }
+        Ok(current)
+    }
+
+    pub fn get_path(&self, path: &str) -> Result<Value, ReflectError> {
+        let field = self.path(path)?;
+        field
+            .get_value()
+            .ok_or(ReflectError::NotAValue(field.reflect_type_name()))
+    }
+
+    pub fn set_path(&mut self, path: &str, value: Value) -> Result<(), ReflectError> {
+        self.path_mut(path)?.set_value(value)
+    }
+}
+
+macro_rules! impl_reflect_int {
+    ($variant:ident, $wide:ty, $write:ident, $($ty:ty),*) => {
+        $(
+            impl Reflect for $ty {
+                fn type_info() -> TypeInfo {
+                    TypeInfo::of::<Self>(stringify!($ty), Vec::new())
+                }
+
+                fn get_value(&self) -> Option<Value> {
+                    Some(Value::$variant(*self as $wide))
+                }
+
+                // always hashed on 64 bits so usize is the same on wasm32 and x86_64
+                fn reflect_hash(&self, state: &mut dyn Hasher) {
+                    state.$write(*self as $wide);
+                }
+
+                fn set_value(&mut self, value: Value) -> Result<(), ReflectError> {
+                    let converted = match &value {
+                        Value::Int(v) => <$ty>::try_from(*v).ok(),
+                        Value::UInt(v) => <$ty>::try_from(*v).ok(),
+                        _ => None,
+                    };
+                    match converted {
+                        Some(v) => {
+                            *self = v;
+                            Ok(())
+                        }
+                        None => Err(ReflectError::TypeMismatch {
+                            expected: stringify!($ty),
+                            found: value,
+                        }),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_reflect_int!(Int, i64, write_i64, i8, i16, i32, i64, isize);
+impl_reflect_int!(UInt, u64, write_u64, u8, u16, u32, u64, usize);
+
+macro_rules! impl_reflect_float {
+    ($($ty:ty),*) => {
+        $(
+            impl Reflect for $ty {
+                fn type_info() -> TypeInfo {
+                    TypeInfo::of::<Self>(stringify!($ty), Vec::new())
+                }
+
+                fn get_value(&self) -> Option<Value> {
+                    Some(Value::Float(*self as f64))
+                }
+
+                fn reflect_hash(&self, state: &mut dyn Hasher) {
+                    state.write(&self.to_le_bytes());
+                }
+
+                fn set_value(&mut self, value: Value) -> Result<(), ReflectError> {
+                    *self = match value {
+                        Value::Float(v) => v as $ty,
+                        Value::Int(v) => v as $ty,
+                        Value::UInt(v) => v as $ty,
+                        _ => {
+                            return Err(ReflectError::TypeMismatch {
+                                expected: stringify!($ty),
+                                found: value,
+                            })
+                        }
+                    };
+                    Ok(())
+                }
+            }
+        )*
+    };
+}
+
+impl_reflect_float!(f32, f64);
+
+impl Reflect for bool {
+    fn type_info() -> TypeInfo {
+        TypeInfo::of::<Self>("bool", Vec::new())
+    }
+
+    fn get_value(&self) -> Option<Value> {
+        Some(Value::Bool(*self))
+    }
+
+    fn reflect_hash(&self, state: &mut dyn Hasher) {
+        state.write_u8(*self as u8);
+    }
+
+    fn set_value(&mut self, value: Value) -> Result<(), ReflectError> {
+        match value {
+            Value::Bool(v) => {
+                *self = v;
+                Ok(())
+            }
+            _ => Err(ReflectError::TypeMismatch {
+                expected: "bool",
+                found: value,
+            }),
+        }
+    }
+}
+
+impl Reflect for String {
+    fn type_info() -> TypeInfo {
+        TypeInfo::of::<Self>("String", Vec::new())
+    }
+
+    fn get_value(&self) -> Option<Value> {
+        Some(Value::String(self.clone()))
+    }
+
+    fn reflect_hash(&self, state: &mut dyn Hasher) {
+        state.write_u64(self.len() as u64);
+        state.write(self.as_bytes());
+    }
+
+    fn set_value(&mut self, value: Value) -> Result<(), ReflectError> {
+        match value {
+            Value::String(v) => {
+                *self = v;
+                Ok(())
+            }
+            _ => Err(ReflectError::TypeMismatch {
+                expected: "String",
+                found: value,
+            }),
+        }
+    }
+}
+
+// elements are accessed by their index (ex: `items.0`)
+impl<T: Reflect> Reflect for Vec<T> {
+    fn type_info() -> TypeInfo {
+        TypeInfo::of::<Self>("Vec", Vec::new())
+    }
+
+    fn field(&self, name: &str) -> Option<&dyn Reflect> {
+        let index = name.parse::<usize>().ok()?;
+        self.get(index).map(|item| item as &dyn Reflect)
+    }
+
+    fn field_mut(&mut self, name: &str) -> Option<&mut dyn Reflect> {
+        let index = name.parse::<usize>().ok()?;
+        self.get_mut(index).map(|item| item as &mut dyn Reflect)
+    }
+
+    fn reflect_hash(&self, state: &mut dyn Hasher) {
+        state.write_u64(self.len() as u64);
+        for item in self {
+            item.reflect_hash(state);
+        }
+    }
+}
+
+// the value is accessed with `0` (ex: `target.0.x`), like a tuple
+impl<T: Reflect> Reflect for Option<T> {
+    fn type_info() -> TypeInfo {
+        TypeInfo::of::<Self>("Option", Vec::new())
+    }
+
+    fn field(&self, name: &str) -> Option<&dyn Reflect> {
+        match (name, self) {
+            ("0", Some(value)) => Some(value as &dyn Reflect),
+            _ => None,
+        }
+    }
+
+    fn field_mut(&mut self, name: &str) -> Option<&mut dyn Reflect> {
+        match (name, self) {
+            ("0", Some(value)) => Some(value as &mut dyn Reflect),
+            _ => None,
+        }
+    }
+
+    fn reflect_hash(&self, state: &mut dyn Hasher) {
+        match self {
+            None => state.write_u8(0),
+            Some(value) => {
+                state.write_u8(1);
+                value.reflect_hash(state);
+            }
+        }
+    }
+}
+
+// entries are accessed by their key when it is a primitive value (ex: `stock.iron`)
+impl<K: Reflect, V: Reflect> Reflect for HashMap<K, V> {
+    fn type_info() -> TypeInfo {
+        TypeInfo::of::<Self>("HashMap", Vec::new())
+    }
+
+    fn field(&self, name: &str) -> Option<&dyn Reflect> {
+        self.iter()
+            .find(|(key, _)| key_matches(*key, name))
+            .map(|(_, value)| value as &dyn Reflect)
+    }
+
+    fn field_mut(&mut self, name: &str) -> Option<&mut dyn Reflect> {
+        self.iter_mut()
+            .find(|(key, _)| key_matches(*key, name))
+            .map(|(_, value)| value as &mut dyn Reflect)
+    }
+
+    // the iteration order is random, every entry is hashed on its own
+    // and the results are summed so the order does not matter
+    fn reflect_hash(&self, state: &mut dyn Hasher) {
+        let mut sum = 0u64;
+        for (key, value) in self {
+            let mut entry = StateHasher::new();
+            key.reflect_hash(&mut entry);
+            value.reflect_hash(&mut entry);
+            sum = sum.wrapping_add(entry.finish());
+        }
+        state.write_u64(self.len() as u64);
+        state.write_u64(sum);
+    }
+}
+
+fn key_matches(key: &dyn Reflect, name: &str) -> bool {
+    match key.get_value() {
+        Some(Value::String(key)) => key == name,
+        Some(Value::Int(key)) => name.parse() == Ok(key),
+        Some(Value::UInt(key)) => name.parse() == Ok(key),
+        Some(Value::Bool(key)) => name.parse() == Ok(key),
+        _ => false,
+    }
+}
@@ -0,0 +1,126 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+
+use crate::serialization::{ResourceData, ResourceSerializer, SaveError};
+
+/// A resource is a unique value stored in the world, outside of any entity
+/// (ex: the game time, the random generator...)
+pub trait Resource: Any {
+    fn name() -> &'static str
+    where
+        Self: Sized,
+    {
+        std::any::type_name::<Self>()
+    }
+
+    /// Set by `#[resource(serializable)]`, resources without it are not saved
+    fn serializer() -> Option<ResourceSerializer>
+    where
+        Self: Sized,
+    {
+        None
+    }
+}
+
+/// Resources deserialized from a save, not yet stored
+pub type DecodedResources = Vec<(TypeId, Box<dyn Any>)>;
+
+struct ResourceEntry {
+    name: &'static str,
+    value: Box<dyn Any>,
+    serializer: Option<ResourceSerializer>,
+}
+
+// store all the resources, one value per type
+#[derive(Default)]
+pub struct Resources {
+    resources: HashMap<TypeId, ResourceEntry>,
+}
+
+impl Resources {
+    pub fn new() -> Self {
+        Self {
+            resources: HashMap::new(),
+        }
+    }
+
+    /// Insert a resource, replacing the previous value of the same type
+    pub fn insert<R: Resource>(&mut self, resource: R) {
+        self.resources.insert(
+            TypeId::of::<R>(),
+            ResourceEntry {
+                name: R::name(),
+                value: Box::new(resource),
+                serializer: R::serializer(),
+            },
+        );
+    }
+
+    pub fn remove<R: Resource>(&mut self) -> Option<R> {
+        let entry = self.resources.remove(&TypeId::of::<R>())?;
+        entry.value.downcast::<R>().ok().map(|resource| *resource)
+    }
+
+    pub fn has<R: Resource>(&self) -> bool {
+        self.resources.contains_key(&TypeId::of::<R>())
+    }
+
+    pub fn borrow<R: Resource>(&self) -> Option<&R> {
+        self.resources
+            .get(&TypeId::of::<R>())
+            .and_then(|entry| entry.value.downcast_ref::<R>())
+    }
+
+    pub fn borrow_mut<R: Resource>(&mut self) -> Option<&mut R> {
+        self.resources
+            .get_mut(&TypeId::of::<R>())
+            .and_then(|entry| entry.value.downcast_mut::<R>())
+    }
+
+    /// Serialize every serializable resource, sorted by name
+    pub fn save(&self) -> Result<Vec<ResourceData>, SaveError> {
+        let mut data = self
+            .resources
+            .values()
+            .filter_map(|entry| {
+                let serializer = entry.serializer?;
+                Some(
+                    (serializer.serialize)(entry.value.as_ref()).map(|value| ResourceData {
+                        name: entry.name.to_string(),
+                        value,
+                    }),
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        data.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(data)
+    }
+
+    /// Deserialize saved resources without storing them (see `replace`)
+    /// A resource must already be inserted to be loaded, that's how its type is known
+    pub fn decode(&self, data: Vec<ResourceData>) -> Result<DecodedResources, SaveError> {
+        data.into_iter()
+            .map(|resource| {
+                let (type_id, serializer) = self
+                    .resources
+                    .iter()
+                    .find_map(|(type_id, entry)| match entry.serializer {
+                        Some(serializer) if entry.name == resource.name => {
+                            Some((*type_id, serializer))
+                        }
+                        _ => None,
+                    })
+                    .ok_or_else(|| SaveError::UnknownResource(resource.name.clone()))?;
+                Ok((type_id, (serializer.deserialize)(resource.value)?))
+            })
+            .collect()
+    }
+
+    pub fn replace(&mut self, values: DecodedResources) {
+        for (type_id, value) in values {
+            if let Some(entry) = self.resources.get_mut(&type_id) {
+                entry.value = value;
+            }
+        }
+    }
+}
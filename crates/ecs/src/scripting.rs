@@ -0,0 +1,528 @@
+use std::any::Any;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+
+use rhai::{AST, Array, CallFnOptions, Dynamic, Engine, EvalAltResult, FLOAT, INT, Map, Scope};
+
+use crate::entity_manager::EntityManager;
+use crate::event::{EventReader, Events};
+use crate::reflect::{Reflect, Value};
+use crate::resource::Resource;
+use crate::system::System;
+
+type ScriptResult<T> = Result<T, Box<EvalAltResult>>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScriptError {
+    /// The script is not valid
+    Compile(String),
+    /// The script has no function with this name
+    MissingFunction(String),
+    /// A script system failed during an update
+    Runtime { function: String, error: String },
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScriptError::Compile(error) => write!(f, "cannot compile script: {}", error),
+            ScriptError::MissingFunction(name) => write!(f, "unknown script function: {}", name),
+            ScriptError::Runtime { function, error } => {
+                write!(f, "script function {} failed: {}", function, error)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ScriptError {}
+
+/// An event sent by a script with `world.emit(name)` or `world.emit(name, value)`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScriptEvent {
+    pub name: String,
+    pub value: Option<Value>,
+}
+
+/// The events sent by the scripts, it is a resource of the world
+/// The game reads them after the update with `drain`
+#[derive(Debug, Default)]
+pub struct ScriptEvents {
+    events: Vec<ScriptEvent>,
+}
+
+impl Resource for ScriptEvents {
+    fn name() -> &'static str {
+        "ScriptEvents"
+    }
+}
+
+impl ScriptEvents {
+    pub fn new() -> Self {
+        Self { events: Vec::new() }
+    }
+
+    pub fn push(&mut self, event: ScriptEvent) {
+        self.events.push(event);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &ScriptEvent> {
+        self.events.iter()
+    }
+
+    pub fn drain(&mut self) -> Vec<ScriptEvent> {
+        std::mem::take(&mut self.events)
+    }
+}
+
+/// The runtime errors of the script systems, it is a resource of the world
+/// A failing system does not stop the update
+#[derive(Debug, Default)]
+pub struct ScriptErrors {
+    pub errors: Vec<ScriptError>,
+}
+
+impl Resource for ScriptErrors {
+    fn name() -> &'static str {
+        "ScriptErrors"
+    }
+}
+
+// reach a resource without knowing its Rust type
+#[derive(Clone, Copy)]
+struct ResourceAccess {
+    get: fn(&EntityManager) -> Option<&dyn Reflect>,
+    get_mut: fn(&mut EntityManager) -> Option<&mut dyn Reflect>,
+}
+
+fn reflect_resource<R: Resource + Reflect>(entity_manager: &EntityManager) -> Option<&dyn Reflect> {
+    entity_manager
+        .borrow_resource::<R>()
+        .map(|resource| resource as &dyn Reflect)
+}
+
+fn reflect_resource_mut<R: Resource + Reflect>(
+    entity_manager: &mut EntityManager,
+) -> Option<&mut dyn Reflect> {
+    entity_manager
+        .borrow_resource_mut::<R>()
+        .map(|resource| resource as &mut dyn Reflect)
+}
+
+type ResourceAccesses = Rc<RefCell<HashMap<String, ResourceAccess>>>;
+
+// read an `Events<E>` queue without knowing E, the reader is an `EventReader<E>`
+#[derive(Clone, Copy)]
+struct EventAccess {
+    new_reader: fn() -> Box<dyn Any>,
+    read: fn(&EntityManager, &mut dyn Any) -> Option<Array>,
+}
+
+fn new_event_reader<E: 'static>() -> Box<dyn Any> {
+    Box::new(EventReader::<E>::new())
+}
+
+fn read_events<E: Reflect>(entity_manager: &EntityManager, reader: &mut dyn Any) -> Option<Array> {
+    let events = entity_manager.borrow_resource::<Events<E>>()?;
+    let reader = reader.downcast_mut::<EventReader<E>>().unwrap();
+    let fields = E::type_info().fields;
+    Some(
+        reader
+            .read(events)
+            .map(|event| {
+                // the fields holding a value, a primitive event is its own value
+                if let Some(value) = event.get_value() {
+                    return to_dynamic(value).unwrap_or(Dynamic::UNIT);
+                }
+                let mut map = Map::new();
+                for field in fields.iter() {
+                    let value = event.field(field.name).and_then(|field| field.get_value());
+                    if let Some(Ok(value)) = value.map(to_dynamic) {
+                        map.insert(field.name.into(), value);
+                    }
+                }
+                Dynamic::from_map(map)
+            })
+            .collect(),
+    )
+}
+
+type EventAccesses = Rc<RefCell<HashMap<String, EventAccess>>>;
+
+// the readers of a script system, by event name
+type EventReaders = Rc<RefCell<HashMap<String, Box<dyn Any>>>>;
+
+/// Embed the Rhai language, scripts see the world through a `world` handle:
+/// ```rhai
+/// fn burn(world, dt) {
+///     for entity in world.query(["Burner", "Crafter"]) {
+///         let fuel = world.get(entity, "Burner", "fuel") - dt;
+///         world.set(entity, "Burner", "fuel", fuel);
+///         if fuel <= 0.0 { world.emit("out-of-fuel", entity); }
+///     }
+/// }
+/// ```
+/// Components are accessed by name through the reflection (see `TypeRegistry`),
+/// resources must be registered with `register_resource` and events with
+/// `register_event`.
+pub struct ScriptEngine {
+    engine: Rc<Engine>,
+    resources: ResourceAccesses,
+    events: EventAccesses,
+}
+
+impl Default for ScriptEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ScriptEngine {
+    pub fn new() -> Self {
+        let mut engine = Engine::new();
+        register_world_api(&mut engine);
+
+        Self {
+            engine: Rc::new(engine),
+            resources: Rc::new(RefCell::new(HashMap::new())),
+            events: Rc::new(RefCell::new(HashMap::new())),
+        }
+    }
+
+    /// Make the `Events<E>` of the world readable by the scripts, by the name of E:
+    /// `world.events("BeltStopped")` returns the events the system has not read yet,
+    /// each one as a map of its fields (numbers, booleans and strings)
+    pub fn register_event<E: Reflect>(&mut self) -> &mut Self {
+        self.events.borrow_mut().insert(
+            E::type_info().name.to_string(),
+            EventAccess {
+                new_reader: new_event_reader::<E>,
+                read: read_events::<E>,
+            },
+        );
+        self
+    }
+
+    /// Make a resource readable and writable by the scripts, by its `Resource::name`
+    pub fn register_resource<R: Resource + Reflect>(&mut self) -> &mut Self {
+        self.resources.borrow_mut().insert(
+            R::name().to_string(),
+            ResourceAccess {
+                get: reflect_resource::<R>,
+                get_mut: reflect_resource_mut::<R>,
+            },
+        );
+        self
+    }
+
+    pub fn compile(&self, source: &str) -> Result<Script, ScriptError> {
+        let ast = self
+            .engine
+            .compile(source)
+            .map_err(|error| ScriptError::Compile(error.to_string()))?;
+
+        // the top level statements (constants...) are run once
+        let mut scope = Scope::new();
+        self.engine
+            .run_ast_with_scope(&mut scope, &ast)
+            .map_err(|error| ScriptError::Compile(error.to_string()))?;
+
+        Ok(Script {
+            engine: self.engine.clone(),
+            resources: self.resources.clone(),
+            events: self.events.clone(),
+            ast: Rc::new(ast),
+            scope,
+        })
+    }
+}
+
+/// A compiled script, its functions can be registered as systems
+pub struct Script {
+    engine: Rc<Engine>,
+    resources: ResourceAccesses,
+    events: EventAccesses,
+    ast: Rc<AST>,
+    scope: Scope<'static>,
+}
+
+impl Script {
+    /// A system calling `function(world, delta_time)` on every update
+    pub fn system(&self, function: &str) -> Result<ScriptSystem, ScriptError> {
+        if !self.ast.iter_functions().any(|f| f.name == function) {
+            return Err(ScriptError::MissingFunction(function.to_string()));
+        }
+
+        Ok(ScriptSystem {
+            engine: self.engine.clone(),
+            resources: self.resources.clone(),
+            events: self.events.clone(),
+            readers: Rc::new(RefCell::new(HashMap::new())),
+            ast: self.ast.clone(),
+            scope: self.scope.clone(),
+            function: function.to_string(),
+        })
+    }
+}
+
+/// A script function run as a system, see `Script::system`
+/// Runtime errors are stored in the `ScriptErrors` resource
+/// During the call the entity manager is moved into the `world` handle of the
+/// script and an empty one is left in its place, it is moved back after the call:
+/// the hooks and the indexes run on the moved manager as usual and nothing else
+/// can see the empty one. A panic during the call (in a hook) loses the entities.
+pub struct ScriptSystem {
+    engine: Rc<Engine>,
+    resources: ResourceAccesses,
+    events: EventAccesses,
+    readers: EventReaders,
+    ast: Rc<AST>,
+    scope: Scope<'static>,
+    function: String,
+}
+
+impl System for ScriptSystem {
+    fn update(&mut self, delta_time: f32, entity_manager: &mut EntityManager) {
+        // the entity manager is lent to the script for the duration of the call
+        let world = ScriptWorld {
+            entity_manager: Rc::new(RefCell::new(std::mem::take(entity_manager))),
+            resources: self.resources.clone(),
+            events: self.events.clone(),
+            readers: self.readers.clone(),
+        };
+        let result = self.engine.call_fn_with_options::<Dynamic>(
+            CallFnOptions::new().eval_ast(false),
+            &mut self.scope,
+            &self.ast,
+            &self.function,
+            (world.clone(), delta_time as FLOAT),
+        );
+        *entity_manager = world.entity_manager.take();
+
+        if let Err(error) = result {
+            if entity_manager.borrow_resource::<ScriptErrors>().is_none() {
+                entity_manager.insert_resource(ScriptErrors::default());
+            }
+            entity_manager
+                .borrow_resource_mut::<ScriptErrors>()
+                .unwrap()
+                .errors
+                .push(ScriptError::Runtime {
+                    function: self.function.clone(),
+                    error: error.to_string(),
+                });
+        }
+    }
+
+    fn name(&self) -> &str {
+        &self.function
+    }
+}
+
+// the `world` handle given to the scripts
+#[derive(Clone)]
+struct ScriptWorld {
+    entity_manager: Rc<RefCell<EntityManager>>,
+    resources: ResourceAccesses,
+    events: EventAccesses,
+    readers: EventReaders,
+}
+
+fn register_world_api(engine: &mut Engine) {
+    engine
+        .register_type_with_name::<ScriptWorld>("World")
+        .register_fn("query", script_query)
+        .register_fn("get", script_get)
+        .register_fn("set", script_set)
+        .register_fn("has", script_has)
+        .register_fn("create_entity", script_create_entity)
+        .register_fn("remove_entity", script_remove_entity)
+        .register_fn("is_alive", script_is_alive)
+        .register_fn("resource", script_resource)
+        .register_fn("set_resource", script_set_resource)
+        .register_fn("emit", script_emit)
+        .register_fn("emit", script_emit_value)
+        .register_fn("events", script_events);
+}
+
+fn script_query(world: &mut ScriptWorld, names: Array) -> ScriptResult<Array> {
+    let names = names
+        .into_iter()
+        .map(|name| name.into_string())
+        .collect::<Result<Vec<String>, _>>()
+        .map_err(|type_name| format!("component names must be strings, found {}", type_name))?;
+    let names: Vec<&str> = names.iter().map(|name| name.as_str()).collect();
+
+    let entities = world
+        .entity_manager
+        .borrow()
+        .query_entities_by_name(&names)
+        .ok_or_else(|| format!("unknown component in query: {:?}", names))?;
+    Ok(entities
+        .into_iter()
+        .map(|entity| Dynamic::from(entity as INT))
+        .collect())
+}
+
+fn script_get(
+    world: &mut ScriptWorld,
+    entity: INT,
+    component: &str,
+    path: &str,
+) -> ScriptResult<Dynamic> {
+    let entity_manager = world.entity_manager.borrow();
+    let component = entity_manager
+        .reflect_component_for_entity(entity_id(entity)?, component)
+        .ok_or_else(|| format!("entity {} has no component {}", entity, component))?;
+    let value = component
+        .get_path(path)
+        .map_err(|error| error.to_string())?;
+    to_dynamic(value)
+}
+
+fn script_set(
+    world: &mut ScriptWorld,
+    entity: INT,
+    component: &str,
+    path: &str,
+    value: Dynamic,
+) -> ScriptResult<()> {
+    let mut entity_manager = world.entity_manager.borrow_mut();
+    let component = entity_manager
+        .reflect_component_mut_for_entity(entity_id(entity)?, component)
+        .ok_or_else(|| format!("entity {} has no component {}", entity, component))?;
+    component
+        .set_path(path, to_value(value)?)
+        .map_err(|error| error.to_string().into())
+}
+
+fn script_has(world: &mut ScriptWorld, entity: INT, component: &str) -> bool {
+    let Ok(entity) = usize::try_from(entity) else {
+        return false;
+    };
+    world
+        .entity_manager
+        .borrow()
+        .has_component_by_name(entity, component)
+}
+
+fn script_create_entity(world: &mut ScriptWorld) -> INT {
+    world.entity_manager.borrow_mut().create_entity() as INT
+}
+
+fn script_remove_entity(world: &mut ScriptWorld, entity: INT) -> ScriptResult<()> {
+    world
+        .entity_manager
+        .borrow_mut()
+        .remove_entity(entity_id(entity)?);
+    Ok(())
+}
+
+fn script_is_alive(world: &mut ScriptWorld, entity: INT) -> bool {
+    usize::try_from(entity).is_ok_and(|entity| world.entity_manager.borrow().is_alive(entity))
+}
+
+fn script_resource(world: &mut ScriptWorld, name: &str, path: &str) -> ScriptResult<Dynamic> {
+    let access = resource_access(world, name)?;
+    let entity_manager = world.entity_manager.borrow();
+    let resource = (access.get)(&entity_manager)
+        .ok_or_else(|| format!("resource {} is not inserted", name))?;
+    let value = resource.get_path(path).map_err(|error| error.to_string())?;
+    to_dynamic(value)
+}
+
+fn script_set_resource(
+    world: &mut ScriptWorld,
+    name: &str,
+    path: &str,
+    value: Dynamic,
+) -> ScriptResult<()> {
+    let access = resource_access(world, name)?;
+    let mut entity_manager = world.entity_manager.borrow_mut();
+    let resource = (access.get_mut)(&mut entity_manager)
+        .ok_or_else(|| format!("resource {} is not inserted", name))?;
+    resource
+        .set_path(path, to_value(value)?)
+        .map_err(|error| error.to_string().into())
+}
+
+fn script_emit(world: &mut ScriptWorld, name: &str) {
+    emit(world, name, None);
+}
+
+fn script_emit_value(world: &mut ScriptWorld, name: &str, value: Dynamic) -> ScriptResult<()> {
+    emit(world, name, Some(to_value(value)?));
+    Ok(())
+}
+
+fn emit(world: &mut ScriptWorld, name: &str, value: Option<Value>) {
+    let mut entity_manager = world.entity_manager.borrow_mut();
+    if entity_manager.borrow_resource::<ScriptEvents>().is_none() {
+        entity_manager.insert_resource(ScriptEvents::new());
+    }
+    entity_manager
+        .borrow_resource_mut::<ScriptEvents>()
+        .unwrap()
+        .push(ScriptEvent {
+            name: name.to_string(),
+            value,
+        });
+}
+
+fn script_events(world: &mut ScriptWorld, name: &str) -> ScriptResult<Array> {
+    let access = world
+        .events
+        .borrow()
+        .get(name)
+        .copied()
+        .ok_or_else(|| format!("event {} is not registered for scripts", name))?;
+    let mut readers = world.readers.borrow_mut();
+    let reader = readers
+        .entry(name.to_string())
+        .or_insert_with(access.new_reader);
+    (access.read)(&world.entity_manager.borrow(), reader.as_mut())
+        .ok_or_else(|| format!("event {} is not added to the world", name).into())
+}
+
+fn resource_access(world: &ScriptWorld, name: &str) -> ScriptResult<ResourceAccess> {
+    world
+        .resources
+        .borrow()
+        .get(name)
+        .copied()
+        .ok_or_else(|| format!("resource {} is not registered for scripts", name).into())
+}
+
+fn entity_id(entity: INT) -> ScriptResult<usize> {
+    usize::try_from(entity).map_err(|_| format!("invalid entity: {}", entity).into())
+}
+
+// the scripts only have signed integers, larger unsigned values are an error
+fn to_dynamic(value: Value) -> ScriptResult<Dynamic> {
+    Ok(match value {
+        Value::Bool(v) => Dynamic::from(v),
+        Value::Int(v) => Dynamic::from(v as INT),
+        Value::UInt(v) => Dynamic::from(
+            INT::try_from(v).map_err(|_| format!("{} is too large for a script integer", v))?,
+        ),
+        Value::Float(v) => Dynamic::from(v as FLOAT),
+        Value::String(v) => Dynamic::from(v),
+    })
+}
+
+fn to_value(value: Dynamic) -> ScriptResult<Value> {
+    if let Ok(v) = value.as_bool() {
+        return Ok(Value::Bool(v));
+    }
+    if let Ok(v) = value.as_int() {
+        return Ok(Value::Int(v));
+    }
+    if let Ok(v) = value.as_float() {
+        return Ok(Value::Float(v));
+    }
+    value
+        .into_string()
+        .map(Value::String)
+        .map_err(|type_name| format!("cannot store a {} in a field", type_name).into())
+}
@@ -0,0 +1,460 @@
+use std::any::Any;
+use std::fmt;
+
+use serde::{Deserialize, Serialize, de::DeserializeOwned, ser};
+use serde_json::Value;
+
+use crate::component::Component;
+use crate::component_manager::{
+    ComponentManager, ComponentManagerTrait, cast_manager, cast_manager_mut,
+};
+use crate::index::IndexError;
+use crate::resource::Resource;
+
+/// Version of the save file layout, bumped when `SaveData` changes
+pub const SAVE_FORMAT_VERSION: u32 = 2;
+
+/// Every binary save starts with these bytes followed by the format version
+const SAVE_MAGIC: &[u8; 4] = b"FSAV";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SaveError {
+    Encode(String),
+    Decode(String),
+    /// The data does not start with the save header
+    InvalidHeader,
+    /// The save was written by a newer version of the game
+    UnsupportedVersion {
+        found: u32,
+        supported: u32,
+    },
+    /// The save contains a component that is not registered in the world
+    UnknownComponent(String),
+    /// The save contains a resource that is not inserted in the world
+    UnknownResource(String),
+    /// The component was saved with a newer schema than the registered one
+    UnsupportedComponentVersion {
+        name: String,
+        found: u32,
+        supported: u32,
+    },
+    /// No migration is registered to upgrade the component from this version
+    MissingMigration {
+        name: String,
+        version: u32,
+    },
+    /// A migration function failed
+    Migration {
+        name: String,
+        version: u32,
+        error: String,
+    },
+    /// The entity ids of the save are inconsistent (out of range, duplicated free ids,
+    /// components on dead entities...)
+    Corrupt(String),
+    /// A component uses a key already used in a unique index
+    Index(IndexError),
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SaveError::Encode(error) => write!(f, "cannot encode save: {}", error),
+            SaveError::Decode(error) => write!(f, "cannot decode save: {}", error),
+            SaveError::InvalidHeader => write!(f, "not a save file"),
+            SaveError::UnsupportedVersion { found, supported } => write!(
+                f,
+                "save format version {} is newer than the supported version {}",
+                found, supported
+            ),
+            SaveError::UnknownComponent(name) => write!(f, "unknown component: {}", name),
+            SaveError::UnknownResource(name) => write!(f, "unknown resource: {}", name),
+            SaveError::UnsupportedComponentVersion {
+                name,
+                found,
+                supported,
+            } => write!(
+                f,
+                "component {} version {} is newer than the supported version {}",
+                name, found, supported
+            ),
+            SaveError::MissingMigration { name, version } => write!(
+                f,
+                "no migration registered for component {} version {}",
+                name, version
+            ),
+            SaveError::Migration {
+                name,
+                version,
+                error,
+            } => write!(
+                f,
+                "cannot migrate component {} version {}: {}",
+                name, version, error
+            ),
+            SaveError::Corrupt(error) => write!(f, "corrupt save: {}", error),
+            SaveError::Index(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for SaveError {}
+
+/// The content of a save file
+/// Components and resources are stored as self-describing values,
+/// keyed by their registered name
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SaveData {
+    pub version: u32,
+    pub entities: Vec<EntityData>,
+    /// Free entity ids, in the order they will be reused
+    pub available_ids: Vec<usize>,
+    pub components: Vec<ComponentData>,
+    pub resources: Vec<ResourceData>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct EntityData {
+    pub alive: bool,
+    pub generation: u32,
+    /// Missing from the saves written before entities could be disabled
+    #[serde(default)]
+    pub disabled: bool,
+}
+
+/// Serialized components along with their owner
+pub type ComponentEntries = Vec<(usize, Value)>;
+
+/// All the instances of a component type, in storage order
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ComponentData {
+    pub name: String,
+    /// Schema version of the component when the save was written
+    #[serde(default = "first_schema_version")]
+    pub version: u32,
+    pub entries: ComponentEntries,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResourceData {
+    pub name: String,
+    pub value: Value,
+}
+
+impl SaveData {
+    /// Encode into the binary save format
+    pub fn to_bytes(&self) -> Result<Vec<u8>, SaveError> {
+        let mut bytes = SAVE_MAGIC.to_vec();
+        bytes.extend_from_slice(&self.version.to_le_bytes());
+        let body = rmp_serde::to_vec(self).map_err(|e| SaveError::Encode(e.to_string()))?;
+        bytes.extend_from_slice(&body);
+        Ok(bytes)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SaveError> {
+        if bytes.len() < 8 || &bytes[..4] != SAVE_MAGIC {
+            return Err(SaveError::InvalidHeader);
+        }
+
+        // the version is checked before decoding, a newer layout may not decode at all
+        let version = u32::from_le_bytes(bytes[4..8].try_into().unwrap());
+        check_version(version)?;
+
+        rmp_serde::from_slice(&bytes[8..]).map_err(|e| SaveError::Decode(e.to_string()))
+    }
+
+    /// Encode into the human readable (JSON) debug format
+    pub fn to_debug_string(&self) -> Result<String, SaveError> {
+        serde_json::to_string_pretty(self).map_err(|e| SaveError::Encode(e.to_string()))
+    }
+
+    pub fn from_debug_str(text: &str) -> Result<Self, SaveError> {
+        let data: Self =
+            serde_json::from_str(text).map_err(|e| SaveError::Decode(e.to_string()))?;
+        check_version(data.version)?;
+        Ok(data)
+    }
+}
+
+fn first_schema_version() -> u32 {
+    1
+}
+
+fn check_version(version: u32) -> Result<(), SaveError> {
+    if version > SAVE_FORMAT_VERSION {
+        return Err(SaveError::UnsupportedVersion {
+            found: version,
+            supported: SAVE_FORMAT_VERSION,
+        });
+    }
+    Ok(())
+}
+
+// the values pass through JSON, which writes NaN and the infinities as null:
+// they are rejected instead of producing a save that can not be loaded
+pub(crate) fn to_value<T: Serialize>(value: &T) -> Result<Value, SaveError> {
+    value
+        .serialize(FiniteFloats)
+        .map_err(|e| SaveError::Encode(e.0))?;
+    serde_json::to_value(value).map_err(|e| SaveError::Encode(e.to_string()))
+}
+
+#[derive(Debug)]
+struct NonFiniteFloat(String);
+
+impl fmt::Display for NonFiniteFloat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for NonFiniteFloat {}
+
+impl ser::Error for NonFiniteFloat {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Self(msg.to_string())
+    }
+}
+
+// a serializer writing nothing, it only fails on a NaN or infinite float
+struct FiniteFloats;
+
+fn check_float(value: f64) -> Result<(), NonFiniteFloat> {
+    if value.is_finite() {
+        Ok(())
+    } else {
+        Err(NonFiniteFloat(format!("{} can not be saved", value)))
+    }
+}
+
+macro_rules! accept {
+    ($($method:ident($($arg:ty),*)),+) => {
+        $(
+            fn $method(self, $(_: $arg),*) -> Result<(), NonFiniteFloat> {
+                Ok(())
+            }
+        )+
+    };
+}
+
+macro_rules! compound {
+    ($($trait:ident::$method:ident($($arg:ty),*)),+) => {
+        $(
+            impl ser::$trait for FiniteFloats {
+                type Ok = ();
+                type Error = NonFiniteFloat;
+
+                fn $method<T: ?Sized + Serialize>(
+                    &mut self,
+                    $(_: $arg,)*
+                    value: &T,
+                ) -> Result<(), NonFiniteFloat> {
+                    value.serialize(FiniteFloats)
+                }
+
+                fn end(self) -> Result<(), NonFiniteFloat> {
+                    Ok(())
+                }
+            }
+        )+
+    };
+}
+
+compound!(
+    SerializeSeq::serialize_element(),
+    SerializeTuple::serialize_element(),
+    SerializeTupleStruct::serialize_field(),
+    SerializeTupleVariant::serialize_field(),
+    SerializeStruct::serialize_field(&'static str),
+    SerializeStructVariant::serialize_field(&'static str)
+);
+
+impl ser::SerializeMap for FiniteFloats {
+    type Ok = ();
+    type Error = NonFiniteFloat;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<(), NonFiniteFloat> {
+        key.serialize(FiniteFloats)
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), NonFiniteFloat> {
+        value.serialize(FiniteFloats)
+    }
+
+    fn end(self) -> Result<(), NonFiniteFloat> {
+        Ok(())
+    }
+}
+
+impl ser::Serializer for FiniteFloats {
+    type Ok = ();
+    type Error = NonFiniteFloat;
+    type SerializeSeq = Self;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Self;
+    type SerializeTupleVariant = Self;
+    type SerializeMap = Self;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Self;
+
+    accept!(
+        serialize_bool(bool),
+        serialize_i8(i8),
+        serialize_i16(i16),
+        serialize_i32(i32),
+        serialize_i64(i64),
+        serialize_i128(i128),
+        serialize_u8(u8),
+        serialize_u16(u16),
+        serialize_u32(u32),
+        serialize_u64(u64),
+        serialize_u128(u128),
+        serialize_char(char),
+        serialize_str(&str),
+        serialize_bytes(&[u8]),
+        serialize_none(),
+        serialize_unit(),
+        serialize_unit_struct(&'static str),
+        serialize_unit_variant(&'static str, u32, &'static str)
+    );
+
+    fn serialize_f32(self, value: f32) -> Result<(), NonFiniteFloat> {
+        check_float(value as f64)
+    }
+
+    fn serialize_f64(self, value: f64) -> Result<(), NonFiniteFloat> {
+        check_float(value)
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<(), NonFiniteFloat> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _: &'static str,
+        value: &T,
+    ) -> Result<(), NonFiniteFloat> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        value: &T,
+    ) -> Result<(), NonFiniteFloat> {
+        value.serialize(self)
+    }
+
+    fn serialize_seq(self, _: Option<usize>) -> Result<Self, NonFiniteFloat> {
+        Ok(self)
+    }
+
+    fn serialize_tuple(self, _: usize) -> Result<Self, NonFiniteFloat> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_struct(self, _: &'static str, _: usize) -> Result<Self, NonFiniteFloat> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self, NonFiniteFloat> {
+        Ok(self)
+    }
+
+    fn serialize_map(self, _: Option<usize>) -> Result<Self, NonFiniteFloat> {
+        Ok(self)
+    }
+
+    fn serialize_struct(self, _: &'static str, _: usize) -> Result<Self, NonFiniteFloat> {
+        Ok(self)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self, NonFiniteFloat> {
+        Ok(self)
+    }
+}
+
+pub(crate) fn from_value<T: DeserializeOwned>(value: Value) -> Result<T, SaveError> {
+    serde_json::from_value(value).map_err(|e| SaveError::Decode(e.to_string()))
+}
+
+/// Type-erased serde functions of a component
+/// Created by `#[component(serializable)]`, see `Component::serializer`
+#[derive(Clone, Copy)]
+pub struct ComponentSerializer {
+    pub(crate) version: u32,
+    pub(crate) new_manager: fn() -> Box<dyn ComponentManagerTrait>,
+    pub(crate) serialize: fn(&dyn ComponentManagerTrait) -> Result<ComponentEntries, SaveError>,
+    pub(crate) deserialize:
+        fn(&mut dyn ComponentManagerTrait, usize, Value) -> Result<(), SaveError>,
+}
+
+impl ComponentSerializer {
+    /// `version` is the schema version of the component, see `Migrations`
+    pub fn of<T: 'static + Component + Serialize + DeserializeOwned>(version: u32) -> Self {
+        Self {
+            version,
+            new_manager: || Box::new(ComponentManager::<T>::new()),
+            serialize: serialize_components::<T>,
+            deserialize: deserialize_component::<T>,
+        }
+    }
+
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+}
+
+fn serialize_components<T: 'static + Component + Serialize>(
+    manager: &dyn ComponentManagerTrait,
+) -> Result<ComponentEntries, SaveError> {
+    let manager = cast_manager::<T>(manager).unwrap();
+    manager
+        .iter()
+        .map(|(entity_id, component)| Ok((entity_id, to_value(component)?)))
+        .collect()
+}
+
+fn deserialize_component<T: 'static + Component + DeserializeOwned>(
+    manager: &mut dyn ComponentManagerTrait,
+    entity_id: usize,
+    value: Value,
+) -> Result<(), SaveError> {
+    let component = from_value::<T>(value)?;
+    cast_manager_mut::<T>(manager)
+        .unwrap()
+        .try_add(entity_id, component)
+        .map_err(SaveError::Index)
+}
+
+/// Type-erased serde functions of a resource
+/// Created by `#[resource(serializable)]`, see `Resource::serializer`
+#[derive(Clone, Copy)]
+pub struct ResourceSerializer {
+    pub(crate) serialize: fn(&dyn Any) -> Result<Value, SaveError>,
+    pub(crate) deserialize: fn(Value) -> Result<Box<dyn Any>, SaveError>,
+}
+
+impl ResourceSerializer {
+    pub fn of<T: Resource + Serialize + DeserializeOwned>() -> Self {
+        Self {
+            serialize: |resource| to_value(resource.downcast_ref::<T>().unwrap()),
+            deserialize: |value| Ok(Box::new(from_value::<T>(value)?)),
+        }
+    }
+}
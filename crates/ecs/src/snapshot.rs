@@ -0,0 +1,45 @@
+use std::any::TypeId;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::component_manager::ComponentManagerTrait;
+use crate::dynamic::ComponentId;
+use crate::entity_manager::Entities;
+use crate::query_manager::QueryManager;
+use crate::random::Random;
+
+/// A copy of the entities, components and `Random` streams of a world at a given time
+/// Taking it is cheap: the storages are shared with the world and only copied
+/// when the world modifies them. `Random` is part of the state hash so it is captured
+/// too, replaying from a snapshot draws the same numbers; the other resources are not.
+pub struct Snapshot {
+    pub(crate) entities: Arc<Entities>,
+    pub(crate) components_managers: HashMap<TypeId, Box<dyn ComponentManagerTrait>>,
+    pub(crate) dynamic_managers: HashMap<ComponentId, Box<dyn ComponentManagerTrait>>,
+    pub(crate) query_manager: Arc<QueryManager>,
+    pub(crate) random: Option<Random>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SnapshotError {
+    /// Some entities have a component that is not `#[component(clone)]`
+    NotClonable(&'static str),
+    /// Components were registered or unregistered since the snapshot was taken
+    RegistrationChanged,
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotError::NotClonable(name) => {
+                write!(f, "component {} is not clonable", name)
+            }
+            SnapshotError::RegistrationChanged => {
+                write!(f, "registered components changed since the snapshot")
+            }
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
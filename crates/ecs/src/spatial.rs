@@ -0,0 +1,259 @@
+use std::collections::HashMap;
+
+use ecs_macros::{Component, Resource};
+use serde::{Deserialize, Serialize};
+
+use crate::entity_manager::EntityManager;
+
+/// The tile of an entity, indexed by the `SpatialIndex` resource
+/// The index is updated by the component hooks: move an entity with
+/// `replace_component_for_entity` instead of modifying its position in place
+#[derive(Component, Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[reflect(name = "ecs::TilePosition")]
+#[component(
+    serializable,
+    clone,
+    debug,
+    on_add = index_entity,
+    on_remove = remove_position
+)]
+pub struct TilePosition {
+    pub x: i32,
+    pub y: i32,
+}
+
+/// The size of an entity covering several tiles (an assembler is 3x3),
+/// its `TilePosition` is the top left tile. Entities without it cover one tile.
+/// The index covers at most `MAX_SIZE` tiles per side, larger footprints are capped
+#[derive(Component, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[reflect(name = "ecs::TileFootprint")]
+#[component(
+    serializable,
+    clone,
+    debug,
+    on_add = index_entity,
+    on_remove = remove_footprint
+)]
+pub struct TileFootprint {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl TileFootprint {
+    // a footprint is indexed tile by tile, a corrupt save or a typo must not
+    // make the hook fill millions of cells
+    pub const MAX_SIZE: u32 = 256;
+}
+
+impl Default for TileFootprint {
+    fn default() -> Self {
+        Self {
+            width: 1,
+            height: 1,
+        }
+    }
+}
+
+/// A rectangle of tiles, `x` and `y` is the top left tile
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TileRect {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl TileRect {
+    pub fn new(x: i32, y: i32, width: u32, height: u32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        x >= self.x
+            && y >= self.y
+            && (x as i64) < self.x as i64 + self.width as i64
+            && (y as i64) < self.y as i64 + self.height as i64
+    }
+
+    pub fn intersects(&self, other: &TileRect) -> bool {
+        (self.x as i64) < other.x as i64 + other.width as i64
+            && (other.x as i64) < self.x as i64 + self.width as i64
+            && (self.y as i64) < other.y as i64 + other.height as i64
+            && (other.y as i64) < self.y as i64 + self.height as i64
+    }
+
+    pub fn area(&self) -> u64 {
+        self.width as u64 * self.height as u64
+    }
+
+    pub fn tiles(&self) -> impl Iterator<Item = (i32, i32)> + use<> {
+        let rect = *self;
+        (0..rect.height as i64).flat_map(move |dy| {
+            (0..rect.width as i64)
+                .map(move |dx| ((rect.x as i64 + dx) as i32, (rect.y as i64 + dy) as i32))
+        })
+    }
+
+    // squared distance from a tile to the nearest tile of the rectangle,
+    // on 128 bits as the distance between two i32 tiles squared overflows an i64
+    fn distance_squared(&self, x: i32, y: i32) -> i128 {
+        let nearest_x =
+            (x as i64).clamp(self.x as i64, self.x as i64 + self.width.max(1) as i64 - 1);
+        let nearest_y =
+            (y as i64).clamp(self.y as i64, self.y as i64 + self.height.max(1) as i64 - 1);
+        let (dx, dy) = ((nearest_x - x as i64) as i128, (nearest_y - y as i64) as i128);
+        dx * dx + dy * dy
+    }
+}
+
+/// Find the entities by tile, maintained by the `TilePosition` and `TileFootprint` hooks
+/// Inserted in the resources when the first positioned entity is added
+/// The disabled entities keep their tiles (a ghost building still blocks its
+/// tiles), check `EntityManager::is_disabled` to skip them
+#[derive(Resource, Debug, Default)]
+pub struct SpatialIndex {
+    cells: HashMap<(i32, i32), Vec<usize>>,
+    bounds: HashMap<usize, TileRect>,
+}
+
+impl SpatialIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The entities covering a tile
+    pub fn at(&self, x: i32, y: i32) -> &[usize] {
+        self.cells.get(&(x, y)).map_or(&[], |entities| entities)
+    }
+
+    /// The tiles covered by an entity
+    pub fn bounds(&self, entity_id: usize) -> Option<TileRect> {
+        self.bounds.get(&entity_id).copied()
+    }
+
+    /// The entities covering at least one tile of the rectangle, sorted by id
+    pub fn in_rect(&self, rect: TileRect) -> Vec<usize> {
+        let mut entities: Vec<usize> = if rect.area() > self.bounds.len() as u64 {
+            // a large area, cheaper to test every entity
+            self.bounds
+                .iter()
+                .filter(|(_, bounds)| bounds.intersects(&rect))
+                .map(|(entity_id, _)| *entity_id)
+                .collect()
+        } else {
+            rect.tiles()
+                .flat_map(|(x, y)| self.at(x, y).iter().copied())
+                .collect()
+        };
+        entities.sort();
+        entities.dedup();
+        entities
+    }
+
+    /// The entities with a tile at most `radius` tiles away from (x, y), sorted by id
+    pub fn in_radius(&self, x: i32, y: i32, radius: u32) -> Vec<usize> {
+        let size = radius.saturating_mul(2).saturating_add(1);
+        let square = TileRect::new(
+            x.saturating_sub_unsigned(radius),
+            y.saturating_sub_unsigned(radius),
+            size,
+            size,
+        );
+        let radius_squared = radius as i128 * radius as i128;
+        self.in_rect(square)
+            .into_iter()
+            .filter(|entity_id| self.bounds[entity_id].distance_squared(x, y) <= radius_squared)
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.bounds.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bounds.is_empty()
+    }
+
+    fn insert(&mut self, entity_id: usize, bounds: TileRect) {
+        self.remove(entity_id);
+        for tile in bounds.tiles() {
+            self.cells.entry(tile).or_default().push(entity_id);
+        }
+        self.bounds.insert(entity_id, bounds);
+    }
+
+    fn remove(&mut self, entity_id: usize) {
+        let Some(bounds) = self.bounds.remove(&entity_id) else {
+            return;
+        };
+        for tile in bounds.tiles() {
+            if let Some(entities) = self.cells.get_mut(&tile) {
+                entities.retain(|id| *id != entity_id);
+                if entities.is_empty() {
+                    self.cells.remove(&tile);
+                }
+            }
+        }
+    }
+}
+
+fn spatial_index(entity_manager: &mut EntityManager) -> &mut SpatialIndex {
+    if entity_manager.borrow_resource::<SpatialIndex>().is_none() {
+        entity_manager.insert_resource(SpatialIndex::new());
+    }
+    entity_manager
+        .borrow_resource_mut::<SpatialIndex>()
+        .unwrap()
+}
+
+fn index_with_footprint(
+    entity_manager: &mut EntityManager,
+    entity_id: usize,
+    footprint: TileFootprint,
+) {
+    let Some(position) = entity_manager
+        .borrow_component_for_entity::<TilePosition>(entity_id)
+        .copied()
+    else {
+        return;
+    };
+    let bounds = TileRect::new(
+        position.x,
+        position.y,
+        footprint.width.min(TileFootprint::MAX_SIZE),
+        footprint.height.min(TileFootprint::MAX_SIZE),
+    );
+    spatial_index(entity_manager).insert(entity_id, bounds);
+}
+
+// the footprint is not registered by every game
+fn footprint(entity_manager: &EntityManager, entity_id: usize) -> TileFootprint {
+    if !entity_manager.has_component::<TileFootprint>(entity_id) {
+        return TileFootprint::default();
+    }
+    entity_manager
+        .borrow_component_for_entity::<TileFootprint>(entity_id)
+        .copied()
+        .unwrap_or_default()
+}
+
+fn index_entity(entity_manager: &mut EntityManager, entity_id: usize) {
+    let footprint = footprint(entity_manager, entity_id);
+    index_with_footprint(entity_manager, entity_id, footprint);
+}
+
+fn remove_position(entity_manager: &mut EntityManager, entity_id: usize) {
+    spatial_index(entity_manager).remove(entity_id);
+}
+
+// back to a single tile, unless the position is removed too
+fn remove_footprint(entity_manager: &mut EntityManager, entity_id: usize) {
+    if spatial_index(entity_manager).bounds(entity_id).is_some() {
+        index_with_footprint(entity_manager, entity_id, TileFootprint::default());
+    }
+}
@@ -0,0 +1,136 @@
+use std::any::Any;
+
+use crate::entity_manager::EntityManager;
+use crate::resource::Resource;
+use crate::system::System;
+
+/// The current state of the game (main menu, in game, paused...),
+/// a resource added by `World::init_state`
+/// A requested state is entered at the start of the next `World::update`
+pub struct State<S> {
+    current: S,
+    next: Option<S>,
+}
+
+impl<S: 'static> Resource for State<S> {}
+
+impl<S: Clone + PartialEq> State<S> {
+    pub fn new(initial: S) -> Self {
+        Self {
+            current: initial,
+            next: None,
+        }
+    }
+
+    pub fn get(&self) -> &S {
+        &self.current
+    }
+
+    /// Request a transition, the last request of a tick wins
+    pub fn set(&mut self, next: S) {
+        self.next = Some(next);
+    }
+
+    /// The requested state, not entered yet
+    pub fn next(&self) -> Option<&S> {
+        self.next.as_ref()
+    }
+}
+
+/// Run a system only in some states, see `World::register_system_in_state`
+pub struct InState<S, T> {
+    system: T,
+    states: Vec<S>,
+}
+
+impl<S, T> InState<S, T> {
+    pub fn new(system: T, states: Vec<S>) -> Self {
+        Self { system, states }
+    }
+}
+
+impl<S: 'static + Clone + PartialEq, T: System> System for InState<S, T> {
+    fn update(&mut self, delta_time: f32, entity_manager: &mut EntityManager) {
+        let active = entity_manager
+            .borrow_resource::<State<S>>()
+            .is_some_and(|state| self.states.contains(state.get()));
+        if active {
+            self.system.update(delta_time, entity_manager);
+        }
+    }
+
+    fn name(&self) -> &str {
+        self.system.name()
+    }
+}
+
+// the systems run when a state of type S is entered or exited
+pub(crate) trait StateSchedules {
+    // enter the requested state, the initial state on the first call
+    fn apply(&mut self, delta_time: f32, entity_manager: &mut EntityManager);
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+pub(crate) struct Schedules<S> {
+    on_enter: Vec<(S, Box<dyn System>)>,
+    on_exit: Vec<(S, Box<dyn System>)>,
+    // the initial state has been entered
+    started: bool,
+}
+
+impl<S> Schedules<S> {
+    pub(crate) fn new() -> Self {
+        Self {
+            on_enter: Vec::new(),
+            on_exit: Vec::new(),
+            started: false,
+        }
+    }
+
+    pub(crate) fn add_on_enter(&mut self, state: S, system: Box<dyn System>) {
+        self.on_enter.push((state, system));
+    }
+
+    pub(crate) fn add_on_exit(&mut self, state: S, system: Box<dyn System>) {
+        self.on_exit.push((state, system));
+    }
+}
+
+fn run_schedule<S: PartialEq>(
+    schedule: &mut [(S, Box<dyn System>)],
+    state: &S,
+    delta_time: f32,
+    entity_manager: &mut EntityManager,
+) {
+    for (_, system) in schedule.iter_mut().filter(|(s, _)| s == state) {
+        system.update(delta_time, entity_manager);
+    }
+}
+
+impl<S: 'static + Clone + PartialEq> StateSchedules for Schedules<S> {
+    fn apply(&mut self, delta_time: f32, entity_manager: &mut EntityManager) {
+        let Some(state) = entity_manager.borrow_resource_mut::<State<S>>() else {
+            return;
+        };
+        let current = state.current.clone();
+        let next = state.next.take();
+
+        if !self.started {
+            self.started = true;
+            run_schedule(&mut self.on_enter, &current, delta_time, entity_manager);
+        }
+        let Some(next) = next.filter(|next| *next != current) else {
+            return;
+        };
+
+        run_schedule(&mut self.on_exit, &current, delta_time, entity_manager);
+        if let Some(state) = entity_manager.borrow_resource_mut::<State<S>>() {
+            state.current = next.clone();
+        }
+        run_schedule(&mut self.on_enter, &next, delta_time, entity_manager);
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self as &mut dyn Any
+    }
+}
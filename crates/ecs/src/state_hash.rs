@@ -0,0 +1,122 @@
+use std::collections::BTreeMap;
+use std::hash::Hasher;
+
+use serde::{Deserialize, Serialize};
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+/// FNV-1a hasher
+/// Unlike `DefaultHasher` its output is stable across Rust versions and platforms,
+/// two peers running the same simulation get the same hash
+pub struct StateHasher {
+    hash: u64,
+}
+
+impl Default for StateHasher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StateHasher {
+    pub fn new() -> Self {
+        Self {
+            hash: FNV_OFFSET_BASIS,
+        }
+    }
+}
+
+impl Hasher for StateHasher {
+    fn finish(&self) -> u64 {
+        self.hash
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.hash ^= *byte as u64;
+            self.hash = self.hash.wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    // the default implementations use the native endianness and width
+    fn write_u16(&mut self, i: u16) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        self.write_u64(i as u64);
+    }
+
+    fn write_i16(&mut self, i: i16) {
+        self.write_u16(i as u16);
+    }
+
+    fn write_i32(&mut self, i: i32) {
+        self.write_u32(i as u32);
+    }
+
+    fn write_i64(&mut self, i: i64) {
+        self.write_u64(i as u64);
+    }
+
+    fn write_isize(&mut self, i: isize) {
+        self.write_u64(i as u64);
+    }
+}
+
+/// Hash of the whole world state at a tick
+/// `entities` covers the entity ids and generations, `components` has one hash per
+/// component type (and one for the `Random` resource) so a mismatch can be narrowed down
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateHash {
+    pub hash: u64,
+    pub entities: u64,
+    pub components: BTreeMap<String, u64>,
+}
+
+impl StateHash {
+    pub(crate) fn new(entities: u64, components: BTreeMap<String, u64>) -> Self {
+        let mut hasher = StateHasher::new();
+        hasher.write_u64(entities);
+        for (name, hash) in components.iter() {
+            hasher.write(name.as_bytes());
+            hasher.write_u64(*hash);
+        }
+
+        Self {
+            hash: hasher.finish(),
+            entities,
+            components,
+        }
+    }
+
+    /// Names of the parts that differ between two hashes
+    /// (`entities` or component names)
+    pub fn mismatches(&self, other: &StateHash) -> Vec<String> {
+        let mut mismatches = Vec::new();
+        if self.entities != other.entities {
+            mismatches.push("entities".to_string());
+        }
+
+        let names: std::collections::BTreeSet<&String> = self
+            .components
+            .keys()
+            .chain(other.components.keys())
+            .collect();
+        for name in names {
+            if self.components.get(name) != other.components.get(name) {
+                mismatches.push(name.clone());
+            }
+        }
+        mismatches
+    }
+}
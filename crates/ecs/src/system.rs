@@ -2,4 +2,9 @@ use crate::entity_manager::EntityManager;
 
 pub trait System {
     fn update(&mut self, delta_time: f32, entity_manager: &mut EntityManager);
+
+    /// The name shown by the profiler
+    fn name(&self) -> &str {
+        std::any::type_name::<Self>()
+    }
 }
@@ -0,0 +1,113 @@
+use std::time::Duration;
+
+use ecs_macros::Resource;
+
+/// The slowest and the fastest game speed
+pub const MIN_SPEED: f32 = 0.25;
+pub const MAX_SPEED: f32 = 64.0;
+
+/// The clock of the simulation, a resource inserted by `World::new`
+/// Every tick lasts `delta` seconds of game time, `World::run_frame` runs as many
+/// ticks as the real time elapsed times the speed allows
+#[derive(Resource, Debug, Clone)]
+pub struct Time {
+    tick: u64,
+    delta: f32,
+    real_elapsed: Duration,
+    paused: bool,
+    speed: f32,
+    // the game time not yet spent in a tick, in seconds
+    accumulator: f64,
+    max_ticks_per_frame: u32,
+}
+
+impl Default for Time {
+    fn default() -> Self {
+        Self::new(1.0 / 60.0)
+    }
+}
+
+impl Time {
+    pub fn new(delta: f32) -> Self {
+        Self {
+            tick: 0,
+            delta,
+            real_elapsed: Duration::ZERO,
+            paused: false,
+            speed: 1.0,
+            accumulator: 0.0,
+            max_ticks_per_frame: 256,
+        }
+    }
+
+    /// The number of the current tick, counted from 0
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    /// The fixed duration of a tick in seconds, given to `System::update`
+    pub fn delta(&self) -> f32 {
+        self.delta
+    }
+
+    pub fn set_delta(&mut self, delta: f32) {
+        self.delta = delta;
+    }
+
+    /// The game time of the ticks already run
+    pub fn elapsed(&self) -> Duration {
+        Duration::from_secs_f64(self.tick as f64 * self.delta as f64)
+    }
+
+    /// The real time given to `World::run_frame`, paused frames included
+    pub fn real_elapsed(&self) -> Duration {
+        self.real_elapsed
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// No tick runs in `World::run_frame` while paused
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+        self.accumulator = 0.0;
+    }
+
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    /// Clamped between `MIN_SPEED` and `MAX_SPEED`
+    pub fn set_speed(&mut self, speed: f32) {
+        self.speed = speed.clamp(MIN_SPEED, MAX_SPEED);
+    }
+
+    /// The ticks a frame can run at most, the time left over is dropped
+    /// so a slow frame does not make the next ones slower
+    pub fn set_max_ticks_per_frame(&mut self, max_ticks: u32) {
+        self.max_ticks_per_frame = max_ticks.max(1);
+    }
+
+    // the number of ticks to run for a frame
+    pub(crate) fn advance(&mut self, frame_time: Duration) -> u32 {
+        self.real_elapsed += frame_time;
+        if self.paused || self.delta <= 0.0 {
+            return 0;
+        }
+
+        self.accumulator += frame_time.as_secs_f64() * self.speed as f64;
+        // a frame of exactly one tick must not be lost to the rounding of the delta
+        let ticks = (self.accumulator / self.delta as f64 + 1e-4).floor();
+        if ticks > self.max_ticks_per_frame as f64 {
+            self.accumulator = 0.0;
+            return self.max_ticks_per_frame;
+        }
+        self.accumulator = (self.accumulator - ticks * self.delta as f64).max(0.0);
+        ticks as u32
+    }
+
+    pub(crate) fn end_tick(&mut self) {
+        self.tick += 1;
+    }
+}
@@ -0,0 +1,166 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::dynamic::DynamicComponent;
+use crate::index::IndexError;
+
+/// An entity with all its components, taken out of a world
+/// See `EntityManager::take_entity` and `EntityManager::spawn_entity_bundle`
+pub struct EntityBundle {
+    // the type, its registered name and the component
+    pub(crate) components: Vec<(TypeId, &'static str, Box<dyn Any>)>,
+    pub(crate) dynamic_components: Vec<DynamicComponent>,
+    // the zero-sized components, only their type is moved
+    pub(crate) tags: Vec<(TypeId, &'static str)>,
+    // the entity stays hidden from the queries
+    pub(crate) disabled: bool,
+}
+
+impl EntityBundle {
+    pub fn len(&self) -> usize {
+        self.components.len() + self.dynamic_components.len() + self.tags.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn contains<T: 'static>(&self) -> bool {
+        self.components
+            .iter()
+            .any(|(type_id, _, _)| *type_id == TypeId::of::<T>())
+            || self
+                .tags
+                .iter()
+                .any(|(type_id, _)| *type_id == TypeId::of::<T>())
+    }
+}
+
+/// The new id of each moved entity, used to remap the relations
+pub type EntityMap = HashMap<usize, usize>;
+
+/// The id given to a relation whose entity was not moved to the other world
+pub const NO_ENTITY: usize = usize::MAX;
+
+/// Give the new id of the entities of an `EntityMap`
+/// The ids missing from the map are either kept (copies in the same world) or
+/// replaced by `NO_ENTITY` (moves to another world, where they would point to
+/// unrelated entities)
+pub struct EntityMapper<'a> {
+    entity_map: &'a EntityMap,
+    keep_unmapped: bool,
+}
+
+impl<'a> EntityMapper<'a> {
+    /// The relations to the other entities are kept, see `EntityManager::clone_entities`
+    pub fn keep_unmapped(entity_map: &'a EntityMap) -> Self {
+        Self {
+            entity_map,
+            keep_unmapped: true,
+        }
+    }
+
+    /// The relations to the other entities are cut, see `EntityManager::move_entities`
+    pub fn clear_unmapped(entity_map: &'a EntityMap) -> Self {
+        Self {
+            entity_map,
+            keep_unmapped: false,
+        }
+    }
+
+    pub fn map(&self, entity_id: usize) -> usize {
+        match self.entity_map.get(&entity_id) {
+            Some(new_id) => *new_id,
+            None if self.keep_unmapped => entity_id,
+            None => NO_ENTITY,
+        }
+    }
+}
+
+/// A value holding entity ids (a relation to another entity)
+/// Fields marked with `#[component(entity)]` are remapped when their entity
+/// is moved to another world or copied
+pub trait MapEntities {
+    fn map_entities(&mut self, mapper: &EntityMapper);
+}
+
+impl MapEntities for usize {
+    fn map_entities(&mut self, mapper: &EntityMapper) {
+        *self = mapper.map(*self);
+    }
+}
+
+impl<T: MapEntities> MapEntities for Option<T> {
+    fn map_entities(&mut self, mapper: &EntityMapper) {
+        if let Some(value) = self {
+            value.map_entities(mapper);
+        }
+    }
+}
+
+impl<T: MapEntities> MapEntities for Vec<T> {
+    fn map_entities(&mut self, mapper: &EntityMapper) {
+        for value in self.iter_mut() {
+            value.map_entities(mapper);
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TransferError {
+    /// The entity does not exist in the source world
+    UnknownEntity(usize),
+    /// The target world has not registered a component of the entity
+    UnregisteredComponent(&'static str),
+    /// The target world has not registered a dynamic component with the same schema
+    UnregisteredDynamicComponent(String),
+    /// A component uses a key already used in a unique index of the target world
+    Index(IndexError),
+}
+
+impl fmt::Display for TransferError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TransferError::UnknownEntity(entity_id) => write!(f, "unknown entity: {}", entity_id),
+            TransferError::UnregisteredComponent(name) => {
+                write!(f, "component {} is not registered in the target", name)
+            }
+            TransferError::UnregisteredDynamicComponent(name) => {
+                write!(
+                    f,
+                    "dynamic component {} is not registered in the target",
+                    name
+                )
+            }
+            TransferError::Index(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for TransferError {}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CloneError {
+    /// The entity to clone does not exist
+    UnknownEntity(usize),
+    /// The entity has a component that is not `#[component(clone)]`
+    NotClonable(&'static str),
+    /// The entity has a component with a `#[component(unique)]` field,
+    /// a copy would use the same key
+    UniqueIndex(&'static str),
+}
+
+impl fmt::Display for CloneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CloneError::UnknownEntity(entity_id) => write!(f, "unknown entity: {}", entity_id),
+            CloneError::NotClonable(name) => write!(f, "component {} is not clonable", name),
+            CloneError::UniqueIndex(name) => {
+                write!(f, "component {} has a unique index", name)
+            }
+        }
+    }
+}
+
+impl std::error::Error for CloneError {}
@@ -0,0 +1,156 @@
+use std::any::TypeId;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::dynamic::{ComponentId, DynamicComponentInfo, DynamicError};
+use crate::reflect::{Reflect, TypeInfo};
+use crate::serialization::ComponentSerializer;
+
+// This struct stores the runtime description of every registered component.
+// It is filled by `EntityManager::register_component`.
+#[derive(Default)]
+pub struct TypeRegistry {
+    types: HashMap<TypeId, TypeInfo>,
+    serializers: HashMap<TypeId, ComponentSerializer>,
+    dynamic_types: HashMap<ComponentId, Arc<DynamicComponentInfo>>,
+    next_dynamic_id: u32,
+}
+
+impl TypeRegistry {
+    pub fn new() -> Self {
+        Self {
+            types: HashMap::new(),
+            serializers: HashMap::new(),
+            dynamic_types: HashMap::new(),
+            next_dynamic_id: 0,
+        }
+    }
+
+    /// The short name of the type is its key in the saves, the prefabs and the
+    /// scripts, two components with the same name (in different modules) are rejected
+    pub fn register<T: Reflect>(&mut self) -> Result<&mut Self, RegisterError> {
+        let info = T::type_info();
+        let duplicate = self
+            .types
+            .values()
+            .any(|other| other.name == info.name && other.type_id != info.type_id);
+        if duplicate || self.get_dynamic_by_name(info.name).is_some() {
+            return Err(RegisterError::DuplicateName {
+                name: info.name,
+                type_path: info.type_path,
+            });
+        }
+
+        self.types.insert(info.type_id, info);
+        Ok(self)
+    }
+
+    pub fn register_serializer<T: 'static>(
+        &mut self,
+        serializer: ComponentSerializer,
+    ) -> &mut Self {
+        self.serializers.insert(TypeId::of::<T>(), serializer);
+        self
+    }
+
+    pub fn unregister<T: 'static>(&mut self) -> &mut Self {
+        self.types.remove(&TypeId::of::<T>());
+        self.serializers.remove(&TypeId::of::<T>());
+        self
+    }
+
+    pub fn serializer(&self, type_id: TypeId) -> Option<&ComponentSerializer> {
+        self.serializers.get(&type_id)
+    }
+
+    pub fn get(&self, type_id: TypeId) -> Option<&TypeInfo> {
+        self.types.get(&type_id)
+    }
+
+    /// Find a type by its short name (ex: `Position`) or its full path
+    /// The short names are unique, see `register`
+    pub fn get_by_name(&self, name: &str) -> Option<&TypeInfo> {
+        self.types
+            .values()
+            .find(|info| info.type_path == name)
+            .or_else(|| self.types.values().find(|info| info.name == name))
+    }
+
+    /// Register the schema of a component defined at runtime
+    /// Its name must not be used by another component
+    pub fn register_dynamic(
+        &mut self,
+        info: DynamicComponentInfo,
+    ) -> Result<ComponentId, DynamicError> {
+        if self.get_by_name(&info.name).is_some() || self.get_dynamic_by_name(&info.name).is_some()
+        {
+            return Err(DynamicError::DuplicateName(info.name));
+        }
+
+        let id = ComponentId(self.next_dynamic_id);
+        self.next_dynamic_id += 1;
+        self.dynamic_types.insert(id, Arc::new(info));
+        Ok(id)
+    }
+
+    pub fn unregister_dynamic(&mut self, id: ComponentId) -> &mut Self {
+        self.dynamic_types.remove(&id);
+        self
+    }
+
+    pub fn get_dynamic(&self, id: ComponentId) -> Option<&Arc<DynamicComponentInfo>> {
+        self.dynamic_types.get(&id)
+    }
+
+    pub fn get_dynamic_by_name(
+        &self,
+        name: &str,
+    ) -> Option<(ComponentId, &Arc<DynamicComponentInfo>)> {
+        self.dynamic_types
+            .iter()
+            .find(|(_, info)| info.name == name)
+            .map(|(id, info)| (*id, info))
+    }
+
+    pub fn iter_dynamic(&self) -> impl Iterator<Item = (ComponentId, &DynamicComponentInfo)> {
+        self.dynamic_types
+            .iter()
+            .map(|(id, info)| (*id, info.as_ref()))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &TypeInfo> {
+        self.types.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.types.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.types.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RegisterError {
+    /// Another component type has the same short name
+    DuplicateName {
+        name: &'static str,
+        type_path: &'static str,
+    },
+}
+
+impl fmt::Display for RegisterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RegisterError::DuplicateName { name, type_path } => write!(
+                f,
+                "cannot register {}: a component named {} is already registered",
+                type_path, name
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RegisterError {}
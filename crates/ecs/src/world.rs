@@ -1,32 +1,286 @@
-use crate::{component::Component, entity_manager::EntityManager, system::System};
+use std::time::{Duration, Instant};
+
+use crate::{
+    bundle::Bundle,
+    component::Component,
+    dump::WorldDump,
+    dynamic::{ComponentId, DynamicComponent, DynamicComponentInfo, DynamicError},
+    entity_manager::EntityManager,
+    event::{Events, UpdateEvents},
+    index::{ComponentIndex, IndexError, Indexed},
+    migration::Migrations,
+    prefab::{PrefabError, Prefabs},
+    profiler::{Profiler, SystemSample},
+    random::Random,
+    reflect::Reflect,
+    resource::Resource,
+    serialization::{SaveData, SaveError},
+    snapshot::{Snapshot, SnapshotError},
+    state::{InState, Schedules, State, StateSchedules},
+    state_hash::StateHash,
+    system::System,
+    time::Time,
+    transfer::{CloneError, EntityMap, TransferError},
+    type_registry::{RegisterError, TypeRegistry},
+};
 
 pub struct World {
     entity_manager: EntityManager,
     systems: Vec<Box<dyn System>>,
+    profiler: Option<Profiler>,
+    // the enter and exit systems of each state type, see `init_state`
+    states: Vec<Box<dyn StateSchedules>>,
+}
+
+impl Default for World {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl World {
     pub fn new() -> Self {
-        Self {
+        let mut world = Self {
             entity_manager: EntityManager::new(),
             systems: Vec::new(),
-        }
+            profiler: None,
+            states: Vec::new(),
+        };
+        world
+            .insert_resource(Time::default())
+            .insert_resource(Random::default());
+        world
+    }
+
+    /// Replace the `Random` resource with new streams derived from `seed`
+    pub fn set_seed(&mut self, seed: u64) -> &mut Self {
+        self.insert_resource(Random::new(seed))
     }
 
     pub fn create_entity(&mut self) -> usize {
         self.entity_manager.create_entity()
     }
 
+    pub fn remove_entity(&mut self, entity_id: usize) -> &mut Self {
+        self.entity_manager.remove_entity(entity_id);
+        self
+    }
+
+    /// Create an entity for each bundle of components, see `EntityManager::spawn_batch`
+    pub fn spawn_batch<B, I>(&mut self, bundles: I) -> Vec<usize>
+    where
+        B: Bundle,
+        I: IntoIterator<Item = B>,
+    {
+        self.entity_manager.spawn_batch(bundles)
+    }
+
+    /// Create an entity for each bundle unless a key is already used in a unique index,
+    /// see `EntityManager::try_spawn_batch`
+    pub fn try_spawn_batch<B, I>(&mut self, bundles: I) -> Result<Vec<usize>, IndexError>
+    where
+        B: Bundle,
+        I: IntoIterator<Item = B>,
+    {
+        self.entity_manager.try_spawn_batch(bundles)
+    }
+
+    pub fn despawn_batch(&mut self, entities_ids: &[usize]) -> &mut Self {
+        self.entity_manager.despawn_batch(entities_ids);
+        self
+    }
+
+    /// Move an entity with all its components to another world
+    /// Returns the id of the entity in the target world
+    pub fn move_entity(
+        &mut self,
+        entity_id: usize,
+        target: &mut World,
+    ) -> Result<usize, TransferError> {
+        let entity_map = self.move_entities(&[entity_id], target)?;
+        Ok(entity_map[&entity_id])
+    }
+
+    /// Copy an entity with all its components, see `EntityManager::clone_entities`
+    pub fn clone_entity(&mut self, entity_id: usize) -> Result<usize, CloneError> {
+        let entity_map = self.clone_entities(&[entity_id])?;
+        Ok(entity_map[&entity_id])
+    }
+
+    /// Copy several entities, their relations to each other point to the copies
+    pub fn clone_entities(&mut self, entities_ids: &[usize]) -> Result<EntityMap, CloneError> {
+        self.entity_manager.clone_entities(entities_ids)
+    }
+
+    /// Move several entities to another world, see `EntityManager::move_entities`
+    pub fn move_entities(
+        &mut self,
+        entities_ids: &[usize],
+        target: &mut World,
+    ) -> Result<EntityMap, TransferError> {
+        self.entity_manager
+            .move_entities(&mut target.entity_manager, entities_ids)
+    }
+
+    pub fn is_alive(&self, entity_id: usize) -> bool {
+        self.entity_manager.is_alive(entity_id)
+    }
+
+    pub fn entity_generation(&self, entity_id: usize) -> Option<u32> {
+        self.entity_manager.entity_generation(entity_id)
+    }
+
+    /// Hide an entity from the queries, see `EntityManager::disable_entity`
+    pub fn disable_entity(&mut self, entity_id: usize) -> &mut Self {
+        self.entity_manager.disable_entity(entity_id);
+        self
+    }
+
+    pub fn enable_entity(&mut self, entity_id: usize) -> &mut Self {
+        self.entity_manager.enable_entity(entity_id);
+        self
+    }
+
+    pub fn is_disabled(&self, entity_id: usize) -> bool {
+        self.entity_manager.is_disabled(entity_id)
+    }
+
+    pub fn disabled_entities(&self) -> Vec<usize> {
+        self.entity_manager.disabled_entities()
+    }
+
     pub fn register_component<T: 'static + Component>(&mut self) -> &mut Self {
         self.entity_manager.register_component::<T>();
         self
     }
 
+    /// Register a component unless another component type has the same name
+    pub fn try_register_component<T: 'static + Component>(
+        &mut self,
+    ) -> Result<&mut Self, RegisterError> {
+        self.entity_manager.try_register_component::<T>()?;
+        Ok(self)
+    }
+
+    pub fn unregister_component<T: 'static + Component>(&mut self) -> &mut Self {
+        self.entity_manager.unregister_component::<T>();
+        self
+    }
+
+    /// Register a component defined at runtime, see `DynamicComponentInfo`
+    pub fn register_dynamic_component(
+        &mut self,
+        info: DynamicComponentInfo,
+    ) -> Result<ComponentId, DynamicError> {
+        self.entity_manager.register_dynamic_component(info)
+    }
+
+    pub fn unregister_dynamic_component(&mut self, id: ComponentId) -> &mut Self {
+        self.entity_manager.unregister_dynamic_component(id);
+        self
+    }
+
+    pub fn dynamic_component_id(&self, name: &str) -> Option<ComponentId> {
+        self.entity_manager.dynamic_component_id(name)
+    }
+
+    pub fn new_dynamic_component(&self, id: ComponentId) -> Option<DynamicComponent> {
+        self.entity_manager.new_dynamic_component(id)
+    }
+
+    pub fn add_dynamic_component_to_entity(
+        &mut self,
+        entity_id: usize,
+        component: DynamicComponent,
+    ) -> &mut Self {
+        self.entity_manager
+            .add_dynamic_component_to_entity(entity_id, component);
+        self
+    }
+
+    pub fn borrow_dynamic_component_from_entity(
+        &self,
+        entity_id: usize,
+        id: ComponentId,
+    ) -> Option<&DynamicComponent> {
+        self.entity_manager
+            .borrow_dynamic_component_for_entity(entity_id, id)
+    }
+
     pub fn register_system<T: 'static + System>(&mut self, system: T) -> &mut Self {
         self.systems.push(Box::new(system));
         self
     }
 
+    /// Insert the `State<S>` resource, its `on_enter` systems run at the first update
+    pub fn init_state<S: 'static + Clone + PartialEq>(&mut self, initial: S) -> &mut Self {
+        self.insert_resource(State::new(initial));
+        self.schedules_mut::<S>();
+        self
+    }
+
+    pub fn state<S: 'static + Clone + PartialEq>(&self) -> Option<&S> {
+        self.borrow_resource::<State<S>>().map(State::get)
+    }
+
+    /// Request a transition, applied at the start of the next update
+    pub fn set_state<S: 'static + Clone + PartialEq>(&mut self, next: S) -> &mut Self {
+        if let Some(state) = self.borrow_resource_mut::<State<S>>() {
+            state.set(next);
+        }
+        self
+    }
+
+    /// Register a system only updated while the game is in the state,
+    /// ex: the simulation systems are frozen when the game is paused
+    pub fn register_system_in_state<S, T>(&mut self, state: S, system: T) -> &mut Self
+    where
+        S: 'static + Clone + PartialEq,
+        T: 'static + System,
+    {
+        self.register_system(InState::new(system, vec![state]))
+    }
+
+    /// Register a system run once each time the state is entered
+    pub fn register_system_on_enter<S, T>(&mut self, state: S, system: T) -> &mut Self
+    where
+        S: 'static + Clone + PartialEq,
+        T: 'static + System,
+    {
+        self.schedules_mut::<S>()
+            .add_on_enter(state, Box::new(system));
+        self
+    }
+
+    /// Register a system run once each time the state is exited
+    pub fn register_system_on_exit<S, T>(&mut self, state: S, system: T) -> &mut Self
+    where
+        S: 'static + Clone + PartialEq,
+        T: 'static + System,
+    {
+        self.schedules_mut::<S>()
+            .add_on_exit(state, Box::new(system));
+        self
+    }
+
+    fn schedules_mut<S: 'static + Clone + PartialEq>(&mut self) -> &mut Schedules<S> {
+        let position = self
+            .states
+            .iter_mut()
+            .position(|schedules| schedules.as_any_mut().is::<Schedules<S>>());
+        let index = match position {
+            Some(index) => index,
+            None => {
+                self.states.push(Box::new(Schedules::<S>::new()));
+                self.states.len() - 1
+            }
+        };
+        self.states[index]
+            .as_any_mut()
+            .downcast_mut::<Schedules<S>>()
+            .unwrap()
+    }
+
     pub fn add_component_to_entity<T: 'static + Component>(
         &mut self,
         entity_id: usize,
@@ -37,6 +291,17 @@ impl World {
         self
     }
 
+    /// Add a component unless its key is already used in a unique index
+    pub fn try_add_component_to_entity<T: 'static + Component>(
+        &mut self,
+        entity_id: usize,
+        component: T,
+    ) -> Result<&mut Self, IndexError> {
+        self.entity_manager
+            .try_add_component_to_entity(entity_id, component)?;
+        Ok(self)
+    }
+
     pub fn borrow_component_from_entity<T: 'static + Component>(
         &self,
         entity_id: usize,
@@ -45,10 +310,250 @@ impl World {
             .borrow_component_for_entity::<T>(entity_id)
     }
 
+    pub fn has_component<T: 'static + Component>(&self, entity_id: usize) -> bool {
+        self.entity_manager.has_component::<T>(entity_id)
+    }
+
+    pub fn replace_component_for_entity<T: 'static + Component>(
+        &mut self,
+        entity_id: usize,
+        component: T,
+    ) -> &mut Self {
+        self.entity_manager
+            .replace_component_for_entity(entity_id, component);
+        self
+    }
+
+    pub fn try_replace_component_for_entity<T: 'static + Component>(
+        &mut self,
+        entity_id: usize,
+        component: T,
+    ) -> Result<&mut Self, IndexError> {
+        self.entity_manager
+            .try_replace_component_for_entity(entity_id, component)?;
+        Ok(self)
+    }
+
+    /// Find the entities by the value of the `#[component(index)]` field of T,
+    /// ex: `world.index::<Recipe>().unwrap().get("iron-gear")`
+    pub fn index<T: 'static + Indexed>(&mut self) -> Option<&ComponentIndex<T::Key>> {
+        self.entity_manager.index::<T>()
+    }
+
+    /// Same as `index`, fails if a component modified in place breaks a unique index
+    pub fn try_index<T: 'static + Indexed>(
+        &mut self,
+    ) -> Result<Option<&ComponentIndex<T::Key>>, IndexError> {
+        self.entity_manager.try_index::<T>()
+    }
+
+    /// Update every component T on all the cores, see `EntityManager::par_for_each`
+    #[cfg(feature = "parallel")]
+    pub fn par_for_each<T, F>(&mut self, f: F) -> &mut Self
+    where
+        T: 'static + Component + Send,
+        F: Fn(usize, &mut T) + Sync + Send,
+    {
+        self.entity_manager.par_for_each::<T, F>(f);
+        self
+    }
+
+    #[cfg(feature = "parallel")]
+    pub fn par_for_each_pair<T, U, F>(&mut self, f: F) -> &mut Self
+    where
+        T: 'static + Component + Send,
+        U: 'static + Component + Sync,
+        F: Fn(usize, &mut T, &U) + Sync + Send,
+    {
+        self.entity_manager.par_for_each_pair::<T, U, F>(f);
+        self
+    }
+
+    pub fn remove_component_from_entity<T: 'static + Component>(
+        &mut self,
+        entity_id: usize,
+    ) -> &mut Self {
+        self.entity_manager
+            .remove_component_from_entity::<T>(entity_id);
+        self
+    }
+
+    /// Insert the `Events<E>` resource and the system aging it, once per type
+    pub fn add_event<E: 'static>(&mut self) -> &mut Self {
+        if self.borrow_resource::<Events<E>>().is_none() {
+            self.insert_resource(Events::<E>::new());
+            self.register_system(UpdateEvents::<E>::default());
+        }
+        self
+    }
+
+    pub fn insert_resource<R: Resource>(&mut self, resource: R) -> &mut Self {
+        self.entity_manager.insert_resource(resource);
+        self
+    }
+
+    pub fn borrow_resource<R: Resource>(&self) -> Option<&R> {
+        self.entity_manager.borrow_resource::<R>()
+    }
+
+    pub fn borrow_resource_mut<R: Resource>(&mut self) -> Option<&mut R> {
+        self.entity_manager.borrow_resource_mut::<R>()
+    }
+
+    /// Add the prefabs of a JSON document, see `Prefab` for the format
+    pub fn load_prefabs(&mut self, text: &str) -> Result<&mut Self, PrefabError> {
+        if self.borrow_resource::<Prefabs>().is_none() {
+            self.insert_resource(Prefabs::new());
+        }
+        self.borrow_resource_mut::<Prefabs>()
+            .unwrap()
+            .load_json(text)?;
+        Ok(self)
+    }
+
+    pub fn spawn_prefab(&mut self, name: &str) -> Result<usize, PrefabError> {
+        self.entity_manager.spawn_prefab(name)
+    }
+
+    /// Save the world in the binary save format
+    pub fn save(&self) -> Result<Vec<u8>, SaveError> {
+        self.entity_manager.save()?.to_bytes()
+    }
+
+    /// Save the world in the human readable debug format
+    pub fn save_debug(&self) -> Result<String, SaveError> {
+        self.entity_manager.save()?.to_debug_string()
+    }
+
+    /// Deterministic hash of the world, compare it between peers to detect desyncs
+    pub fn state_hash(&self) -> StateHash {
+        self.entity_manager.state_hash()
+    }
+
+    /// Cheap copy of the entities and components, used for rollback and previews
+    pub fn snapshot(&self) -> Result<Snapshot, SnapshotError> {
+        self.entity_manager.snapshot()
+    }
+
+    pub fn restore(&mut self, snapshot: &Snapshot) -> Result<(), SnapshotError> {
+        self.entity_manager.restore(snapshot)
+    }
+
+    /// Register here the migrations of components whose schema changed
+    pub fn migrations_mut(&mut self) -> &mut Migrations {
+        self.entity_manager.migrations_mut()
+    }
+
+    /// Load a save produced by `save`, the world must have the saved components
+    /// registered and the saved resources inserted
+    pub fn load(&mut self, bytes: &[u8]) -> Result<(), SaveError> {
+        self.entity_manager.load(SaveData::from_bytes(bytes)?)
+    }
+
+    pub fn load_debug(&mut self, text: &str) -> Result<(), SaveError> {
+        self.entity_manager.load(SaveData::from_debug_str(text)?)
+    }
+
+    pub fn type_registry(&self) -> &TypeRegistry {
+        self.entity_manager.type_registry()
+    }
+
+    pub fn reflect_component_from_entity(
+        &self,
+        entity_id: usize,
+        component_name: &str,
+    ) -> Option<&dyn Reflect> {
+        self.entity_manager
+            .reflect_component_for_entity(entity_id, component_name)
+    }
+
+    pub fn reflect_component_mut_from_entity(
+        &mut self,
+        entity_id: usize,
+        component_name: &str,
+    ) -> Option<&mut dyn Reflect> {
+        self.entity_manager
+            .reflect_component_mut_for_entity(entity_id, component_name)
+    }
+
+    /// A report of the entities, their components, the signature groups and the systems
+    /// for debugging, print it or use `WorldDump::to_json`
+    pub fn dump(&self) -> WorldDump {
+        let mut dump = self.entity_manager.dump();
+        dump.systems = self
+            .systems
+            .iter()
+            .map(|system| system.name().to_string())
+            .collect();
+        dump
+    }
+
+    /// Record the time spent in each system over the last `window` ticks
+    pub fn enable_profiler(&mut self, window: usize) -> &mut Self {
+        self.profiler = Some(Profiler::new(window));
+        self.entity_manager.set_query_profiling(true);
+        self
+    }
+
+    pub fn disable_profiler(&mut self) -> Option<Profiler> {
+        self.entity_manager.set_query_profiling(false);
+        self.profiler.take()
+    }
+
+    pub fn profiler(&self) -> Option<&Profiler> {
+        self.profiler.as_ref()
+    }
+
+    /// Run the ticks fitting in a frame of `frame_time` real time, according to
+    /// the speed of the `Time` resource (none while paused), returns their number
+    pub fn run_frame(&mut self, frame_time: Duration) -> u32 {
+        let ticks = match self.borrow_resource_mut::<Time>() {
+            Some(time) => time.advance(frame_time),
+            None => 1,
+        };
+        for _ in 0..ticks {
+            self.update();
+        }
+        ticks
+    }
+
+    /// Run one tick, even when the `Time` resource is paused (step by step)
     pub fn update(&mut self) {
-        let delta_time = 1.0 / 60.0;
+        let delta_time = self
+            .borrow_resource::<Time>()
+            .map_or(1.0 / 60.0, Time::delta);
+        self.run_systems(delta_time);
+        if let Some(time) = self.borrow_resource_mut::<Time>() {
+            time.end_tick();
+        }
+    }
+
+    fn run_systems(&mut self, delta_time: f32) {
+        // the requested states are entered before the systems run
+        for schedules in self.states.iter_mut() {
+            schedules.apply(delta_time, &mut self.entity_manager);
+        }
+        let Some(profiler) = self.profiler.as_mut() else {
+            for system in self.systems.iter_mut() {
+                system.update(delta_time, &mut self.entity_manager);
+            }
+            return;
+        };
+
+        // queries run outside of the systems are not recorded
+        self.entity_manager.take_query_stats();
+        let tick_start = Instant::now();
+        let mut samples = Vec::with_capacity(self.systems.len());
         for system in self.systems.iter_mut() {
+            let start = Instant::now();
             system.update(delta_time, &mut self.entity_manager);
+            samples.push(SystemSample {
+                name: system.name().to_string(),
+                start: start.duration_since(tick_start),
+                duration: start.elapsed(),
+                query_stats: self.entity_manager.take_query_stats(),
+            });
         }
+        profiler.record_tick(tick_start, tick_start.elapsed(), samples);
     }
 }
@@ -0,0 +1,147 @@
+mod common;
+
+use common::Position;
+use ecs::world::World;
+use ecs::{entity_manager::EntityManager, system::System};
+use ecs_macros::Component;
+
+#[derive(Component, Debug, PartialEq)]
+struct Ore {
+    amount: u32,
+}
+
+struct MiningSystem;
+
+impl System for MiningSystem {
+    fn update(&mut self, _delta_time: f32, entity_manager: &mut EntityManager) {
+        let entities = entity_manager
+            .query_entities_pair::<Position, Ore>()
+            .unwrap();
+        for entity in entities {
+            let (_, ore) = entity_manager
+                .borrow_components_pair_for_entity::<Position, Ore>(entity)
+                .unwrap();
+            ore.amount -= 1;
+        }
+    }
+}
+
+fn new_world() -> World {
+    let mut world = World::new();
+    world.register_component::<Position>();
+    world.register_component::<Ore>();
+    world.register_system(MiningSystem);
+    world
+}
+
+fn ore_field(count: usize) -> impl Iterator<Item = (Position, Ore)> {
+    (0..count).map(|i| {
+        (
+            Position {
+                x: i as f32,
+                y: 0.0,
+            },
+            Ore { amount: 100 },
+        )
+    })
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spawn_batch() {
+        let mut world = new_world();
+        let entities = world.spawn_batch(ore_field(3));
+        assert_eq!(entities, vec![0, 1, 2]);
+
+        assert_eq!(
+            world.borrow_component_from_entity::<Position>(2),
+            Some(&Position { x: 2.0, y: 0.0 })
+        );
+
+        // the entities are part of the queries
+        world.update();
+        for entity in entities {
+            assert_eq!(
+                world.borrow_component_from_entity::<Ore>(entity),
+                Some(&Ore { amount: 99 })
+            );
+        }
+    }
+
+    #[test]
+    fn spawn_batch_matches_single_spawns() {
+        let mut batch = new_world();
+        batch.spawn_batch(ore_field(10));
+        batch.spawn_batch((0..5).map(|i| {
+            (Position {
+                x: i as f32,
+                y: 1.0,
+            },)
+        }));
+
+        let mut single = new_world();
+        for (position, ore) in ore_field(10) {
+            let entity = single.create_entity();
+            single.add_component_to_entity(entity, position);
+            single.add_component_to_entity(entity, ore);
+        }
+        for i in 0..5 {
+            let entity = single.create_entity();
+            single.add_component_to_entity(
+                entity,
+                Position {
+                    x: i as f32,
+                    y: 1.0,
+                },
+            );
+        }
+
+        assert_eq!(batch.state_hash(), single.state_hash());
+    }
+
+    #[test]
+    fn despawn_batch() {
+        let mut world = new_world();
+        world.spawn_batch(ore_field(5));
+        world.despawn_batch(&[1, 3, 3, 42]);
+
+        assert!(world.is_alive(0));
+        assert!(!world.is_alive(1));
+        assert!(!world.is_alive(3));
+        assert!(world.borrow_component_from_entity::<Ore>(1).is_none());
+
+        world.update();
+        assert_eq!(
+            world.borrow_component_from_entity::<Ore>(4),
+            Some(&Ore { amount: 99 })
+        );
+    }
+
+    #[test]
+    fn spawn_batch_reuses_ids() {
+        let mut world = new_world();
+        world.spawn_batch(ore_field(5));
+        world.despawn_batch(&[1, 3]);
+
+        let entities = world.spawn_batch(ore_field(3));
+        assert_eq!(entities, vec![3, 1, 5]);
+        assert_eq!(world.entity_generation(1), Some(1));
+        assert_eq!(world.entity_generation(5), Some(0));
+
+        // the reused entities are in the queries only once
+        world.update();
+        assert_eq!(
+            world.borrow_component_from_entity::<Ore>(1),
+            Some(&Ore { amount: 99 })
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn spawn_batch_of_unregistered_component() {
+        let mut world = World::new();
+        world.spawn_batch(ore_field(1));
+    }
+}
@@ -0,0 +1,198 @@
+mod common;
+
+use common::{Position, spawn_at};
+use ecs::dynamic::{DynamicComponentInfo, FieldKind};
+use ecs::reflect::Value;
+use ecs::transfer::CloneError;
+use ecs::world::World;
+use ecs_macros::Component;
+
+#[derive(Component, Clone, Debug, PartialEq)]
+#[component(clone)]
+struct Inserter {
+    #[component(entity)]
+    from: usize,
+    #[component(entity)]
+    to: Vec<usize>,
+}
+
+// a machine feeding itself, like a furnace with a fuel loop
+#[derive(Component, Clone, Debug, PartialEq)]
+#[component(clone)]
+struct Output {
+    #[component(entity)]
+    target: usize,
+}
+
+#[derive(Component, Clone, Debug, PartialEq)]
+#[component(clone)]
+struct Selected;
+
+// not clonable
+#[derive(Component, Debug, PartialEq)]
+struct RenderHandle {
+    id: u64,
+}
+
+#[derive(Component, Clone, Debug, PartialEq)]
+#[component(clone)]
+struct NetworkId(#[component(unique)] u32);
+
+fn new_world() -> World {
+    let mut world = World::new();
+    world.register_component::<Position>();
+    world.register_component::<Inserter>();
+    world.register_component::<Output>();
+    world.register_component::<Selected>();
+    world.register_component::<RenderHandle>();
+    world.register_component::<NetworkId>();
+    world
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clone_entity_with_its_components() {
+        let mut world = new_world();
+        let chest = spawn_at(&mut world, 1.0, 2.0);
+        world.add_component_to_entity(chest, Selected);
+
+        let copy = world.clone_entity(chest).unwrap();
+
+        assert_ne!(copy, chest);
+        assert_eq!(
+            world.borrow_component_from_entity::<Position>(copy),
+            Some(&Position { x: 1.0, y: 2.0 })
+        );
+        assert!(world.has_component::<Selected>(copy));
+        // the copies are independent
+        world.replace_component_for_entity(copy, Position { x: 5.0, y: 2.0 });
+        assert_eq!(
+            world.borrow_component_from_entity::<Position>(chest),
+            Some(&Position { x: 1.0, y: 2.0 })
+        );
+    }
+
+    #[test]
+    fn copy_is_queried_like_the_original() {
+        let mut world = new_world();
+        let chest = spawn_at(&mut world, 0.0, 0.0);
+
+        let copy = world.clone_entity(chest).unwrap();
+
+        let dump = world.dump();
+        assert_eq!(
+            dump.entity(copy).unwrap().signature,
+            dump.entity(chest).unwrap().signature
+        );
+    }
+
+    #[test]
+    fn self_relations_point_to_the_copy() {
+        let mut world = new_world();
+        let furnace = world.create_entity();
+        world.add_component_to_entity(furnace, Output { target: furnace });
+
+        let copy = world.clone_entity(furnace).unwrap();
+
+        assert_eq!(
+            world.borrow_component_from_entity::<Output>(copy),
+            Some(&Output { target: copy })
+        );
+    }
+
+    #[test]
+    fn relations_inside_the_copied_group_are_remapped() {
+        let mut world = new_world();
+        let belt = world.create_entity();
+        let chest = world.create_entity();
+        let inserter = world.create_entity();
+        world.add_component_to_entity(
+            inserter,
+            Inserter {
+                from: belt,
+                to: vec![chest],
+            },
+        );
+
+        // a blueprint of the inserter and the chest, the belt stays shared
+        let entity_map = world.clone_entities(&[inserter, chest]).unwrap();
+
+        assert_eq!(
+            world.borrow_component_from_entity::<Inserter>(entity_map[&inserter]),
+            Some(&Inserter {
+                from: belt,
+                to: vec![entity_map[&chest]],
+            })
+        );
+        assert_eq!(
+            world.borrow_component_from_entity::<Inserter>(inserter),
+            Some(&Inserter {
+                from: belt,
+                to: vec![chest],
+            })
+        );
+    }
+
+    #[test]
+    fn dynamic_components_are_copied() {
+        let mut world = new_world();
+        let id = world
+            .register_dynamic_component(
+                DynamicComponentInfo::new("Overclock").with_field("speed", FieldKind::Float),
+            )
+            .unwrap();
+        let mut component = world.new_dynamic_component(id).unwrap();
+        component.set("speed", Value::Float(1.5)).unwrap();
+        let machine = world.create_entity();
+        world.add_dynamic_component_to_entity(machine, component);
+
+        let copy = world.clone_entity(machine).unwrap();
+
+        assert_eq!(
+            world
+                .borrow_dynamic_component_from_entity(copy, id)
+                .unwrap()
+                .get("speed"),
+            Some(&Value::Float(1.5))
+        );
+    }
+
+    #[test]
+    fn non_clonable_components_prevent_the_copy() {
+        let mut world = new_world();
+        let sprite = spawn_at(&mut world, 0.0, 0.0);
+        world.add_component_to_entity(sprite, RenderHandle { id: 3 });
+
+        assert_eq!(
+            world.clone_entity(sprite),
+            Err(CloneError::NotClonable("RenderHandle"))
+        );
+        assert_eq!(world.dump().entities.len(), 1);
+    }
+
+    #[test]
+    fn unique_keys_prevent_the_copy() {
+        let mut world = new_world();
+        let chest = world.create_entity();
+        world.add_component_to_entity(chest, NetworkId(4));
+
+        assert_eq!(
+            world.clone_entity(chest),
+            Err(CloneError::UniqueIndex("NetworkId"))
+        );
+    }
+
+    #[test]
+    fn unknown_entities_are_rejected() {
+        let mut world = new_world();
+        let chest = world.create_entity();
+        world.remove_entity(chest);
+
+        assert_eq!(
+            world.clone_entity(chest),
+            Err(CloneError::UnknownEntity(chest))
+        );
+    }
+}
@@ -0,0 +1,58 @@
+// fixtures shared by the integration tests, included with `mod common;`
+// every test file is its own crate and uses a part of them only
+#![allow(dead_code)]
+
+use ecs::world::World;
+use ecs::{entity_manager::EntityManager, system::System};
+use ecs_macros::Component;
+use serde::{Deserialize, Serialize};
+
+// serializable, clonable and shown by the dumps, so it can be saved, snapshot and moved
+#[derive(Component, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[component(serializable, clone, debug)]
+pub struct Position {
+    pub x: f32,
+    pub y: f32,
+}
+
+#[derive(Component, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[component(serializable, clone, debug)]
+pub struct Velocity {
+    pub x: f32,
+    pub y: f32,
+}
+
+// moves the entities by their velocity
+pub struct SpeedSystem;
+
+impl System for SpeedSystem {
+    fn update(&mut self, _delta_time: f32, entity_manager: &mut EntityManager) {
+        let entities = entity_manager
+            .query_entities_pair::<Velocity, Position>()
+            .unwrap();
+        for entity in entities {
+            let (velocity, position) = entity_manager
+                .borrow_components_pair_for_entity::<Velocity, Position>(entity)
+                .unwrap();
+            position.x += velocity.x;
+            position.y += velocity.y;
+        }
+    }
+}
+
+// an entity with a single component, which must be registered
+pub fn spawn<T: 'static + ecs::component::Component>(world: &mut World, component: T) -> usize {
+    let entity = world.create_entity();
+    world.add_component_to_entity(entity, component);
+    entity
+}
+
+pub fn spawn_at(world: &mut World, x: f32, y: f32) -> usize {
+    spawn(world, Position { x, y })
+}
+
+pub fn spawn_moving(world: &mut World, x: f32, y: f32, velocity: Velocity) -> usize {
+    let entity = spawn_at(world, x, y);
+    world.add_component_to_entity(entity, velocity);
+    entity
+}
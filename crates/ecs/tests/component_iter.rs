@@ -0,0 +1,94 @@
+mod common;
+
+use common::Position;
+use ecs::world::World;
+use ecs::{entity_manager::EntityManager, system::System};
+use ecs_macros::Component;
+
+#[derive(Component, Debug, PartialEq)]
+struct Weight {
+    value: f32,
+}
+
+// moves every entity down by its weight
+struct GravitySystem;
+
+impl System for GravitySystem {
+    fn update(&mut self, _delta_time: f32, entity_manager: &mut EntityManager) {
+        let falls: Vec<(usize, f32)> = entity_manager
+            .iter_components::<Position>()
+            .filter_map(|(entity, _)| {
+                let weight = entity_manager.borrow_component_for_entity::<Weight>(entity)?;
+                Some((entity, weight.value))
+            })
+            .collect();
+
+        for (entity, position) in entity_manager.iter_components_mut::<Position>() {
+            if let Some((_, fall)) = falls.iter().find(|(id, _)| *id == entity) {
+                position.y -= fall;
+            }
+        }
+    }
+}
+
+fn new_world() -> World {
+    let mut world = World::new();
+    world.register_component::<Position>();
+    world.register_component::<Weight>();
+    world.register_system(GravitySystem);
+    world
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn iter_components_after_removal() {
+        let mut entity_manager = EntityManager::new();
+        entity_manager.register_component::<Position>();
+        for i in 0..4 {
+            let entity = entity_manager.create_entity();
+            entity_manager.add_component_to_entity(
+                entity,
+                Position {
+                    x: i as f32,
+                    y: 0.0,
+                },
+            );
+        }
+        entity_manager.remove_entity(1);
+
+        assert_eq!(entity_manager.component_entities::<Position>(), &[0, 3, 2]);
+        for (entity, position) in entity_manager.iter_components::<Position>() {
+            assert_eq!(position.x, entity as f32);
+        }
+    }
+
+    #[test]
+    fn iter_components_mut_looks_up_other_components() {
+        let mut world = new_world();
+        let light = world.create_entity();
+        world.add_component_to_entity(light, Position { x: 0.0, y: 10.0 });
+        world.add_component_to_entity(light, Weight { value: 1.0 });
+        let floating = world.create_entity();
+        world.add_component_to_entity(floating, Position { x: 0.0, y: 10.0 });
+        let heavy = world.create_entity();
+        world.add_component_to_entity(heavy, Weight { value: 5.0 });
+        world.add_component_to_entity(heavy, Position { x: 0.0, y: 10.0 });
+
+        world.update();
+
+        assert_eq!(
+            world.borrow_component_from_entity::<Position>(light),
+            Some(&Position { x: 0.0, y: 9.0 })
+        );
+        assert_eq!(
+            world.borrow_component_from_entity::<Position>(floating),
+            Some(&Position { x: 0.0, y: 10.0 })
+        );
+        assert_eq!(
+            world.borrow_component_from_entity::<Position>(heavy),
+            Some(&Position { x: 0.0, y: 5.0 })
+        );
+    }
+}
@@ -0,0 +1,255 @@
+mod common;
+
+use common::{Position, Velocity, spawn_moving};
+use ecs::entity_manager::EntityManager;
+use ecs::spatial::{SpatialIndex, TilePosition, TileRect};
+use ecs::system::System;
+use ecs::world::World;
+use ecs_macros::Resource;
+
+#[derive(Resource, Default)]
+struct Seen {
+    entities: Vec<usize>,
+    including_disabled: Vec<usize>,
+}
+
+// record the entities seen by the queries
+struct QuerySystem;
+
+impl System for QuerySystem {
+    fn update(&mut self, _delta_time: f32, entity_manager: &mut EntityManager) {
+        let entities = entity_manager
+            .query_entities_pair::<Position, Velocity>()
+            .unwrap();
+        let including_disabled = entity_manager
+            .query_entities_pair_including_disabled::<Position, Velocity>()
+            .unwrap();
+        let seen = entity_manager.borrow_resource_mut::<Seen>().unwrap();
+        seen.entities = entities;
+        seen.including_disabled = including_disabled;
+    }
+}
+
+fn new_world() -> World {
+    let mut world = World::new();
+    world.register_component::<Position>();
+    world.register_component::<Velocity>();
+    world.insert_resource(Seen::default());
+    world.register_system(QuerySystem);
+
+    for i in 0..4 {
+        spawn_moving(&mut world, i as f32, 0.0, Velocity { x: 1.0, y: 1.0 });
+    }
+    world
+}
+
+fn new_entity_manager() -> EntityManager {
+    let mut entity_manager = EntityManager::new();
+    entity_manager.register_component::<Position>();
+    for i in 0..4 {
+        let entity = entity_manager.create_entity();
+        entity_manager.add_component_to_entity(
+            entity,
+            Position {
+                x: i as f32,
+                y: 0.0,
+            },
+        );
+    }
+    entity_manager
+}
+
+// the entities seen by the queries at the last update
+fn seen(world: &mut World) -> (Vec<usize>, Vec<usize>) {
+    world.update();
+    let seen = world.borrow_resource::<Seen>().unwrap();
+    let mut entities = seen.entities.clone();
+    let mut including_disabled = seen.including_disabled.clone();
+    entities.sort();
+    including_disabled.sort();
+    (entities, including_disabled)
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn queries_skip_disabled_entities() {
+        let mut world = new_world();
+        world.disable_entity(1).disable_entity(3);
+
+        let (entities, including_disabled) = seen(&mut world);
+
+        assert_eq!(entities, vec![0, 2]);
+        assert_eq!(including_disabled, vec![0, 1, 2, 3]);
+        assert!(world.is_disabled(1));
+        assert!(!world.is_disabled(0));
+        assert_eq!(world.disabled_entities(), vec![1, 3]);
+    }
+
+    #[test]
+    fn disabled_entities_keep_their_components() {
+        let mut world = new_world();
+        world.disable_entity(2);
+
+        assert!(world.is_alive(2));
+        assert_eq!(
+            world.borrow_component_from_entity::<Position>(2),
+            Some(&Position { x: 2.0, y: 0.0 })
+        );
+
+        world.enable_entity(2);
+        let (entities, _) = seen(&mut world);
+        assert_eq!(entities, vec![0, 1, 2, 3]);
+        assert!(world.disabled_entities().is_empty());
+    }
+
+    #[test]
+    fn reused_ids_start_enabled() {
+        let mut world = new_world();
+        world.disable_entity(1).remove_entity(1);
+
+        assert!(!world.is_disabled(1));
+        let entity = world.create_entity();
+        assert_eq!(entity, 1);
+        assert!(!world.is_disabled(entity));
+        assert!(world.disabled_entities().is_empty());
+    }
+
+    #[test]
+    fn dead_entities_can_not_be_disabled() {
+        let mut world = new_world();
+        world.disable_entity(42);
+
+        assert!(!world.is_disabled(42));
+        assert!(world.disabled_entities().is_empty());
+    }
+
+    #[test]
+    fn disabled_flag_is_saved() {
+        let mut world = new_world();
+        world.disable_entity(0);
+
+        let bytes = world.save().unwrap();
+        let mut loaded = new_world();
+        loaded.load(&bytes).unwrap();
+
+        assert_eq!(loaded.disabled_entities(), vec![0]);
+        assert_eq!(loaded.state_hash(), world.state_hash());
+        let (entities, _) = seen(&mut loaded);
+        assert_eq!(entities, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn disabled_flag_is_part_of_the_state() {
+        let mut world = new_world();
+        let hash = world.state_hash();
+        let snapshot = world.snapshot().unwrap();
+
+        world.disable_entity(0);
+        assert_ne!(world.state_hash(), hash);
+
+        world.restore(&snapshot).unwrap();
+        assert_eq!(world.state_hash(), hash);
+        assert!(!world.is_disabled(0));
+    }
+
+    #[test]
+    fn clones_and_moved_entities_stay_disabled() {
+        let mut world = new_world();
+        world.disable_entity(0);
+
+        let copy = world.clone_entity(0).unwrap();
+        assert!(world.is_disabled(copy));
+
+        let mut other = new_world();
+        let moved = world.move_entity(0, &mut other).unwrap();
+        assert!(other.is_disabled(moved));
+        assert_eq!(world.disabled_entities(), vec![copy]);
+    }
+
+    #[test]
+    fn dump_shows_disabled_entities() {
+        let mut world = new_world();
+        world.disable_entity(2);
+
+        let dump = world.dump();
+        assert!(dump.entities[2].disabled);
+        assert!(!dump.entities[1].disabled);
+        assert!(dump.to_string().contains("(generation 0, disabled)"));
+    }
+
+    #[test]
+    fn component_iterators_skip_disabled_entities() {
+        let mut entity_manager = new_entity_manager();
+        entity_manager.disable_entity(1);
+
+        for (_, position) in entity_manager.iter_components_mut::<Position>() {
+            position.y += 1.0;
+        }
+        let y: Vec<(usize, f32)> = entity_manager
+            .iter_components::<Position>()
+            .map(|(entity_id, position)| (entity_id, position.y))
+            .collect();
+        assert_eq!(y, vec![(0, 1.0), (2, 1.0), (3, 1.0)]);
+    }
+
+    #[test]
+    fn component_slices_include_disabled_entities() {
+        let mut entity_manager = new_entity_manager();
+        entity_manager.disable_entity(1);
+
+        for position in entity_manager.borrow_components_mut::<Position>() {
+            position.y += 1.0;
+        }
+        assert_eq!(
+            entity_manager.component_entities::<Position>(),
+            &[0, 1, 2, 3]
+        );
+        assert!(
+            entity_manager
+                .borrow_components::<Position>()
+                .iter()
+                .all(|position| position.y == 1.0)
+        );
+    }
+
+    #[test]
+    fn spatial_index_includes_disabled_entities() {
+        let mut world = new_world();
+        world.register_component::<TilePosition>();
+        world.add_component_to_entity(0, TilePosition { x: 1, y: 1 });
+        world.add_component_to_entity(1, TilePosition { x: 1, y: 1 });
+        world.disable_entity(1);
+
+        let index = world.borrow_resource::<SpatialIndex>().unwrap();
+        assert_eq!(index.at(1, 1), &[0, 1]);
+        assert_eq!(index.in_rect(TileRect::new(0, 0, 3, 3)), vec![0, 1]);
+        let enabled: Vec<usize> = index
+            .at(1, 1)
+            .iter()
+            .copied()
+            .filter(|entity_id| !world.is_disabled(*entity_id))
+            .collect();
+        assert_eq!(enabled, vec![0]);
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn parallel_iteration_skips_disabled_entities() {
+        let mut world = new_world();
+        world.disable_entity(1);
+
+        world.par_for_each::<Position, _>(|_, position| position.y += 1.0);
+
+        let y: Vec<f32> = (0..4)
+            .map(|entity| {
+                world
+                    .borrow_component_from_entity::<Position>(entity)
+                    .unwrap()
+                    .y
+            })
+            .collect();
+        assert_eq!(y, vec![1.0, 0.0, 1.0, 1.0]);
+    }
+}
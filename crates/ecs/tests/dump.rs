@@ -0,0 +1,122 @@
+mod common;
+
+use common::{Position, spawn_at};
+use ecs::dynamic::{DynamicComponentInfo, FieldKind};
+use ecs::reflect::Value;
+use ecs::world::World;
+use ecs::{entity_manager::EntityManager, system::System};
+use ecs_macros::Component;
+
+#[derive(Component)]
+struct Velocity {
+    x: f32,
+    y: f32,
+}
+
+struct SpeedSystem;
+
+impl System for SpeedSystem {
+    fn update(&mut self, _delta_time: f32, _entity_manager: &mut EntityManager) {}
+
+    fn name(&self) -> &str {
+        "speed"
+    }
+}
+
+fn new_world() -> (World, usize, usize) {
+    let mut world = World::new();
+    world.register_component::<Position>();
+    world.register_component::<Velocity>();
+    world.register_system(SpeedSystem);
+
+    let first = spawn_at(&mut world, 0.0, 0.0);
+    let second = spawn_at(&mut world, 10.0, 10.0);
+    let removed = world.create_entity();
+    world.add_component_to_entity(second, Velocity { x: 1.0, y: 1.0 });
+    world.remove_entity(removed);
+    (world, first, second)
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dump_entities_and_groups() {
+        let (world, first, second) = new_world();
+        let dump = world.dump();
+
+        assert_eq!(dump.entities.len(), 2);
+        assert_eq!(dump.free_ids, vec![2]);
+        assert_eq!(dump.systems, vec!["speed".to_string()]);
+
+        let entity = dump.entity(second).unwrap();
+        let names: Vec<&str> = entity
+            .components
+            .iter()
+            .map(|component| component.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["Position", "Velocity"]);
+        assert_eq!(
+            entity.component("Position").unwrap().value.as_deref(),
+            Some("Position { x: 10.0, y: 10.0 }")
+        );
+        // Velocity is not marked with `#[component(debug)]`
+        assert_eq!(entity.component("Velocity").unwrap().value, None);
+
+        assert_eq!(dump.groups.len(), 2);
+        let group = dump
+            .groups
+            .iter()
+            .find(|group| group.entities == vec![first])
+            .unwrap();
+        assert_eq!(group.components, vec!["Position".to_string()]);
+        assert_eq!(group.signature, dump.entity(first).unwrap().signature);
+    }
+
+    #[test]
+    fn dump_as_text_and_json() {
+        let (world, _, _) = new_world();
+        let dump = world.dump();
+
+        let text = dump.to_string();
+        assert!(text.contains("entities (2):"));
+        assert!(text.contains("#1 (generation 0) 0b11 [Position, Velocity]"));
+        assert!(text.contains("Position: Position { x: 0.0, y: 0.0 }"));
+        assert!(text.contains("free ids: [2]"));
+
+        let json: serde_json::Value = serde_json::from_str(&dump.to_json()).unwrap();
+        assert_eq!(json["entities"][1]["components"][1]["name"], "Velocity");
+        assert_eq!(
+            json["entities"][1]["components"][1]["value"],
+            serde_json::Value::Null
+        );
+        assert_eq!(json["systems"][0], "speed");
+    }
+
+    #[test]
+    fn dump_dynamic_components() {
+        let (mut world, first, _) = new_world();
+        let overclock = world
+            .register_dynamic_component(
+                DynamicComponentInfo::new("Overclock").with_field("bonus", FieldKind::Float),
+            )
+            .unwrap();
+        let component = world
+            .new_dynamic_component(overclock)
+            .unwrap()
+            .with("bonus", Value::Float(0.5))
+            .unwrap();
+        world.add_dynamic_component_to_entity(first, component);
+
+        let dump = world.dump();
+        assert_eq!(
+            dump.entity(first)
+                .unwrap()
+                .component("Overclock")
+                .unwrap()
+                .value
+                .as_deref(),
+            Some(r#"{"bonus":0.5}"#)
+        );
+    }
+}
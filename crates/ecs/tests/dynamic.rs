@@ -0,0 +1,242 @@
+use ecs::dynamic::{ComponentId, DynamicComponentInfo, DynamicError, FieldKind};
+use ecs::reflect::{ReflectError, Value};
+use ecs::serialization::SaveError;
+use ecs::world::World;
+use ecs::{entity_manager::EntityManager, system::System};
+use ecs_macros::Component;
+use serde::{Deserialize, Serialize};
+
+#[derive(Component, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[component(serializable, clone)]
+struct Crafter {
+    speed: f32,
+}
+
+// a mod adds overclocking to the machines without recompiling the game
+fn overclock_info() -> DynamicComponentInfo {
+    DynamicComponentInfo::new("Overclock")
+        .with_field("bonus", FieldKind::Float)
+        .with_field("enabled", FieldKind::Bool)
+}
+
+struct OverclockSystem {
+    overclock: ComponentId,
+}
+
+impl System for OverclockSystem {
+    fn update(&mut self, _delta_time: f32, entity_manager: &mut EntityManager) {
+        let entities = entity_manager
+            .query_entities_with_dynamic::<Crafter>(&[self.overclock])
+            .unwrap();
+        for entity in entities {
+            let overclock = entity_manager
+                .borrow_dynamic_component_for_entity(entity, self.overclock)
+                .unwrap();
+            let Some(Value::Float(bonus)) = overclock.get("bonus").cloned() else {
+                continue;
+            };
+            entity_manager
+                .borrow_components_for_entity::<Crafter>(entity)
+                .unwrap()
+                .speed += bonus as f32;
+        }
+    }
+}
+
+fn new_world() -> (World, ComponentId) {
+    let mut world = World::new();
+    world.register_component::<Crafter>();
+    let overclock = world.register_dynamic_component(overclock_info()).unwrap();
+    (world, overclock)
+}
+
+fn overclocked_machine(world: &mut World, overclock: ComponentId) -> usize {
+    let entity = world.create_entity();
+    world.add_component_to_entity(entity, Crafter { speed: 1.0 });
+    let component = world
+        .new_dynamic_component(overclock)
+        .unwrap()
+        .with("bonus", Value::Float(0.5))
+        .unwrap();
+    world.add_dynamic_component_to_entity(entity, component);
+    entity
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dynamic_components_are_queried_with_static_ones() {
+        let (mut world, overclock) = new_world();
+        world.register_system(OverclockSystem { overclock });
+
+        let overclocked = overclocked_machine(&mut world, overclock);
+        let normal = world.create_entity();
+        world.add_component_to_entity(normal, Crafter { speed: 1.0 });
+
+        world.update();
+        assert_eq!(
+            world.borrow_component_from_entity::<Crafter>(overclocked),
+            Some(&Crafter { speed: 1.5 })
+        );
+        assert_eq!(
+            world.borrow_component_from_entity::<Crafter>(normal),
+            Some(&Crafter { speed: 1.0 })
+        );
+
+        world.remove_entity(overclocked);
+        assert!(
+            world
+                .borrow_dynamic_component_from_entity(overclocked, overclock)
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn fields_are_type_checked() {
+        let (world, overclock) = new_world();
+        let mut component = world.new_dynamic_component(overclock).unwrap();
+
+        assert_eq!(component.get("enabled"), Some(&Value::Bool(false)));
+        // integers are converted for float fields
+        component.set("bonus", Value::Int(2)).unwrap();
+        assert_eq!(component.get("bonus"), Some(&Value::Float(2.0)));
+        assert_eq!(
+            component.set("enabled", Value::Int(1)),
+            Err(ReflectError::TypeMismatch {
+                expected: "bool",
+                found: Value::Int(1)
+            })
+        );
+        assert_eq!(
+            component.set("unknown", Value::Int(1)),
+            Err(ReflectError::FieldNotFound("unknown".to_string()))
+        );
+    }
+
+    #[test]
+    fn names_are_unique() {
+        let (mut world, overclock) = new_world();
+        assert_eq!(world.dynamic_component_id("Overclock"), Some(overclock));
+        assert_eq!(
+            world.register_dynamic_component(overclock_info()),
+            Err(DynamicError::DuplicateName("Overclock".to_string()))
+        );
+        assert_eq!(
+            world.register_dynamic_component(DynamicComponentInfo::new("Crafter")),
+            Err(DynamicError::DuplicateName("Crafter".to_string()))
+        );
+    }
+
+    #[test]
+    fn reflect_dynamic_component_by_name() {
+        let (mut world, overclock) = new_world();
+        let entity = overclocked_machine(&mut world, overclock);
+
+        let component = world
+            .reflect_component_mut_from_entity(entity, "Overclock")
+            .unwrap();
+        component.set_path("enabled", Value::Bool(true)).unwrap();
+
+        let component = world
+            .reflect_component_from_entity(entity, "Overclock")
+            .unwrap();
+        assert_eq!(component.get_path("enabled"), Ok(Value::Bool(true)));
+        assert_eq!(component.get_path("bonus"), Ok(Value::Float(0.5)));
+    }
+
+    #[test]
+    fn non_finite_dynamic_floats_are_not_saved() {
+        let (mut world, overclock) = new_world();
+        let entity = world.create_entity();
+        let component = world
+            .new_dynamic_component(overclock)
+            .unwrap()
+            .with("bonus", Value::Float(f64::NAN))
+            .unwrap();
+        world.add_dynamic_component_to_entity(entity, component);
+
+        assert!(matches!(world.save(), Err(SaveError::Encode(_))));
+    }
+
+    #[test]
+    fn dynamic_components_are_saved() {
+        let (mut world, overclock) = new_world();
+        let entity = overclocked_machine(&mut world, overclock);
+        let bytes = world.save().unwrap();
+
+        let (mut loaded, overclock) = new_world();
+        loaded.load(&bytes).unwrap();
+        assert_eq!(
+            loaded
+                .borrow_dynamic_component_from_entity(entity, overclock)
+                .and_then(|component| component.get("bonus")),
+            Some(&Value::Float(0.5))
+        );
+        assert_eq!(loaded.state_hash(), world.state_hash());
+    }
+
+    #[test]
+    fn dynamic_components_in_prefabs() {
+        let (mut world, overclock) = new_world();
+        world
+            .load_prefabs(
+                r#"{
+                    "assembler": {
+                        "components": {
+                            "Crafter": { "speed": 1.0 },
+                            "Overclock": { "bonus": 0.25 }
+                        }
+                    }
+                }"#,
+            )
+            .unwrap();
+
+        let entity = world.spawn_prefab("assembler").unwrap();
+        let component = world
+            .borrow_dynamic_component_from_entity(entity, overclock)
+            .unwrap();
+        assert_eq!(component.get("bonus"), Some(&Value::Float(0.25)));
+        assert_eq!(component.get("enabled"), Some(&Value::Bool(false)));
+    }
+
+    #[test]
+    fn restore_dynamic_components() {
+        let (mut world, overclock) = new_world();
+        world.register_system(OverclockSystem { overclock });
+        let entity = overclocked_machine(&mut world, overclock);
+        let snapshot = world.snapshot().unwrap();
+        let hash = world.state_hash();
+
+        world.update();
+        world.remove_entity(entity);
+        world.restore(&snapshot).unwrap();
+
+        assert_eq!(world.state_hash(), hash);
+        assert!(
+            world
+                .borrow_dynamic_component_from_entity(entity, overclock)
+                .is_some()
+        );
+    }
+
+    #[test]
+    fn unregister_dynamic_component() {
+        let (mut world, overclock) = new_world();
+        let entity = overclocked_machine(&mut world, overclock);
+        world.unregister_dynamic_component(overclock);
+
+        assert_eq!(world.dynamic_component_id("Overclock"), None);
+        assert!(
+            world
+                .borrow_dynamic_component_from_entity(entity, overclock)
+                .is_none()
+        );
+        // the entity keeps its static components
+        assert!(
+            world
+                .borrow_component_from_entity::<Crafter>(entity)
+                .is_some()
+        );
+    }
+}
@@ -203,4 +203,50 @@ mod tests {
             "second entity y position should be updated by the velocity"
         );
     }
+
+    #[test]
+    fn unregister_component() {
+        let mut entity_manager = EntityManager::new();
+
+        entity_manager.register_component::<Position>();
+        entity_manager.register_component::<Velocity>();
+
+        let first_entity = entity_manager.create_entity();
+        let second_entity = entity_manager.create_entity();
+
+        entity_manager.add_component_to_entity(first_entity, Position { x: 0.0, y: 0.0 });
+        entity_manager.add_component_to_entity(second_entity, Position { x: 10.0, y: 10.0 });
+        entity_manager.add_component_to_entity(second_entity, Velocity { x: 1.0, y: 1.0 });
+
+        entity_manager.unregister_component::<Velocity>();
+
+        assert!(
+            entity_manager.query_entities::<Velocity>().is_none(),
+            "unregistered component should not be queryable"
+        );
+
+        let mut entities = entity_manager.query_entities::<Position>().unwrap();
+        entities.sort();
+        assert_eq!(
+            entities,
+            vec![first_entity, second_entity],
+            "entities should keep their other components"
+        );
+
+        // Weight reuses the bit freed by Velocity, no entity should match it
+        entity_manager.register_component::<Weight>();
+        assert_eq!(
+            entity_manager.query_entities::<Weight>().unwrap(),
+            Vec::<usize>::new(),
+            "stale Velocity bits should have been stripped"
+        );
+
+        entity_manager.add_component_to_entity(second_entity, Weight { value: 1.0 });
+        assert_eq!(
+            entity_manager
+                .query_entities_pair::<Position, Weight>()
+                .unwrap(),
+            vec![second_entity]
+        );
+    }
 }
@@ -0,0 +1,125 @@
+use ecs::entity_manager::EntityManager;
+use ecs::event::{EventReader, Events};
+use ecs::system::System;
+use ecs::world::World;
+use ecs_macros::Resource;
+
+// sent when an assembler finishes a craft
+struct Crafted(u32);
+
+#[derive(Resource, Default)]
+struct Received {
+    before: Vec<u32>,
+    after: Vec<u32>,
+}
+
+// sends the next event of `pending` each tick
+struct CraftSystem {
+    pending: Vec<u32>,
+}
+
+impl System for CraftSystem {
+    fn update(&mut self, _delta_time: f32, entity_manager: &mut EntityManager) {
+        if self.pending.is_empty() {
+            return;
+        }
+        let event = Crafted(self.pending.remove(0));
+        entity_manager
+            .borrow_resource_mut::<Events<Crafted>>()
+            .unwrap()
+            .send(event);
+    }
+}
+
+// registered before or after the system sending the events
+struct CounterSystem {
+    reader: EventReader<Crafted>,
+    before: bool,
+}
+
+impl System for CounterSystem {
+    fn update(&mut self, _delta_time: f32, entity_manager: &mut EntityManager) {
+        let events = entity_manager.borrow_resource::<Events<Crafted>>().unwrap();
+        let read: Vec<u32> = self.reader.read(events).map(|event| event.0).collect();
+        let received = entity_manager.borrow_resource_mut::<Received>().unwrap();
+        if self.before {
+            received.before.extend(read);
+        } else {
+            received.after.extend(read);
+        }
+    }
+}
+
+fn new_world(pending: Vec<u32>) -> World {
+    let mut world = World::new();
+    world
+        .add_event::<Crafted>()
+        .insert_resource(Received::default())
+        .register_system(CounterSystem {
+            reader: EventReader::new(),
+            before: true,
+        })
+        .register_system(CraftSystem { pending })
+        .register_system(CounterSystem {
+            reader: EventReader::new(),
+            before: false,
+        });
+    world
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events_are_read_once_per_reader() {
+        let mut world = new_world(vec![1, 2, 3]);
+        for _ in 0..6 {
+            world.update();
+        }
+
+        let received = world.borrow_resource::<Received>().unwrap();
+        assert_eq!(received.before, vec![1, 2, 3]);
+        assert_eq!(received.after, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn readers_skip_the_events_read_or_dropped() {
+        let mut events = Events::new();
+        let mut reader = EventReader::new();
+        events.send(Crafted(1));
+        events.send(Crafted(2));
+        assert_eq!(reader.len(&events), 2);
+        assert_eq!(
+            reader.read(&events).map(|event| event.0).collect::<Vec<_>>(),
+            vec![1, 2]
+        );
+        assert!(reader.is_empty(&events));
+
+        // every call of `iter` sees the events again
+        assert_eq!(events.iter().count(), 2);
+        assert_eq!(events.iter().count(), 2);
+
+        events.update();
+        events.send(Crafted(3));
+        assert_eq!(
+            reader.read(&events).map(|event| event.0).collect::<Vec<_>>(),
+            vec![3]
+        );
+
+        // the reader missed two ticks
+        events.send(Crafted(4));
+        events.update();
+        events.update();
+        events.send(Crafted(5));
+        assert_eq!(reader.len(&events), 1);
+        assert_eq!(
+            reader.read(&events).map(|event| event.0).collect::<Vec<_>>(),
+            vec![5]
+        );
+
+        events.send(Crafted(6));
+        events.drain();
+        assert!(reader.is_empty(&events));
+        assert_eq!(reader.read(&events).count(), 0);
+    }
+}
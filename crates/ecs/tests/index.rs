@@ -0,0 +1,377 @@
+mod common;
+
+use common::spawn;
+use ecs::entity_manager::EntityManager;
+use ecs::index::{IndexError, IndexKind};
+use ecs::prefab::PrefabError;
+use ecs::reflect::Value;
+use ecs::serialization::SaveError;
+use ecs::transfer::TransferError;
+use ecs::world::World;
+use ecs_macros::Component;
+use serde::{Deserialize, Serialize};
+
+#[derive(Component, Clone, Debug, PartialEq)]
+#[component(clone)]
+struct Recipe {
+    #[component(index)]
+    name: String,
+    progress: f32,
+}
+
+#[derive(Component, Clone, Debug, PartialEq)]
+#[component(clone)]
+struct Tier {
+    #[component(index = "ordered")]
+    level: u32,
+}
+
+#[derive(Component, Clone, Debug, PartialEq)]
+#[component(clone)]
+struct NetworkId(#[component(unique)] u64);
+
+#[derive(Component, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[component(serializable, clone)]
+struct TrainStop {
+    #[component(unique)]
+    name: String,
+}
+
+fn recipe(name: &str) -> Recipe {
+    Recipe {
+        name: name.to_string(),
+        progress: 0.0,
+    }
+}
+
+fn new_world() -> World {
+    let mut world = World::new();
+    world.register_component::<Recipe>();
+    world.register_component::<Tier>();
+    world.register_component::<NetworkId>();
+    world.register_component::<TrainStop>();
+    world
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hash_lookup() {
+        let mut world = new_world();
+        let first = spawn(&mut world, recipe("iron-gear"));
+        let second = spawn(&mut world, recipe("copper-cable"));
+        let third = spawn(&mut world, recipe("iron-gear"));
+
+        let index = world.index::<Recipe>().unwrap();
+        assert_eq!(index.kind(), IndexKind::Hash);
+        assert!(!index.is_unique());
+        assert_eq!(index.get("iron-gear"), &[first, third]);
+        assert_eq!(index.get("copper-cable"), &[second]);
+        assert!(index.get("steel").is_empty());
+        assert_eq!(
+            index.key_of(second).map(String::as_str),
+            Some("copper-cable")
+        );
+        assert_eq!(index.len(), 3);
+    }
+
+    #[test]
+    fn removed_components_leave_the_index() {
+        let mut world = new_world();
+        let first = spawn(&mut world, recipe("iron-gear"));
+        let second = spawn(&mut world, recipe("iron-gear"));
+        let cable = spawn(&mut world, recipe("copper-cable"));
+
+        world.remove_component_from_entity::<Recipe>(first);
+        world.remove_entity(cable);
+
+        let index = world.index::<Recipe>().unwrap();
+        assert_eq!(index.get("iron-gear"), &[second]);
+        assert!(!index.contains_key("copper-cable"));
+        assert_eq!(index.keys().len(), 1);
+    }
+
+    #[test]
+    fn replaced_and_modified_components_are_indexed_again() {
+        let mut world = new_world();
+        let assembler = spawn(&mut world, recipe("iron-gear"));
+        let furnace = spawn(&mut world, recipe("iron-plate"));
+
+        world.replace_component_for_entity(assembler, recipe("copper-cable"));
+        world
+            .reflect_component_mut_from_entity(furnace, "Recipe")
+            .unwrap()
+            .field_mut("name")
+            .unwrap()
+            .set_value(Value::String("steel-plate".to_string()))
+            .unwrap();
+
+        let index = world.index::<Recipe>().unwrap();
+        assert!(index.get("iron-gear").is_empty());
+        assert!(index.get("iron-plate").is_empty());
+        assert_eq!(index.get("copper-cable"), &[assembler]);
+        assert_eq!(index.get("steel-plate"), &[furnace]);
+    }
+
+    #[test]
+    fn bulk_changes_are_indexed_again() {
+        let mut entity_manager = EntityManager::new();
+        entity_manager.register_component::<Tier>();
+        let entities: Vec<usize> = (0..3)
+            .map(|level| {
+                let entity = entity_manager.create_entity();
+                entity_manager.add_component_to_entity(entity, Tier { level });
+                entity
+            })
+            .collect();
+
+        for (_, tier) in entity_manager.iter_components_mut::<Tier>() {
+            tier.level += 10;
+        }
+
+        let index = entity_manager.index::<Tier>().unwrap();
+        assert!(index.get(&0).is_empty());
+        assert_eq!(index.get(&12), &[entities[2]]);
+        assert_eq!(index.len(), 3);
+    }
+
+    #[test]
+    fn ordered_range() {
+        let mut world = new_world();
+        let entities: Vec<usize> = [5, 1, 3, 2, 4]
+            .into_iter()
+            .map(|level| spawn(&mut world, Tier { level }))
+            .collect();
+
+        let index = world.index::<Tier>().unwrap();
+        assert_eq!(index.kind(), IndexKind::Ordered);
+        assert_eq!(
+            index.range(2..=4),
+            vec![entities[3], entities[2], entities[4]]
+        );
+        assert_eq!(index.range(..2), vec![entities[1]]);
+        assert_eq!(index.keys(), vec![&1, &2, &3, &4, &5]);
+    }
+
+    #[test]
+    fn hash_range_is_ordered_by_key() {
+        let mut world = new_world();
+        let gear = spawn(&mut world, recipe("iron-gear"));
+        let cable = spawn(&mut world, recipe("copper-cable"));
+        spawn(&mut world, recipe("steel-plate"));
+
+        let index = world.index::<Recipe>().unwrap();
+        let range: (std::ops::Bound<&str>, std::ops::Bound<&str>) = (
+            std::ops::Bound::Included("a"),
+            std::ops::Bound::Excluded("j"),
+        );
+        assert_eq!(index.range::<str, _>(range), vec![cable, gear]);
+    }
+
+    #[test]
+    fn unique_index_rejects_duplicates() {
+        let mut world = new_world();
+        let chest = spawn(&mut world, NetworkId(7));
+        let other = world.create_entity();
+
+        let error = world
+            .try_add_component_to_entity(other, NetworkId(7))
+            .err()
+            .unwrap();
+        assert_eq!(
+            error,
+            IndexError::Duplicate {
+                component: "NetworkId",
+                entity_id: other,
+                owner: chest,
+            }
+        );
+        assert!(!world.has_component::<NetworkId>(other));
+
+        world
+            .try_add_component_to_entity(other, NetworkId(8))
+            .unwrap();
+        let index = world.index::<NetworkId>().unwrap();
+        assert!(index.is_unique());
+        assert_eq!(index.get_one(&7), Some(chest));
+        assert_eq!(index.get_one(&8), Some(other));
+    }
+
+    #[test]
+    fn unique_index_rejects_duplicate_replacements() {
+        let mut world = new_world();
+        let chest = spawn(&mut world, NetworkId(7));
+        let other = spawn(&mut world, NetworkId(8));
+
+        assert!(
+            world
+                .try_replace_component_for_entity(other, NetworkId(7))
+                .is_err()
+        );
+        assert_eq!(
+            world.borrow_component_from_entity::<NetworkId>(other),
+            Some(&NetworkId(8))
+        );
+
+        // an entity can keep its own key
+        world
+            .try_replace_component_for_entity(chest, NetworkId(7))
+            .unwrap();
+        world.remove_entity(other);
+        world
+            .try_replace_component_for_entity(chest, NetworkId(8))
+            .unwrap();
+        assert_eq!(world.index::<NetworkId>().unwrap().get_one(&8), Some(chest));
+    }
+
+    #[test]
+    #[should_panic(expected = "already used by entity")]
+    fn add_panics_on_duplicates() {
+        let mut world = new_world();
+        spawn(&mut world, NetworkId(7));
+        spawn(&mut world, NetworkId(7));
+    }
+
+    #[test]
+    fn restore_brings_back_the_index() {
+        let mut world = new_world();
+        let assembler = spawn(&mut world, recipe("iron-gear"));
+        let snapshot = world.snapshot().unwrap();
+
+        world.replace_component_for_entity(assembler, recipe("copper-cable"));
+        let furnace = spawn(&mut world, recipe("iron-gear"));
+        assert_eq!(
+            world.index::<Recipe>().unwrap().get("iron-gear"),
+            &[furnace]
+        );
+
+        world.restore(&snapshot).unwrap();
+        let index = world.index::<Recipe>().unwrap();
+        assert_eq!(index.get("iron-gear"), &[assembler]);
+        assert!(index.get("copper-cable").is_empty());
+    }
+
+    #[test]
+    fn in_place_duplicates_are_errors() {
+        let mut world = new_world();
+        let chest = spawn(&mut world, NetworkId(7));
+        let other = spawn(&mut world, NetworkId(8));
+
+        world
+            .reflect_component_mut_from_entity(other, "NetworkId")
+            .unwrap()
+            .set_path("0", Value::UInt(7))
+            .unwrap();
+        let error = IndexError::Duplicate {
+            component: "NetworkId",
+            entity_id: other,
+            owner: chest,
+        };
+        let third = world.create_entity();
+        assert_eq!(
+            world
+                .try_add_component_to_entity(third, NetworkId(9))
+                .err(),
+            Some(error.clone())
+        );
+        assert_eq!(world.try_index::<NetworkId>().err(), Some(error));
+
+        // the index is left as it was, the change is picked up once fixed
+        world
+            .reflect_component_mut_from_entity(other, "NetworkId")
+            .unwrap()
+            .set_path("0", Value::UInt(10))
+            .unwrap();
+        let index = world.try_index::<NetworkId>().unwrap().unwrap();
+        assert_eq!(index.get_one(&7), Some(chest));
+        assert_eq!(index.get_one(&10), Some(other));
+        assert!(!index.contains_key(&8));
+    }
+
+    #[test]
+    fn move_to_a_world_using_the_key() {
+        let mut surface = new_world();
+        let mut platform = new_world();
+        let chest = spawn(&mut surface, NetworkId(7));
+        let other = spawn(&mut surface, NetworkId(8));
+        let owner = spawn(&mut platform, NetworkId(8));
+
+        assert_eq!(
+            surface.move_entities(&[chest, other], &mut platform).err(),
+            Some(TransferError::Index(IndexError::Duplicate {
+                component: "NetworkId",
+                entity_id: other,
+                owner,
+            }))
+        );
+        assert!(surface.is_alive(chest));
+        assert!(surface.is_alive(other));
+        assert_eq!(platform.index::<NetworkId>().unwrap().len(), 1);
+
+        let moved = surface.move_entity(chest, &mut platform).unwrap();
+        assert_eq!(
+            platform.index::<NetworkId>().unwrap().get_one(&7),
+            Some(moved)
+        );
+    }
+
+    #[test]
+    fn spawn_batch_with_a_used_key() {
+        let mut world = new_world();
+        let owner = spawn(&mut world, NetworkId(3));
+
+        let result = world.try_spawn_batch([1, 2, 3].map(|id| (NetworkId(id),)));
+        assert!(matches!(
+            result,
+            Err(IndexError::Duplicate { owner: found, .. }) if found == owner
+        ));
+        // nothing was created, the next entities get the free ids
+        assert!(!world.is_alive(owner + 1));
+        assert_eq!(world.index::<NetworkId>().unwrap().len(), 1);
+        assert_eq!(world.create_entity(), owner + 1);
+
+        // two bundles of the batch with the same key
+        let result = world.try_spawn_batch([4, 5, 4].map(|id| (NetworkId(id),)));
+        assert!(matches!(result, Err(IndexError::Duplicate { .. })));
+        assert_eq!(world.index::<NetworkId>().unwrap().len(), 1);
+
+        let entities = world
+            .try_spawn_batch([4, 5].map(|id| (NetworkId(id),)))
+            .unwrap();
+        assert_eq!(
+            world.index::<NetworkId>().unwrap().get_one(&5),
+            Some(entities[1])
+        );
+    }
+
+    #[test]
+    fn prefab_with_a_used_key() {
+        let mut world = new_world();
+        world
+            .load_prefabs(r#"{ "iron-stop": { "components": { "TrainStop": { "name": "iron" } } } }"#)
+            .unwrap();
+        let first = world.spawn_prefab("iron-stop").unwrap();
+
+        let result = world.spawn_prefab("iron-stop");
+        assert!(matches!(
+            result,
+            Err(PrefabError::Index(IndexError::Duplicate { owner, .. })) if owner == first
+        ));
+        // the entity created for the second copy was removed
+        assert!(!world.is_alive(first + 1));
+        assert_eq!(world.index::<TrainStop>().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn save_with_a_used_key() {
+        let mut world = new_world();
+        spawn(&mut world, TrainStop { name: "iron".to_string() });
+        spawn(&mut world, TrainStop { name: "copper".to_string() });
+
+        let text = world.save_debug().unwrap().replace("copper", "iron");
+        let mut loaded = new_world();
+        assert!(matches!(loaded.load_debug(&text), Err(SaveError::Corrupt(_))));
+        assert!(loaded.index::<TrainStop>().unwrap().is_empty());
+    }
+}
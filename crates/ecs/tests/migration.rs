@@ -0,0 +1,231 @@
+use ecs::migration::ComponentPayload;
+use ecs::serialization::SaveError;
+use ecs::world::World;
+use serde_json::{Value, json};
+
+// the components as they were in the previous release of the game
+mod v1 {
+    use ecs_macros::Component;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Component, Serialize, Deserialize, Debug, PartialEq)]
+    #[component(serializable)]
+    pub struct Position {
+        pub x: i32,
+        pub y: i32,
+    }
+
+    #[derive(Component, Serialize, Deserialize, Debug, PartialEq)]
+    #[component(serializable)]
+    pub struct Burner {
+        pub fuel: f32,
+        pub temperature: f32,
+    }
+
+    #[derive(Component, Serialize, Deserialize, Debug, PartialEq)]
+    #[component(serializable)]
+    pub struct Legacy {
+        pub value: u32,
+    }
+}
+
+// the components of the current release
+mod v2 {
+    use ecs_macros::Component;
+    use serde::{Deserialize, Serialize};
+
+    // fields renamed
+    #[derive(Component, Serialize, Deserialize, Debug, PartialEq)]
+    #[component(serializable, version = 2)]
+    pub struct Position {
+        pub tile_x: i32,
+        pub tile_y: i32,
+    }
+
+    // Burner has been split into Fuel and Heat
+    #[derive(Component, Serialize, Deserialize, Debug, PartialEq)]
+    #[component(serializable)]
+    pub struct Fuel {
+        pub amount: f32,
+    }
+
+    #[derive(Component, Serialize, Deserialize, Debug, PartialEq)]
+    #[component(serializable)]
+    pub struct Heat {
+        pub temperature: f32,
+    }
+}
+
+fn v1_save() -> Vec<u8> {
+    let mut world = World::new();
+    world.register_component::<v1::Position>();
+    world.register_component::<v1::Burner>();
+    world.register_component::<v1::Legacy>();
+
+    let furnace = world.create_entity();
+    world.add_component_to_entity(furnace, v1::Position { x: 3, y: 4 });
+    world.add_component_to_entity(
+        furnace,
+        v1::Burner {
+            fuel: 10.0,
+            temperature: 500.0,
+        },
+    );
+    world.add_component_to_entity(furnace, v1::Legacy { value: 1 });
+
+    let wall = world.create_entity();
+    world.add_component_to_entity(wall, v1::Position { x: -1, y: 0 });
+
+    world.save().unwrap()
+}
+
+fn v2_world() -> World {
+    let mut world = World::new();
+    world.register_component::<v2::Position>();
+    world.register_component::<v2::Fuel>();
+    world.register_component::<v2::Heat>();
+    world
+}
+
+fn rename_field(mut value: Value, from: &str, to: &str) -> Result<Value, String> {
+    let object = value.as_object_mut().ok_or("expected an object")?;
+    let field = object
+        .remove(from)
+        .ok_or_else(|| format!("missing field {}", from))?;
+    object.insert(to.to_string(), field);
+    Ok(value)
+}
+
+fn register_migrations(world: &mut World) {
+    world
+        .migrations_mut()
+        .upgrade("Position", 1, |value| {
+            let value = rename_field(value, "x", "tile_x")?;
+            rename_field(value, "y", "tile_y")
+        })
+        .register("Burner", 1, |payload| {
+            Ok(vec![
+                ComponentPayload::new("Fuel", 1, json!({ "amount": payload.value["fuel"] })),
+                ComponentPayload::new(
+                    "Heat",
+                    1,
+                    json!({ "temperature": payload.value["temperature"] }),
+                ),
+            ])
+        })
+        .remove_component("Legacy");
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn old_save_is_migrated() {
+        let mut world = v2_world();
+        register_migrations(&mut world);
+
+        world.load(&v1_save()).unwrap();
+
+        assert_eq!(
+            world.borrow_component_from_entity::<v2::Position>(0),
+            Some(&v2::Position {
+                tile_x: 3,
+                tile_y: 4
+            })
+        );
+        assert_eq!(
+            world.borrow_component_from_entity::<v2::Position>(1),
+            Some(&v2::Position {
+                tile_x: -1,
+                tile_y: 0
+            })
+        );
+        assert_eq!(
+            world.borrow_component_from_entity::<v2::Fuel>(0),
+            Some(&v2::Fuel { amount: 10.0 })
+        );
+        assert_eq!(
+            world.borrow_component_from_entity::<v2::Heat>(0),
+            Some(&v2::Heat { temperature: 500.0 })
+        );
+        assert_eq!(world.borrow_component_from_entity::<v2::Fuel>(1), None);
+    }
+
+    #[test]
+    fn migrated_world_saves_with_current_versions() {
+        let mut world = v2_world();
+        register_migrations(&mut world);
+        world.load(&v1_save()).unwrap();
+
+        // no migration needed to load a save of the current version
+        let mut reloaded = v2_world();
+        reloaded.load(&world.save().unwrap()).unwrap();
+        assert_eq!(
+            reloaded.borrow_component_from_entity::<v2::Position>(0),
+            Some(&v2::Position {
+                tile_x: 3,
+                tile_y: 4
+            })
+        );
+    }
+
+    #[test]
+    fn missing_migration_is_an_error() {
+        let mut world = v2_world();
+        world
+            .migrations_mut()
+            .register("Burner", 1, |_| Ok(Vec::new()))
+            .remove_component("Legacy");
+
+        assert_eq!(
+            world.load(&v1_save()),
+            Err(SaveError::MissingMigration {
+                name: "Position".to_string(),
+                version: 1
+            })
+        );
+    }
+
+    #[test]
+    fn failing_migration_is_an_error() {
+        let mut world = v2_world();
+        register_migrations(&mut world);
+        world
+            .migrations_mut()
+            .upgrade("Position", 1, |value| rename_field(value, "z", "tile_z"));
+
+        assert_eq!(
+            world.load(&v1_save()),
+            Err(SaveError::Migration {
+                name: "Position".to_string(),
+                version: 1,
+                error: "missing field z".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn newer_component_is_rejected() {
+        let mut world = v2_world();
+        let entity = world.create_entity();
+        world.add_component_to_entity(
+            entity,
+            v2::Position {
+                tile_x: 0,
+                tile_y: 0,
+            },
+        );
+        let save = world.save().unwrap();
+
+        let mut old_world = World::new();
+        old_world.register_component::<v1::Position>();
+        assert_eq!(
+            old_world.load(&save),
+            Err(SaveError::UnsupportedComponentVersion {
+                name: "Position".to_string(),
+                found: 2,
+                supported: 1
+            })
+        );
+    }
+}
@@ -0,0 +1,111 @@
+#![cfg(feature = "parallel")]
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use ecs::parallel::BATCH_SIZE;
+use ecs::world::World;
+use ecs_macros::Component;
+
+#[derive(Component, Debug, PartialEq)]
+struct BeltItem {
+    progress: f32,
+}
+
+#[derive(Component)]
+struct BeltSpeed {
+    speed: f32,
+}
+
+#[derive(Component)]
+struct Powered;
+
+fn new_world() -> World {
+    let mut world = World::new();
+    world.register_component::<BeltItem>();
+    world.register_component::<BeltSpeed>();
+    world.register_component::<Powered>();
+    world
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_component_is_visited_once() {
+        let mut world = new_world();
+        let count = BATCH_SIZE * 3 + 7;
+        let entities = world.spawn_batch((0..count).map(|_| (BeltItem { progress: 0.0 },)));
+        // spread the entities over several signature groups
+        for entity in entities.iter().step_by(3) {
+            world.add_component_to_entity(*entity, Powered);
+        }
+
+        let visits = AtomicUsize::new(0);
+        world.par_for_each::<BeltItem, _>(|_, item| {
+            item.progress += 1.0;
+            visits.fetch_add(1, Ordering::Relaxed);
+        });
+
+        assert_eq!(visits.into_inner(), count);
+        for entity in entities {
+            assert_eq!(
+                world.borrow_component_from_entity::<BeltItem>(entity),
+                Some(&BeltItem { progress: 1.0 })
+            );
+        }
+    }
+
+    #[test]
+    fn owner_is_given_with_the_component() {
+        let mut world = new_world();
+        let entities = world.spawn_batch((0..100).map(|_| (BeltItem { progress: 0.0 },)));
+
+        world.par_for_each::<BeltItem, _>(|entity_id, item| item.progress = entity_id as f32);
+
+        for entity in entities {
+            assert_eq!(
+                world.borrow_component_from_entity::<BeltItem>(entity),
+                Some(&BeltItem {
+                    progress: entity as f32
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn pair_only_visits_entities_with_both_components() {
+        let mut world = new_world();
+        let fast = world.spawn_batch(
+            (0..BATCH_SIZE + 1).map(|_| (BeltItem { progress: 0.0 }, BeltSpeed { speed: 2.0 })),
+        );
+        let idle = world.spawn_batch((0..10).map(|_| (BeltItem { progress: 0.0 },)));
+
+        world.par_for_each_pair::<BeltItem, BeltSpeed, _>(|_, item, belt| {
+            item.progress += belt.speed;
+        });
+
+        for entity in fast {
+            assert_eq!(
+                world.borrow_component_from_entity::<BeltItem>(entity),
+                Some(&BeltItem { progress: 2.0 })
+            );
+        }
+        for entity in idle {
+            assert_eq!(
+                world.borrow_component_from_entity::<BeltItem>(entity),
+                Some(&BeltItem { progress: 0.0 })
+            );
+        }
+    }
+
+    #[test]
+    fn unregistered_components_and_tags_are_ignored() {
+        let mut world = World::new();
+        world.register_component::<Powered>();
+        let entity = world.create_entity();
+        world.add_component_to_entity(entity, Powered);
+
+        world.par_for_each::<BeltItem, _>(|_, _| panic!("not registered"));
+        world.par_for_each::<Powered, _>(|_, _| panic!("a tag"));
+    }
+}
@@ -0,0 +1,231 @@
+use ecs::entity_manager::EntityManager;
+use ecs::event::Events;
+use ecs::plugin::{App, Plugin, PluginError};
+use ecs::system::System;
+use ecs_macros::{Component, Resource};
+
+#[derive(Component)]
+struct Belt {
+    speed: f32,
+}
+
+#[derive(Component)]
+struct Consumer {
+    watts: f32,
+}
+
+#[derive(Resource, Default)]
+struct PowerGrid {
+    demand: f32,
+}
+
+// sent when a belt stops for lack of power
+struct BeltStopped(usize);
+
+struct PowerSystem;
+
+impl System for PowerSystem {
+    fn update(&mut self, _delta_time: f32, entity_manager: &mut EntityManager) {
+        let demand = entity_manager
+            .iter_components::<Consumer>()
+            .map(|(_, consumer)| consumer.watts)
+            .sum();
+        entity_manager
+            .borrow_resource_mut::<PowerGrid>()
+            .unwrap()
+            .demand = demand;
+    }
+}
+
+struct BeltSystem;
+
+impl System for BeltSystem {
+    fn update(&mut self, _delta_time: f32, entity_manager: &mut EntityManager) {
+        let stopped: Vec<usize> = entity_manager
+            .iter_components::<Belt>()
+            .filter(|(_, belt)| belt.speed == 0.0)
+            .map(|(entity_id, _)| entity_id)
+            .collect();
+        let events = entity_manager
+            .borrow_resource_mut::<Events<BeltStopped>>()
+            .unwrap();
+        for entity_id in stopped {
+            events.send(BeltStopped(entity_id));
+        }
+    }
+}
+
+struct PowerPlugin;
+
+impl Plugin for PowerPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_component::<Consumer>()
+            .insert_resource(PowerGrid::default())
+            .register_system(PowerSystem);
+    }
+}
+
+struct BeltsPlugin;
+
+impl Plugin for BeltsPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_component::<Belt>()
+            .add_event::<BeltStopped>()
+            .register_system(BeltSystem);
+    }
+
+    fn dependencies(&self) -> Vec<Box<dyn Plugin>> {
+        vec![Box::new(PowerPlugin)]
+    }
+}
+
+struct CraftingPlugin;
+
+impl Plugin for CraftingPlugin {
+    fn build(&self, _app: &mut App) {}
+
+    fn dependencies(&self) -> Vec<Box<dyn Plugin>> {
+        vec![Box::new(PowerPlugin)]
+    }
+}
+
+// a named plugin, depending on another one by name
+struct Named {
+    name: &'static str,
+    dependency: Option<&'static str>,
+}
+
+impl Plugin for Named {
+    fn build(&self, _app: &mut App) {}
+
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn dependencies(&self) -> Vec<Box<dyn Plugin>> {
+        match self.dependency {
+            Some(name) => vec![Box::new(Named {
+                name,
+                dependency: Some(self.name),
+            })],
+            None => Vec::new(),
+        }
+    }
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plugin_registers_its_components_resources_and_systems() {
+        let mut app = App::new();
+        app.add_plugin(PowerPlugin).unwrap();
+
+        let world = app.world_mut();
+        let machine = world.create_entity();
+        world.add_component_to_entity(machine, Consumer { watts: 90.0 });
+        app.update();
+
+        let grid = app.world().borrow_resource::<PowerGrid>().unwrap();
+        assert_eq!(grid.demand, 90.0);
+    }
+
+    #[test]
+    fn dependencies_are_built_first() {
+        let mut app = App::new();
+        app.add_plugin(BeltsPlugin).unwrap();
+
+        assert_eq!(
+            app.plugins(),
+            &[
+                std::any::type_name::<PowerPlugin>().to_string(),
+                std::any::type_name::<BeltsPlugin>().to_string(),
+            ]
+        );
+        assert!(app.world().borrow_resource::<PowerGrid>().is_some());
+    }
+
+    #[test]
+    fn shared_dependencies_are_built_once() {
+        let mut app = App::new();
+        app.add_plugin(BeltsPlugin)
+            .unwrap()
+            .add_plugin(CraftingPlugin)
+            .unwrap();
+
+        assert_eq!(app.plugins().len(), 3);
+        assert_eq!(app.world().dump().systems.len(), 3);
+    }
+
+    #[test]
+    fn duplicates_are_rejected() {
+        let mut app = App::new();
+        app.add_plugin(PowerPlugin).unwrap();
+
+        let error = app.add_plugin(PowerPlugin).err().unwrap();
+        assert_eq!(
+            error,
+            PluginError::Duplicate(std::any::type_name::<PowerPlugin>().to_string())
+        );
+        assert_eq!(app.world().dump().systems.len(), 1);
+    }
+
+    #[test]
+    fn cycles_are_rejected() {
+        let mut app = App::new();
+        let error = app
+            .add_plugin(Named {
+                name: "belts",
+                dependency: Some("power"),
+            })
+            .err()
+            .unwrap();
+
+        assert_eq!(
+            error,
+            PluginError::Cycle(vec![
+                "belts".to_string(),
+                "power".to_string(),
+                "belts".to_string()
+            ])
+        );
+        assert!(app.plugins().is_empty());
+    }
+
+    #[test]
+    fn events_are_seen_until_the_next_tick() {
+        let mut app = App::new();
+        app.add_plugin(BeltsPlugin).unwrap();
+        let world = app.world_mut();
+        let belt = world.create_entity();
+        world.add_component_to_entity(belt, Belt { speed: 0.0 });
+
+        app.update();
+        let events = app
+            .world()
+            .borrow_resource::<Events<BeltStopped>>()
+            .unwrap();
+        assert_eq!(
+            events.iter().map(|event| event.0).collect::<Vec<_>>(),
+            vec![belt]
+        );
+
+        app.world_mut().remove_entity(belt);
+        app.update();
+        assert_eq!(
+            app.world()
+                .borrow_resource::<Events<BeltStopped>>()
+                .unwrap()
+                .len(),
+            1
+        );
+
+        app.update();
+        assert!(
+            app.world()
+                .borrow_resource::<Events<BeltStopped>>()
+                .unwrap()
+                .is_empty()
+        );
+    }
+}
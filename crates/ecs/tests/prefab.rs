@@ -0,0 +1,277 @@
+use ecs::prefab::PrefabError;
+use ecs::world::World;
+use ecs::{entity_manager::EntityManager, system::System};
+use ecs_macros::Component;
+use serde::{Deserialize, Serialize};
+
+#[derive(Component, Serialize, Deserialize, Debug, PartialEq)]
+#[component(serializable)]
+struct Crafter {
+    speed: f32,
+    modules: u32,
+}
+
+#[derive(Component, Serialize, Deserialize, Debug, PartialEq)]
+#[component(serializable)]
+struct Burner {
+    fuel: String,
+}
+
+#[derive(Component, Serialize, Deserialize, Debug, PartialEq)]
+#[component(serializable)]
+struct Health(u32);
+
+// not serializable, can not be part of a prefab
+#[derive(Component, Debug)]
+struct Selected;
+
+struct RepairSystem;
+
+impl System for RepairSystem {
+    fn update(&mut self, _delta_time: f32, entity_manager: &mut EntityManager) {
+        let entities = entity_manager
+            .query_entities_pair::<Crafter, Health>()
+            .unwrap();
+        for entity in entities {
+            let (_, health) = entity_manager
+                .borrow_components_pair_for_entity::<Crafter, Health>(entity)
+                .unwrap();
+            health.0 += 1;
+        }
+    }
+}
+
+const BASE: &str = r#"{
+    "assembling-machine-1": {
+        "components": {
+            "Crafter": { "speed": 0.5, "modules": 0 },
+            "Burner": { "fuel": "coal" },
+            "Health": 300
+        }
+    },
+    "assembling-machine-2": {
+        "extends": "assembling-machine-1",
+        "components": {
+            "Crafter": { "speed": 0.75 },
+            "Burner": null
+        }
+    }
+}"#;
+
+fn new_world() -> World {
+    let mut world = World::new();
+    world.register_component::<Crafter>();
+    world.register_component::<Burner>();
+    world.register_component::<Health>();
+    world.register_component::<Selected>();
+    world.load_prefabs(BASE).unwrap();
+    world
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spawn_prefab() {
+        let mut world = new_world();
+        let entity = world.spawn_prefab("assembling-machine-1").unwrap();
+
+        assert_eq!(
+            world.borrow_component_from_entity::<Crafter>(entity),
+            Some(&Crafter {
+                speed: 0.5,
+                modules: 0
+            })
+        );
+        assert_eq!(
+            world.borrow_component_from_entity::<Burner>(entity),
+            Some(&Burner {
+                fuel: "coal".to_string()
+            })
+        );
+        assert_eq!(
+            world.borrow_component_from_entity::<Health>(entity),
+            Some(&Health(300))
+        );
+
+        // the entity is part of the queries
+        world.register_system(RepairSystem);
+        world.update();
+        assert_eq!(
+            world.borrow_component_from_entity::<Health>(entity),
+            Some(&Health(301))
+        );
+    }
+
+    #[test]
+    fn extended_prefab_overrides_fields() {
+        let mut world = new_world();
+        let entity = world.spawn_prefab("assembling-machine-2").unwrap();
+
+        assert_eq!(
+            world.borrow_component_from_entity::<Crafter>(entity),
+            Some(&Crafter {
+                speed: 0.75,
+                modules: 0
+            })
+        );
+        assert_eq!(
+            world.borrow_component_from_entity::<Health>(entity),
+            Some(&Health(300))
+        );
+        assert!(
+            world
+                .borrow_component_from_entity::<Burner>(entity)
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn mods_patch_base_prefabs() {
+        let mut world = new_world();
+        world
+            .load_prefabs(
+                r#"{
+                    "assembling-machine-1": { "components": { "Crafter": { "modules": 2 } } },
+                    "assembling-machine-3": {
+                        "extends": "assembling-machine-2",
+                        "components": { "Crafter": { "speed": 1.25 } }
+                    }
+                }"#,
+            )
+            .unwrap();
+
+        // the patch is inherited by the prefabs extending the base one
+        let entity = world.spawn_prefab("assembling-machine-2").unwrap();
+        assert_eq!(
+            world.borrow_component_from_entity::<Crafter>(entity),
+            Some(&Crafter {
+                speed: 0.75,
+                modules: 2
+            })
+        );
+
+        let entity = world.spawn_prefab("assembling-machine-3").unwrap();
+        assert_eq!(
+            world.borrow_component_from_entity::<Crafter>(entity),
+            Some(&Crafter {
+                speed: 1.25,
+                modules: 2
+            })
+        );
+    }
+
+    #[test]
+    fn mods_remove_inherited_components() {
+        let mut world = new_world();
+        world
+            .load_prefabs(
+                r#"{
+                    "assembling-machine-3": {
+                        "extends": "assembling-machine-1",
+                        "components": { "Crafter": { "speed": 1.25 } }
+                    }
+                }"#,
+            )
+            .unwrap();
+        world
+            .load_prefabs(r#"{ "assembling-machine-3": { "components": { "Burner": null } } }"#)
+            .unwrap();
+
+        let entity = world.spawn_prefab("assembling-machine-3").unwrap();
+        assert!(!world.has_component::<Burner>(entity));
+        assert_eq!(
+            world.borrow_component_from_entity::<Crafter>(entity),
+            Some(&Crafter {
+                speed: 1.25,
+                modules: 0
+            })
+        );
+
+        // the base prefab keeps its burner
+        let entity = world.spawn_prefab("assembling-machine-1").unwrap();
+        assert!(world.has_component::<Burner>(entity));
+    }
+
+    #[test]
+    fn unknown_prefab() {
+        let mut world = new_world();
+        assert_eq!(
+            world.spawn_prefab("oil-refinery"),
+            Err(PrefabError::UnknownPrefab("oil-refinery".to_string()))
+        );
+
+        let mut world = World::new();
+        assert_eq!(
+            world.spawn_prefab("oil-refinery"),
+            Err(PrefabError::UnknownPrefab("oil-refinery".to_string()))
+        );
+    }
+
+    #[test]
+    fn prefab_cycle() {
+        let mut world = new_world();
+        world
+            .load_prefabs(
+                r#"{
+                    "a": { "extends": "b" },
+                    "b": { "extends": "a" }
+                }"#,
+            )
+            .unwrap();
+
+        assert_eq!(
+            world.spawn_prefab("a"),
+            Err(PrefabError::Cycle("a".to_string()))
+        );
+    }
+
+    #[test]
+    fn invalid_prefab_file() {
+        let mut world = new_world();
+        assert!(matches!(
+            world.load_prefabs("{ \"a\": 3 }"),
+            Err(PrefabError::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn unknown_component() {
+        let mut world = new_world();
+        world
+            .load_prefabs(r#"{ "a": { "components": { "Beacon": {} } } }"#)
+            .unwrap();
+        assert_eq!(
+            world.spawn_prefab("a"),
+            Err(PrefabError::UnknownComponent("Beacon".to_string()))
+        );
+
+        world
+            .load_prefabs(r#"{ "c": { "components": { "Selected": {} } } }"#)
+            .unwrap();
+        assert_eq!(
+            world.spawn_prefab("c"),
+            Err(PrefabError::UnknownComponent("Selected".to_string()))
+        );
+        // no entity is created
+        assert!(!world.is_alive(0));
+    }
+
+    #[test]
+    fn invalid_component_does_not_leave_an_entity() {
+        let mut world = new_world();
+        world
+            .load_prefabs(
+                r#"{ "broken": { "components": { "Health": 10, "Crafter": { "speed": "fast" } } } }"#,
+            )
+            .unwrap();
+
+        let error = world.spawn_prefab("broken").unwrap_err();
+        assert!(matches!(
+            error,
+            PrefabError::InvalidComponent { ref component, .. } if component == "Crafter"
+        ));
+        assert!(!world.is_alive(0));
+        assert!(world.borrow_component_from_entity::<Health>(0).is_none());
+    }
+}
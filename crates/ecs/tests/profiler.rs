@@ -0,0 +1,139 @@
+use ecs::world::World;
+use ecs::{entity_manager::EntityManager, system::System};
+use ecs_macros::Component;
+
+#[derive(Component, Debug, PartialEq)]
+struct Burner {
+    fuel: f32,
+}
+
+#[derive(Component, Debug, PartialEq)]
+struct Crafter {
+    speed: f32,
+}
+
+struct BurnSystem;
+
+impl System for BurnSystem {
+    fn update(&mut self, _delta_time: f32, entity_manager: &mut EntityManager) {
+        for entity in entity_manager.query_entities::<Burner>().unwrap() {
+            entity_manager
+                .borrow_components_for_entity::<Burner>(entity)
+                .unwrap()
+                .fuel -= 1.0;
+        }
+    }
+}
+
+struct CraftSystem;
+
+impl System for CraftSystem {
+    fn update(&mut self, _delta_time: f32, entity_manager: &mut EntityManager) {
+        entity_manager.query_entities_pair::<Burner, Crafter>();
+        entity_manager.query_entities::<Crafter>();
+    }
+
+    fn name(&self) -> &str {
+        "craft"
+    }
+}
+
+fn new_world() -> World {
+    let mut world = World::new();
+    world.register_component::<Burner>();
+    world.register_component::<Crafter>();
+    world.register_system(BurnSystem);
+    world.register_system(CraftSystem);
+
+    for i in 0..3 {
+        let entity = world.create_entity();
+        world.add_component_to_entity(entity, Burner { fuel: 10.0 });
+        if i == 0 {
+            world.add_component_to_entity(entity, Crafter { speed: 1.0 });
+        }
+    }
+    world
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn disabled_by_default() {
+        let mut world = new_world();
+        world.update();
+        assert!(world.profiler().is_none());
+    }
+
+    #[test]
+    fn systems_and_queries_are_recorded() {
+        let mut world = new_world();
+        world.enable_profiler(10);
+        world.update();
+
+        let tick = world.profiler().unwrap().last_tick().unwrap();
+        assert_eq!(tick.tick, 0);
+        assert_eq!(tick.systems.len(), 2);
+        assert!(tick.systems[0].name.ends_with("BurnSystem"));
+        assert_eq!(tick.systems[0].query_stats.queries, 1);
+        assert_eq!(tick.systems[0].query_stats.entities, 3);
+        assert_eq!(tick.systems[1].name, "craft");
+        assert_eq!(tick.systems[1].query_stats.queries, 2);
+        assert_eq!(tick.systems[1].query_stats.entities, 2);
+        assert!(tick.systems[0].duration <= tick.duration);
+    }
+
+    #[test]
+    fn rolling_window() {
+        let mut world = new_world();
+        world.enable_profiler(4);
+        for _ in 0..10 {
+            world.update();
+        }
+
+        let profiler = world.profiler().unwrap();
+        let ticks: Vec<u64> = profiler.ticks().map(|tick| tick.tick).collect();
+        assert_eq!(ticks, vec![6, 7, 8, 9]);
+
+        let stats = profiler.system_stats();
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats[1].name, "craft");
+        assert_eq!(stats[1].samples, 4);
+        assert_eq!(stats[1].average_queries, 2.0);
+        assert_eq!(stats[1].average_entities, 2.0);
+        assert!(stats[1].p50 <= stats[1].p99 && stats[1].p99 <= stats[1].max);
+
+        let tick_stats = profiler.tick_stats();
+        assert_eq!(tick_stats.samples, 4);
+        assert_eq!(tick_stats.average_queries, 3.0);
+    }
+
+    #[test]
+    fn chrome_trace() {
+        let mut world = new_world();
+        world.enable_profiler(10);
+        world.update();
+        world.update();
+
+        let trace: serde_json::Value =
+            serde_json::from_str(&world.profiler().unwrap().to_chrome_trace()).unwrap();
+        let events = trace["traceEvents"].as_array().unwrap();
+        // a tick event and an event per system for each tick
+        assert_eq!(events.len(), 6);
+        assert!(events.iter().all(|event| event["ph"] == "X"));
+        assert_eq!(events[2]["name"], "craft");
+        assert_eq!(events[2]["args"]["queries"], 2);
+    }
+
+    #[test]
+    fn disable_profiler() {
+        let mut world = new_world();
+        world.enable_profiler(10);
+        world.update();
+        let profiler = world.disable_profiler().unwrap();
+        assert_eq!(profiler.ticks().count(), 1);
+
+        world.update();
+        assert!(world.profiler().is_none());
+    }
+}
@@ -0,0 +1,65 @@
+use ecs::query_manager::QueryManager;
+
+struct Position;
+struct Velocity;
+
+fn new_query_manager() -> (QueryManager, u128, u128) {
+    let mut query_manager = QueryManager::new();
+    query_manager.register_component::<Position>();
+    query_manager.register_component::<Velocity>();
+    let position = *query_manager.get_bit_for_component::<Position>().unwrap();
+    let velocity = *query_manager.get_bit_for_component::<Velocity>().unwrap();
+    (query_manager, position, velocity)
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn remove_entity_keeps_the_group_consistent() {
+        let (mut query_manager, position, velocity) = new_query_manager();
+        query_manager.add_entities(&[0, 1, 2, 3], position);
+        query_manager.add_entity(4, position | velocity);
+
+        // the last entity of the group takes the row of the removed one
+        query_manager.remove_entity(1);
+        assert_eq!(query_manager.query(position), Some(vec![0, 3, 2, 4]));
+
+        query_manager.remove_entity(3);
+        query_manager.remove_entity(42);
+        assert_eq!(query_manager.query(position), Some(vec![0, 2, 4]));
+        assert_eq!(query_manager.get_bitmask_for_entity(3), 0);
+        assert_eq!(query_manager.get_bitmask_for_entity(4), position | velocity);
+    }
+
+    #[test]
+    fn entity_moves_between_groups() {
+        let (mut query_manager, position, velocity) = new_query_manager();
+        query_manager.add_entities(&[0, 1], position);
+
+        query_manager.remove_entity(0);
+        query_manager.add_entity(0, position | velocity);
+        assert_eq!(query_manager.query(position), Some(vec![1, 0]));
+        assert_eq!(query_manager.query(position | velocity), Some(vec![0]));
+
+        // the emptied group is reused
+        query_manager.remove_entity(1);
+        query_manager.add_entity(2, position);
+        assert_eq!(query_manager.query(position), Some(vec![2, 0]));
+    }
+
+    #[test]
+    fn unregister_component_regroups_entities() {
+        let (mut query_manager, position, velocity) = new_query_manager();
+        query_manager.add_entities(&[0, 1], position);
+        query_manager.add_entity(2, position | velocity);
+        query_manager.add_entity(3, velocity);
+
+        query_manager.unregister_component::<Velocity>();
+        assert_eq!(query_manager.get_bitmask_for_entity(2), position);
+        assert_eq!(query_manager.get_bitmask_for_entity(3), 0);
+
+        query_manager.remove_entity(0);
+        assert_eq!(query_manager.query(position), Some(vec![2, 1]));
+    }
+}
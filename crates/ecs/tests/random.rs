@@ -0,0 +1,243 @@
+use ecs::entity_manager::EntityManager;
+use ecs::random::{Random, Rng};
+use ecs::system::System;
+use ecs::world::World;
+use ecs_macros::Resource;
+
+#[derive(Resource, Default)]
+struct Draws {
+    spawns: Vec<u64>,
+    failures: Vec<u64>,
+}
+
+// draw from the "biter-spawns" stream
+struct SpawnSystem;
+
+impl System for SpawnSystem {
+    fn update(&mut self, _delta_time: f32, entity_manager: &mut EntityManager) {
+        let value = entity_manager
+            .borrow_resource_mut::<Random>()
+            .unwrap()
+            .stream("biter-spawns")
+            .next_u64();
+        entity_manager
+            .borrow_resource_mut::<Draws>()
+            .unwrap()
+            .spawns
+            .push(value);
+    }
+}
+
+// draw from the "machine-failures" stream
+struct FailureSystem;
+
+impl System for FailureSystem {
+    fn update(&mut self, _delta_time: f32, entity_manager: &mut EntityManager) {
+        let value = entity_manager
+            .borrow_resource_mut::<Random>()
+            .unwrap()
+            .stream("machine-failures")
+            .next_u64();
+        entity_manager
+            .borrow_resource_mut::<Draws>()
+            .unwrap()
+            .failures
+            .push(value);
+    }
+}
+
+fn new_world(seed: u64) -> World {
+    let mut world = World::new();
+    world
+        .set_seed(seed)
+        .insert_resource(Draws::default())
+        .register_system(SpawnSystem);
+    world
+}
+
+fn spawns(world: &World) -> Vec<u64> {
+    world.borrow_resource::<Draws>().unwrap().spawns.clone()
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_same_numbers() {
+        let mut a = new_world(42);
+        let mut b = new_world(42);
+        let mut c = new_world(7);
+        for _ in 0..10 {
+            a.update();
+            b.update();
+            c.update();
+        }
+
+        assert_eq!(spawns(&a), spawns(&b));
+        assert_ne!(spawns(&a), spawns(&c));
+        assert_eq!(a.state_hash(), b.state_hash());
+        assert_ne!(a.state_hash(), c.state_hash());
+    }
+
+    #[test]
+    fn new_systems_do_not_change_existing_streams() {
+        let mut world = new_world(42);
+        let mut with_failures = new_world(42);
+        with_failures.register_system(FailureSystem);
+        for _ in 0..10 {
+            world.update();
+            with_failures.update();
+        }
+
+        assert_eq!(spawns(&world), spawns(&with_failures));
+        assert_ne!(
+            spawns(&with_failures),
+            with_failures.borrow_resource::<Draws>().unwrap().failures
+        );
+    }
+
+    #[test]
+    fn entity_streams_are_independent() {
+        let mut random = Random::new(42);
+        let first: Vec<u64> = (0..5)
+            .map(|_| random.entity_stream("furnace", 3).next_u64())
+            .collect();
+
+        let mut other = Random::new(42);
+        other.entity_stream("furnace", 1).next_u64();
+        other.stream("furnace").next_u64();
+        let second: Vec<u64> = (0..5)
+            .map(|_| other.entity_stream("furnace", 3).next_u64())
+            .collect();
+
+        assert_eq!(first, second);
+        assert_ne!(
+            Random::new(42).entity_stream("furnace", 1).next_u64(),
+            first[0]
+        );
+    }
+
+    #[test]
+    fn forgotten_entities_restart_their_streams() {
+        let mut random = Random::new(42);
+        let first = random.entity_stream("furnace", 3).next_u64();
+        random.entity_stream("furnace", 3).next_u64();
+
+        random.forget_entity(3);
+        assert_eq!(random.entity_stream("furnace", 3).next_u64(), first);
+    }
+
+    #[test]
+    fn removed_entities_restart_their_streams() {
+        let mut world = new_world(42);
+        let draw = |world: &mut World, entity: usize| {
+            world
+                .borrow_resource_mut::<Random>()
+                .unwrap()
+                .entity_stream("furnace", entity)
+                .next_u64()
+        };
+
+        let entity = world.create_entity();
+        let first = draw(&mut world, entity);
+        draw(&mut world, entity);
+        world.remove_entity(entity);
+        let reused = world.create_entity();
+        assert_eq!(reused, entity);
+        assert_eq!(draw(&mut world, reused), first);
+
+        let batch = [world.create_entity(), world.create_entity()];
+        let firsts = batch.map(|entity| draw(&mut world, entity));
+        world.despawn_batch(&batch);
+        let mut reused = [world.create_entity(), world.create_entity()];
+        reused.sort();
+        assert_eq!(reused, batch);
+        assert_eq!(batch.map(|entity| draw(&mut world, entity)), firsts);
+    }
+
+    #[test]
+    fn streams_are_saved() {
+        let mut world = new_world(42);
+        for _ in 0..5 {
+            world.update();
+        }
+        let bytes = world.save().unwrap();
+
+        let mut loaded = new_world(0);
+        loaded.load(&bytes).unwrap();
+        assert_eq!(loaded.borrow_resource::<Random>().unwrap().seed(), 42);
+        assert_eq!(
+            loaded.state_hash().components,
+            world.state_hash().components
+        );
+
+        world.update();
+        loaded.update();
+        assert_eq!(spawns(&world).last(), spawns(&loaded).last());
+    }
+
+    #[test]
+    fn draws_change_the_state_hash() {
+        let mut world = new_world(42);
+        let hash = world.state_hash();
+
+        world.update();
+
+        assert_eq!(
+            world.state_hash().mismatches(&hash),
+            vec!["Random".to_string()]
+        );
+    }
+
+    #[test]
+    fn labels_are_delimited_in_the_state_hash() {
+        // "a" followed by a stream whose state reads as 32 'a's, then "z",
+        // against a single stream labelled with the same bytes
+        let split: Random = serde_json::from_str(&format!(
+            r#"{{"seed":0,"streams":{{"a":{{"state":[{a},{a},{a},{a}]}},"z":{{"state":[1,2,3,4]}}}},"entity_streams":{{}}}}"#,
+            a = 0x6161616161616161u64
+        ))
+        .unwrap();
+        let joined: Random = serde_json::from_str(&format!(
+            r#"{{"seed":0,"streams":{{"a{}z":{{"state":[1,2,3,4]}}}},"entity_streams":{{}}}}"#,
+            "a".repeat(32)
+        ))
+        .unwrap();
+
+        let mut world = World::new();
+        world.insert_resource(split);
+        let hash = world.state_hash();
+        world.insert_resource(joined);
+        assert_ne!(world.state_hash(), hash);
+    }
+
+    #[test]
+    fn numbers_are_stable() {
+        let mut rng = Rng::new(0);
+        assert_eq!(rng.next_u64(), 0x99ec5f36cb75f2b4);
+        assert_eq!(rng.next_u64(), 0xbf6e1f784956452a);
+    }
+
+    #[test]
+    fn ranges_and_helpers() {
+        let mut rng = Rng::new(42);
+        for _ in 0..1000 {
+            assert!((-5..5).contains(&rng.range(-5..5)));
+            assert!(rng.below(3) < 3);
+            let x = rng.next_f64();
+            assert!((0.0..1.0).contains(&x));
+            let y = rng.next_f32();
+            assert!((0.0..1.0).contains(&y));
+        }
+        assert!(!rng.chance(0.0));
+        assert!(rng.chance(1.0));
+        assert_eq!(rng.pick::<u32>(&[]), None);
+        assert!([1, 2, 3].contains(rng.pick(&[1, 2, 3]).unwrap()));
+
+        let mut items: Vec<u32> = (0..20).collect();
+        rng.shuffle(&mut items);
+        assert_ne!(items, (0..20).collect::<Vec<u32>>());
+        items.sort();
+        assert_eq!(items, (0..20).collect::<Vec<u32>>());
+    }
+}
@@ -0,0 +1,176 @@
+use std::collections::HashMap;
+
+use ecs::reflect::{Reflect, ReflectError, Value};
+use ecs::world::World;
+use ecs_macros::{Component, Reflect};
+
+#[derive(Reflect, Debug)]
+struct Vec2 {
+    x: f32,
+    y: f32,
+}
+
+#[derive(Component, Debug)]
+struct Transform {
+    position: Vec2,
+    rotation: f32,
+}
+
+#[derive(Component, Debug)]
+struct Recipe {
+    name: String,
+    count: u32,
+}
+
+// a type from another crate, it does not implement Reflect
+#[derive(Debug, Default)]
+struct Handle(std::rc::Rc<u32>);
+
+#[derive(Component, Debug)]
+struct Inventory {
+    target: Option<u32>,
+    stock: HashMap<String, u32>,
+    tags: Vec<String>,
+    #[reflect(ignore)]
+    handle: Handle,
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn registry_is_populated_by_register_component() {
+        let mut world = World::new();
+
+        world.register_component::<Transform>();
+        world.register_component::<Recipe>();
+
+        let registry = world.type_registry();
+        assert_eq!(registry.len(), 2);
+
+        let info = registry.get_by_name("Recipe").unwrap();
+        assert_eq!(info.size, std::mem::size_of::<Recipe>());
+        let fields: Vec<_> = info
+            .fields
+            .iter()
+            .map(|field| (field.name, field.type_name))
+            .collect();
+        assert_eq!(
+            fields,
+            vec![("name", "alloc::string::String"), ("count", "u32")]
+        );
+
+        world.unregister_component::<Recipe>();
+        assert!(world.type_registry().get_by_name("Recipe").is_none());
+    }
+
+    #[test]
+    fn dynamic_get_and_set_by_field_path() {
+        let mut world = World::new();
+        world.register_component::<Transform>();
+
+        let entity = world.create_entity();
+        world.add_component_to_entity(
+            entity,
+            Transform {
+                position: Vec2 { x: 1.0, y: 2.0 },
+                rotation: 0.0,
+            },
+        );
+
+        let transform = world
+            .reflect_component_from_entity(entity, "Transform")
+            .unwrap();
+        assert_eq!(transform.get_path("position.y"), Ok(Value::Float(2.0)));
+        assert_eq!(
+            transform.get_path("position"),
+            Err(ReflectError::NotAValue(std::any::type_name::<Vec2>()))
+        );
+
+        let transform = world
+            .reflect_component_mut_from_entity(entity, "Transform")
+            .unwrap();
+        transform.set_path("position.x", Value::Float(5.0)).unwrap();
+        transform.set_path("rotation", Value::Int(90)).unwrap();
+        assert_eq!(
+            transform.set_path("position.z", Value::Float(1.0)),
+            Err(ReflectError::FieldNotFound("position.z".to_string()))
+        );
+
+        let transform = world
+            .borrow_component_from_entity::<Transform>(entity)
+            .unwrap();
+        assert_eq!(transform.position.x, 5.0);
+        assert_eq!(transform.rotation, 90.0);
+    }
+
+    #[test]
+    fn set_value_checks_types() {
+        let mut recipe = Recipe {
+            name: "iron-gear".to_string(),
+            count: 1,
+        };
+        let recipe: &mut dyn Reflect = &mut recipe;
+
+        assert!(recipe.set_path("count", Value::Int(-1)).is_err());
+        assert!(recipe.set_path("count", Value::Float(1.0)).is_err());
+        assert!(recipe.set_path("name", Value::Bool(true)).is_err());
+
+        recipe.set_path("count", Value::UInt(3)).unwrap();
+        recipe
+            .set_path("name", Value::String("copper-cable".to_string()))
+            .unwrap();
+
+        let recipe = recipe.downcast_ref::<Recipe>().unwrap();
+        assert_eq!(recipe.count, 3);
+        assert_eq!(recipe.name, "copper-cable");
+    }
+
+    #[test]
+    fn containers_and_ignored_fields() {
+        let mut world = World::new();
+        world.register_component::<Inventory>();
+
+        let fields: Vec<_> = world
+            .type_registry()
+            .get_by_name("Inventory")
+            .unwrap()
+            .fields
+            .iter()
+            .map(|field| field.name)
+            .collect();
+        assert_eq!(fields, vec!["target", "stock", "tags"]);
+
+        let entity = world.create_entity();
+        world.add_component_to_entity(
+            entity,
+            Inventory {
+                target: Some(4),
+                stock: HashMap::from([("iron".to_string(), 10)]),
+                tags: vec!["chest".to_string()],
+                handle: Handle::default(),
+            },
+        );
+
+        let inventory = world
+            .reflect_component_mut_from_entity(entity, "Inventory")
+            .unwrap();
+        assert_eq!(inventory.get_path("target.0"), Ok(Value::UInt(4)));
+        assert_eq!(inventory.get_path("stock.iron"), Ok(Value::UInt(10)));
+        assert_eq!(
+            inventory.get_path("tags.0"),
+            Ok(Value::String("chest".to_string()))
+        );
+        assert_eq!(
+            inventory.get_path("handle"),
+            Err(ReflectError::FieldNotFound("handle".to_string()))
+        );
+        inventory.set_path("stock.iron", Value::UInt(7)).unwrap();
+
+        let inventory = world
+            .borrow_component_from_entity::<Inventory>(entity)
+            .unwrap();
+        assert_eq!(inventory.stock["iron"], 7);
+        assert_eq!(*inventory.handle.0, 0);
+    }
+}
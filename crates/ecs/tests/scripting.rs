@@ -0,0 +1,226 @@
+#![cfg(feature = "scripting")]
+
+use ecs::event::Events;
+use ecs::reflect::Value;
+use ecs::scripting::{ScriptEngine, ScriptError, ScriptErrors, ScriptEvent, ScriptEvents};
+use ecs::world::World;
+use ecs_macros::{Component, Reflect, Resource};
+
+#[derive(Component, Debug, PartialEq)]
+struct Burner {
+    fuel: f32,
+}
+
+#[derive(Component, Debug, PartialEq)]
+struct Crafter {
+    speed: f32,
+    crafted: u32,
+}
+
+#[derive(Resource, Reflect, Debug, PartialEq)]
+struct Production {
+    total: u64,
+}
+
+// sent by the game when an assembler finishes a craft
+#[derive(Reflect)]
+struct Crafted {
+    count: u32,
+    item: String,
+}
+
+const SCRIPT: &str = r#"
+    fn burn(world, dt) {
+        for entity in world.query(["Burner", "Crafter"]) {
+            let fuel = world.get(entity, "Burner", "fuel") - 1.0;
+            world.set(entity, "Burner", "fuel", fuel);
+            if fuel <= 0.0 {
+                world.emit("out-of-fuel", entity);
+            } else {
+                world.set(entity, "Crafter", "crafted", world.get(entity, "Crafter", "crafted") + 1);
+                world.set_resource("Production", "total", world.resource("Production", "total") + 1);
+            }
+        }
+    }
+
+    fn count(world, dt) {
+        for event in world.events("Crafted") {
+            if event.item == "gear" {
+                world.set_resource("Production", "total", world.resource("Production", "total") + event.count);
+            }
+        }
+    }
+
+    fn broken(world, dt) {
+        world.get(0, "Unknown", "field");
+    }
+"#;
+
+fn new_world() -> World {
+    let mut world = World::new();
+    world.register_component::<Burner>();
+    world.register_component::<Crafter>();
+    world.insert_resource(Production { total: 0 });
+    world
+}
+
+fn new_engine() -> ScriptEngine {
+    let mut engine = ScriptEngine::new();
+    engine.register_resource::<Production>();
+    engine
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn script_system_updates_components_and_resources() {
+        let mut world = new_world();
+        let script = new_engine().compile(SCRIPT).unwrap();
+        world.register_system(script.system("burn").unwrap());
+
+        let machine = world.create_entity();
+        world.add_component_to_entity(machine, Burner { fuel: 2.0 });
+        world.add_component_to_entity(
+            machine,
+            Crafter {
+                speed: 1.0,
+                crafted: 0,
+            },
+        );
+        let idle = world.create_entity();
+        world.add_component_to_entity(idle, Burner { fuel: 2.0 });
+
+        world.update();
+        assert_eq!(
+            world.borrow_component_from_entity::<Burner>(machine),
+            Some(&Burner { fuel: 1.0 })
+        );
+        assert_eq!(
+            world.borrow_component_from_entity::<Crafter>(machine),
+            Some(&Crafter {
+                speed: 1.0,
+                crafted: 1
+            })
+        );
+        assert_eq!(
+            world.borrow_component_from_entity::<Burner>(idle),
+            Some(&Burner { fuel: 2.0 })
+        );
+        assert_eq!(
+            world.borrow_resource::<Production>(),
+            Some(&Production { total: 1 })
+        );
+
+        world.update();
+        let events = world.borrow_resource_mut::<ScriptEvents>().unwrap().drain();
+        assert_eq!(
+            events,
+            vec![ScriptEvent {
+                name: "out-of-fuel".to_string(),
+                value: Some(Value::Int(machine as i64)),
+            }]
+        );
+    }
+
+    #[test]
+    fn runtime_errors_are_recorded() {
+        let mut world = new_world();
+        let script = new_engine().compile(SCRIPT).unwrap();
+        world.register_system(script.system("broken").unwrap());
+        world.create_entity();
+
+        world.update();
+        let errors = &world.borrow_resource::<ScriptErrors>().unwrap().errors;
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(
+            &errors[0],
+            ScriptError::Runtime { function, .. } if function == "broken"
+        ));
+        // the world is given back to the game
+        assert!(world.is_alive(0));
+    }
+
+    #[test]
+    fn unsigned_value_too_large_for_a_script() {
+        let mut world = new_world();
+        world.insert_resource(Production { total: u64::MAX });
+        let script = new_engine().compile(SCRIPT).unwrap();
+        world.register_system(script.system("burn").unwrap());
+
+        let machine = world.create_entity();
+        world.add_component_to_entity(machine, Burner { fuel: 2.0 });
+        world.add_component_to_entity(
+            machine,
+            Crafter {
+                speed: 1.0,
+                crafted: 0,
+            },
+        );
+
+        world.update();
+        let errors = &world.borrow_resource::<ScriptErrors>().unwrap().errors;
+        assert!(matches!(
+            &errors[..],
+            [ScriptError::Runtime { error, .. }] if error.contains("too large")
+        ));
+        assert_eq!(
+            world.borrow_resource::<Production>(),
+            Some(&Production { total: u64::MAX })
+        );
+    }
+
+    #[test]
+    fn compile_errors() {
+        let engine = new_engine();
+        assert!(matches!(
+            engine.compile("fn burn(world, dt) {"),
+            Err(ScriptError::Compile(_))
+        ));
+        assert_eq!(
+            engine.compile(SCRIPT).unwrap().system("missing").err(),
+            Some(ScriptError::MissingFunction("missing".to_string()))
+        );
+    }
+
+    #[test]
+    fn scripts_read_each_event_once() {
+        let mut world = new_world();
+        world.add_event::<Crafted>();
+        let mut engine = new_engine();
+        engine.register_event::<Crafted>();
+        let script = engine.compile(SCRIPT).unwrap();
+        world.register_system(script.system("count").unwrap());
+
+        let events = world.borrow_resource_mut::<Events<Crafted>>().unwrap();
+        events.send(Crafted {
+            count: 2,
+            item: "gear".to_string(),
+        });
+        events.send(Crafted {
+            count: 5,
+            item: "cable".to_string(),
+        });
+        // the events are kept for two ticks, the script reads them once
+        world.update();
+        world.update();
+        world.update();
+
+        assert_eq!(
+            world.borrow_resource::<Production>(),
+            Some(&Production { total: 2 })
+        );
+        assert!(world.borrow_resource::<ScriptErrors>().is_none());
+    }
+
+    #[test]
+    fn unregistered_events_are_an_error() {
+        let mut world = new_world();
+        let script = new_engine().compile(SCRIPT).unwrap();
+        world.register_system(script.system("count").unwrap());
+
+        world.update();
+        let errors = &world.borrow_resource::<ScriptErrors>().unwrap().errors;
+        assert_eq!(errors.len(), 1);
+    }
+}
@@ -0,0 +1,299 @@
+mod common;
+
+use common::{Position, spawn_at};
+use ecs::serialization::{SAVE_FORMAT_VERSION, SaveData, SaveError};
+use ecs::type_registry::RegisterError;
+use ecs::world::World;
+use ecs_macros::{Component, Resource};
+use serde::{Deserialize, Serialize};
+
+#[derive(Component, Serialize, Deserialize, Debug, PartialEq)]
+#[component(serializable)]
+struct Inventory {
+    items: Vec<String>,
+}
+
+// not serializable, it is rebuilt by the game after loading
+#[derive(Component, Debug, PartialEq)]
+struct Selected;
+
+#[derive(Resource, Serialize, Deserialize, Debug, PartialEq)]
+#[resource(serializable)]
+struct Tick(u64);
+
+// same short name as `Position`, both would be saved as "Position"
+mod other {
+    use ecs_macros::Component;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Component, Serialize, Deserialize, Debug, PartialEq)]
+    #[component(serializable)]
+    pub struct Position {
+        pub tile: u32,
+    }
+}
+
+fn new_world() -> World {
+    let mut world = World::new();
+    world.register_component::<Position>();
+    world.register_component::<Inventory>();
+    world.register_component::<Selected>();
+    world.insert_resource(Tick(0));
+    world
+}
+
+fn populated_world() -> World {
+    let mut world = new_world();
+    world.insert_resource(Tick(42));
+
+    for i in 0..10 {
+        let entity = spawn_at(&mut world, i as f32, -(i as f32));
+        if i % 3 == 0 {
+            world.add_component_to_entity(
+                entity,
+                Inventory {
+                    items: vec![format!("item-{}", i)],
+                },
+            );
+        }
+        if i % 2 == 0 {
+            world.add_component_to_entity(entity, Selected);
+        }
+    }
+
+    // reuse an id to bump its generation, and leave two free ids
+    world.remove_entity(3);
+    world.remove_entity(7);
+    world.remove_entity(5);
+    let reused = world.create_entity();
+    world.add_component_to_entity(reused, Position { x: 99.0, y: 99.0 });
+
+    world
+}
+
+fn assert_same_world(expected: &World, actual: &World) {
+    for entity in 0..10 {
+        assert_eq!(
+            expected.is_alive(entity),
+            actual.is_alive(entity),
+            "entity {} liveness",
+            entity
+        );
+        assert_eq!(
+            expected.entity_generation(entity),
+            actual.entity_generation(entity),
+            "entity {} generation",
+            entity
+        );
+        assert_eq!(
+            expected.borrow_component_from_entity::<Position>(entity),
+            actual.borrow_component_from_entity::<Position>(entity),
+            "entity {} position",
+            entity
+        );
+        assert_eq!(
+            expected.borrow_component_from_entity::<Inventory>(entity),
+            actual.borrow_component_from_entity::<Inventory>(entity),
+            "entity {} inventory",
+            entity
+        );
+        assert!(
+            actual
+                .borrow_component_from_entity::<Selected>(entity)
+                .is_none(),
+            "non serializable components are not loaded"
+        );
+    }
+    assert_eq!(
+        expected.borrow_resource::<Tick>(),
+        actual.borrow_resource::<Tick>()
+    );
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn binary_round_trip() {
+        let world = populated_world();
+        let bytes = world.save().unwrap();
+
+        let mut loaded = new_world();
+        loaded.load(&bytes).unwrap();
+
+        assert_same_world(&world, &loaded);
+        assert_eq!(loaded.entity_generation(5), Some(1));
+        assert_eq!(loaded.borrow_resource::<Tick>(), Some(&Tick(42)));
+
+        // storage order and signatures are rebuilt exactly, saving again gives the same file
+        assert_eq!(loaded.save().unwrap(), bytes);
+    }
+
+    #[test]
+    fn debug_round_trip() {
+        let world = populated_world();
+        let text = world.save_debug().unwrap();
+        assert!(text.contains("\"Inventory\""));
+        assert!(!text.contains("\"Selected\""));
+
+        let mut loaded = new_world();
+        loaded.load_debug(&text).unwrap();
+
+        assert_same_world(&world, &loaded);
+        assert_eq!(loaded.save_debug().unwrap(), text);
+    }
+
+    #[test]
+    fn loaded_world_reuses_the_same_ids() {
+        let mut world = populated_world();
+        let mut loaded = new_world();
+        loaded.load(&world.save().unwrap()).unwrap();
+
+        assert_eq!(world.create_entity(), loaded.create_entity());
+        assert_eq!(world.create_entity(), loaded.create_entity());
+        assert_eq!(world.create_entity(), loaded.create_entity());
+    }
+
+    #[test]
+    fn loading_replaces_existing_entities() {
+        let world = populated_world();
+
+        let mut loaded = new_world();
+        for _ in 0..20 {
+            let entity = loaded.create_entity();
+            loaded.add_component_to_entity(entity, Selected);
+        }
+        loaded.load(&world.save().unwrap()).unwrap();
+
+        assert_same_world(&world, &loaded);
+        assert!(!loaded.is_alive(15));
+    }
+
+    #[test]
+    fn unknown_component_is_an_error() {
+        let world = populated_world();
+
+        let mut loaded = World::new();
+        loaded.register_component::<Position>();
+        loaded.insert_resource(Tick(0));
+
+        assert_eq!(
+            loaded.load(&world.save().unwrap()),
+            Err(SaveError::UnknownComponent("Inventory".to_string()))
+        );
+    }
+
+    #[test]
+    fn newer_save_is_rejected() {
+        let mut bytes = populated_world().save().unwrap();
+        bytes[4..8].copy_from_slice(&(SAVE_FORMAT_VERSION + 1).to_le_bytes());
+
+        let mut loaded = new_world();
+        assert_eq!(
+            loaded.load(&bytes),
+            Err(SaveError::UnsupportedVersion {
+                found: SAVE_FORMAT_VERSION + 1,
+                supported: SAVE_FORMAT_VERSION
+            })
+        );
+        assert_eq!(loaded.load(b"nope"), Err(SaveError::InvalidHeader));
+    }
+
+    #[test]
+    fn duplicate_component_name_is_rejected() {
+        let mut world = new_world();
+
+        assert!(matches!(
+            world.try_register_component::<other::Position>(),
+            Err(RegisterError::DuplicateName {
+                name: "Position",
+                ..
+            })
+        ));
+        // the first registration is kept
+        let info = world.type_registry().get_by_name("Position").unwrap();
+        assert_eq!(info.type_path, std::any::type_name::<Position>());
+    }
+
+    #[test]
+    fn non_finite_floats_are_not_saved() {
+        let mut world = new_world();
+        let entity = world.create_entity();
+        world.add_component_to_entity(
+            entity,
+            Position {
+                x: f32::NAN,
+                y: 0.0,
+            },
+        );
+
+        // JSON would write null, a save which can not be loaded
+        assert!(matches!(world.save(), Err(SaveError::Encode(_))));
+        assert!(matches!(world.save_debug(), Err(SaveError::Encode(_))));
+
+        world.replace_component_for_entity(
+            entity,
+            Position {
+                x: 0.0,
+                y: f32::INFINITY,
+            },
+        );
+        assert!(matches!(world.save(), Err(SaveError::Encode(_))));
+
+        world.replace_component_for_entity(entity, Position { x: 0.0, y: 0.0 });
+        let bytes = world.save().unwrap();
+        new_world().load(&bytes).unwrap();
+    }
+
+    #[test]
+    fn tampered_save_is_an_error() {
+        let text = populated_world().save_debug().unwrap();
+        let tamper = |edit: &dyn Fn(&mut serde_json::Value)| {
+            let mut data: serde_json::Value = serde_json::from_str(&text).unwrap();
+            edit(&mut data);
+            let mut loaded = new_world();
+            let result = loaded.load_debug(&data.to_string());
+            // the world is left untouched
+            assert_eq!(loaded.save_debug().unwrap(), new_world().save_debug().unwrap());
+            result
+        };
+
+        let out_of_range = tamper(&|data| data["available_ids"][0] = 1000.into());
+        assert!(matches!(out_of_range, Err(SaveError::Corrupt(_))));
+
+        let duplicated = tamper(&|data| {
+            let id = data["available_ids"][0].clone();
+            data["available_ids"].as_array_mut().unwrap().push(id);
+        });
+        assert!(matches!(duplicated, Err(SaveError::Corrupt(_))));
+
+        let alive = tamper(&|data| data["available_ids"][0] = 0.into());
+        assert!(matches!(alive, Err(SaveError::Corrupt(_))));
+
+        // entity 7 is dead
+        let dead_owner = tamper(&|data| {
+            let position = data["components"]
+                .as_array_mut()
+                .unwrap()
+                .iter_mut()
+                .find(|component| component["name"] == "Position")
+                .unwrap();
+            position["entries"][0][0] = 7.into();
+        });
+        assert!(matches!(dead_owner, Err(SaveError::Corrupt(_))));
+    }
+
+    #[test]
+    fn generation_wraps_around() {
+        let mut world = new_world();
+        let entity = world.create_entity();
+        world.remove_entity(entity);
+        let mut data = SaveData::from_debug_str(&world.save_debug().unwrap()).unwrap();
+        data.entities[entity].generation = u32::MAX;
+
+        let mut loaded = new_world();
+        loaded.load_debug(&data.to_debug_string().unwrap()).unwrap();
+        assert_eq!(loaded.create_entity(), entity);
+        assert_eq!(loaded.entity_generation(entity), Some(0));
+    }
+}
@@ -0,0 +1,142 @@
+mod common;
+
+use common::{Position, SpeedSystem, Velocity, spawn_moving};
+use ecs::random::Random;
+use ecs::snapshot::SnapshotError;
+use ecs::world::World;
+use ecs::{entity_manager::EntityManager, system::System};
+use ecs_macros::Component;
+
+// not clonable, can not be part of a snapshot (tags have no data and always can)
+#[derive(Component, Debug)]
+struct Selected {
+    player: u32,
+}
+
+// pushes the entities by a random amount
+struct WindSystem;
+
+impl System for WindSystem {
+    fn update(&mut self, _delta_time: f32, entity_manager: &mut EntityManager) {
+        let entities = entity_manager.query_entities::<Position>().unwrap();
+        for entity in entities {
+            let push = entity_manager
+                .borrow_resource_mut::<Random>()
+                .unwrap()
+                .entity_stream("wind", entity)
+                .next_f32();
+            entity_manager
+                .borrow_components_for_entity::<Position>(entity)
+                .unwrap()
+                .x += push;
+        }
+    }
+}
+
+fn new_world() -> World {
+    let mut world = World::new();
+    world.register_component::<Position>();
+    world.register_component::<Velocity>();
+    world.register_component::<Selected>();
+    world.register_system(SpeedSystem);
+
+    for i in 0..5 {
+        spawn_moving(&mut world, i as f32, 0.0, Velocity { x: 1.0, y: 2.0 });
+    }
+    world
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn restore_rolls_back_updates() {
+        let mut world = new_world();
+        let hash = world.state_hash();
+        let snapshot = world.snapshot().unwrap();
+
+        world.update();
+        world.update();
+        assert_eq!(
+            world.borrow_component_from_entity::<Position>(0),
+            Some(&Position { x: 2.0, y: 4.0 })
+        );
+
+        world.restore(&snapshot).unwrap();
+        assert_eq!(world.state_hash(), hash);
+        assert_eq!(
+            world.borrow_component_from_entity::<Position>(0),
+            Some(&Position { x: 0.0, y: 0.0 })
+        );
+
+        // the snapshot is untouched by the updates following a restore
+        world.update();
+        world.restore(&snapshot).unwrap();
+        assert_eq!(world.state_hash(), hash);
+    }
+
+    #[test]
+    fn restore_rolls_back_entities() {
+        let mut world = new_world();
+        let snapshot = world.snapshot().unwrap();
+        let hash = world.state_hash();
+
+        world.remove_entity(1);
+        let entity = world.create_entity();
+        world.add_component_to_entity(entity, Position { x: 9.0, y: 9.0 });
+        let other = world.create_entity();
+        world.add_component_to_entity(other, Velocity { x: 0.0, y: 0.0 });
+
+        world.restore(&snapshot).unwrap();
+        assert_eq!(world.state_hash(), hash);
+        assert!(!world.is_alive(other));
+
+        // query signatures are restored as well
+        world.update();
+        assert_eq!(
+            world.borrow_component_from_entity::<Position>(1),
+            Some(&Position { x: 2.0, y: 2.0 })
+        );
+    }
+
+    #[test]
+    fn non_clonable_components_prevent_snapshots() {
+        let mut world = new_world();
+        world.add_component_to_entity(0, Selected { player: 0 });
+
+        assert_eq!(
+            world.snapshot().err(),
+            Some(SnapshotError::NotClonable("Selected"))
+        );
+    }
+
+    #[test]
+    fn registration_change_prevents_restore() {
+        let mut world = new_world();
+        let snapshot = world.snapshot().unwrap();
+
+        world.unregister_component::<Selected>();
+        assert_eq!(
+            world.restore(&snapshot),
+            Err(SnapshotError::RegistrationChanged)
+        );
+    }
+
+    #[test]
+    fn replay_after_restore_draws_the_same_numbers() {
+        let mut world = new_world();
+        world.set_seed(7).register_system(WindSystem);
+        world.update();
+        let snapshot = world.snapshot().unwrap();
+
+        world.update();
+        world.update();
+        let hash = world.state_hash();
+
+        world.restore(&snapshot).unwrap();
+        world.update();
+        world.update();
+        assert_eq!(world.state_hash(), hash);
+        assert!(world.state_hash().mismatches(&hash).is_empty());
+    }
+}
@@ -0,0 +1,232 @@
+use ecs::entity_manager::EntityManager;
+use ecs::spatial::{SpatialIndex, TileFootprint, TilePosition, TileRect};
+use ecs::world::World;
+use ecs_macros::{Component, Resource};
+
+#[derive(Component, Clone, Debug, PartialEq)]
+#[component(clone, on_add = count_added, on_remove = count_removed)]
+struct Belt {
+    speed: f32,
+}
+
+#[derive(Resource, Default)]
+struct BeltCount {
+    count: i32,
+}
+
+fn count_added(entity_manager: &mut EntityManager, _entity_id: usize) {
+    entity_manager
+        .borrow_resource_mut::<BeltCount>()
+        .unwrap()
+        .count += 1;
+}
+
+fn count_removed(entity_manager: &mut EntityManager, _entity_id: usize) {
+    entity_manager
+        .borrow_resource_mut::<BeltCount>()
+        .unwrap()
+        .count -= 1;
+}
+
+fn new_world() -> World {
+    let mut world = World::new();
+    world.register_component::<TilePosition>();
+    world.register_component::<TileFootprint>();
+    world.register_component::<Belt>();
+    world.insert_resource(BeltCount::default());
+    world
+}
+
+fn spawn(world: &mut World, x: i32, y: i32, footprint: Option<TileFootprint>) -> usize {
+    let entity = world.create_entity();
+    world.add_component_to_entity(entity, TilePosition { x, y });
+    if let Some(footprint) = footprint {
+        world.add_component_to_entity(entity, footprint);
+    }
+    entity
+}
+
+fn index(world: &World) -> &SpatialIndex {
+    world.borrow_resource::<SpatialIndex>().unwrap()
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn point_lookup() {
+        let mut world = new_world();
+        let inserter = spawn(&mut world, 2, 3, None);
+        let assembler = spawn(
+            &mut world,
+            4,
+            4,
+            Some(TileFootprint {
+                width: 3,
+                height: 3,
+            }),
+        );
+
+        assert_eq!(index(&world).at(2, 3), &[inserter]);
+        assert_eq!(index(&world).at(6, 6), &[assembler]);
+        assert!(index(&world).at(7, 6).is_empty());
+        assert_eq!(
+            index(&world).bounds(assembler),
+            Some(TileRect::new(4, 4, 3, 3))
+        );
+    }
+
+    #[test]
+    fn rect_and_radius_lookups() {
+        let mut world = new_world();
+        let near = spawn(&mut world, 1, 0, None);
+        let far = spawn(&mut world, 10, 10, None);
+        let large = spawn(
+            &mut world,
+            -5,
+            2,
+            Some(TileFootprint {
+                width: 4,
+                height: 1,
+            }),
+        );
+
+        assert_eq!(
+            index(&world).in_rect(TileRect::new(-2, -2, 5, 5)),
+            vec![near, large]
+        );
+        // larger than the number of entities, every entity is tested
+        assert_eq!(
+            index(&world).in_rect(TileRect::new(-100, -100, 200, 200)),
+            vec![near, far, large]
+        );
+        // (1, 0) and (-2, 2) are within 2 tiles, (10, 10) is not
+        assert_eq!(index(&world).in_radius(0, 0, 2), vec![near]);
+        assert_eq!(index(&world).in_radius(0, 0, 3), vec![near, large]);
+        // the square around a huge radius does not overflow
+        assert_eq!(
+            index(&world).in_radius(0, 0, u32::MAX),
+            vec![near, far, large]
+        );
+    }
+
+    #[test]
+    fn index_follows_the_components() {
+        let mut world = new_world();
+        let entity = spawn(
+            &mut world,
+            0,
+            0,
+            Some(TileFootprint {
+                width: 2,
+                height: 2,
+            }),
+        );
+
+        world.replace_component_for_entity(entity, TilePosition { x: 5, y: 5 });
+        assert!(index(&world).at(0, 0).is_empty());
+        assert_eq!(index(&world).at(6, 6), &[entity]);
+
+        world.remove_component_from_entity::<TileFootprint>(entity);
+        assert_eq!(
+            index(&world).bounds(entity),
+            Some(TileRect::new(5, 5, 1, 1))
+        );
+
+        world.remove_entity(entity);
+        assert!(index(&world).is_empty());
+    }
+
+    #[test]
+    fn index_is_rebuilt_on_restore_and_load() {
+        let mut world = new_world();
+        let entity = spawn(&mut world, 1, 1, None);
+        let snapshot = world.snapshot().unwrap();
+        let bytes = world.save().unwrap();
+
+        world.replace_component_for_entity(entity, TilePosition { x: 8, y: 8 });
+        spawn(&mut world, 3, 3, None);
+        world.restore(&snapshot).unwrap();
+        assert_eq!(index(&world).len(), 1);
+        assert_eq!(index(&world).at(1, 1), &[entity]);
+
+        let mut loaded = new_world();
+        loaded.load(&bytes).unwrap();
+        assert_eq!(index(&loaded).at(1, 1), &[entity]);
+    }
+
+    #[test]
+    fn index_follows_moved_entities() {
+        let mut world = new_world();
+        let mut target = new_world();
+        let entity = spawn(&mut world, 1, 1, None);
+
+        let moved = world.move_entity(entity, &mut target).unwrap();
+        assert!(index(&world).is_empty());
+        assert_eq!(index(&target).at(1, 1), &[moved]);
+    }
+
+    #[test]
+    fn hooks_are_paired() {
+        let mut world = new_world();
+        let count = |world: &World| world.borrow_resource::<BeltCount>().unwrap().count;
+
+        let first = world.create_entity();
+        world.add_component_to_entity(first, Belt { speed: 1.0 });
+        // replacing runs on_remove then on_add, adding again does nothing
+        world.replace_component_for_entity(first, Belt { speed: 2.0 });
+        world.add_component_to_entity(first, Belt { speed: 3.0 });
+        let batch = world.spawn_batch((0..3).map(|_| (Belt { speed: 1.0 },)));
+        assert_eq!(count(&world), 4);
+        assert_eq!(
+            world.borrow_component_from_entity::<Belt>(first),
+            Some(&Belt { speed: 2.0 })
+        );
+
+        world.despawn_batch(&batch);
+        assert_eq!(count(&world), 1);
+        world.remove_component_from_entity::<Belt>(first);
+        assert_eq!(count(&world), 0);
+    }
+
+    #[test]
+    fn huge_footprints_are_capped() {
+        let mut world = new_world();
+        let entity = spawn(
+            &mut world,
+            0,
+            0,
+            Some(TileFootprint {
+                width: u32::MAX,
+                height: 2,
+            }),
+        );
+
+        assert_eq!(
+            index(&world).bounds(entity),
+            Some(TileRect::new(0, 0, TileFootprint::MAX_SIZE, 2))
+        );
+    }
+
+    #[test]
+    fn builtin_components_are_namespaced() {
+        // a game can have its own TilePosition next to the built-in one
+        #[derive(Component)]
+        struct TilePosition {
+            x: i32,
+        }
+
+        let mut world = new_world();
+        world.register_component::<TilePosition>();
+        let entity = world.create_entity();
+        world.add_component_to_entity(entity, TilePosition { x: 3 });
+
+        let registry = world.type_registry();
+        assert!(registry.get_by_name("ecs::TilePosition").is_some());
+        assert!(registry.get_by_name("ecs::TileFootprint").is_some());
+        assert_eq!(
+            registry.get_by_name("TilePosition").unwrap().type_path,
+            std::any::type_name::<TilePosition>()
+        );
+    }
+}
@@ -0,0 +1,167 @@
+use ecs::entity_manager::EntityManager;
+use ecs::plugin::{App, Plugin};
+use ecs::state::State;
+use ecs::system::System;
+use ecs::world::World;
+use ecs_macros::Resource;
+
+#[derive(Debug, Clone, PartialEq)]
+enum GameState {
+    MainMenu,
+    InGame,
+    Paused,
+}
+
+#[derive(Resource, Default)]
+struct Log {
+    lines: Vec<&'static str>,
+}
+
+// append a line to the log each time it runs
+struct Record(&'static str);
+
+impl System for Record {
+    fn update(&mut self, _delta_time: f32, entity_manager: &mut EntityManager) {
+        entity_manager
+            .borrow_resource_mut::<Log>()
+            .unwrap()
+            .lines
+            .push(self.0);
+    }
+}
+
+// pause the game from a system
+struct PauseSystem;
+
+impl System for PauseSystem {
+    fn update(&mut self, _delta_time: f32, entity_manager: &mut EntityManager) {
+        entity_manager
+            .borrow_resource_mut::<State<GameState>>()
+            .unwrap()
+            .set(GameState::Paused);
+    }
+}
+
+fn new_world() -> World {
+    let mut world = World::new();
+    world.insert_resource(Log::default());
+    world.init_state(GameState::MainMenu);
+    world
+}
+
+// the log lines since the last call
+fn take_log(world: &mut World) -> Vec<&'static str> {
+    std::mem::take(&mut world.borrow_resource_mut::<Log>().unwrap().lines)
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn systems_only_run_in_their_state() {
+        let mut world = new_world();
+        world
+            .register_system_in_state(GameState::InGame, Record("simulation"))
+            .register_system(Record("ui"));
+
+        world.update();
+        assert_eq!(take_log(&mut world), vec!["ui"]);
+
+        world.set_state(GameState::InGame);
+        world.update();
+        assert_eq!(take_log(&mut world), vec!["simulation", "ui"]);
+
+        world.set_state(GameState::Paused);
+        world.update();
+        assert_eq!(take_log(&mut world), vec!["ui"]);
+    }
+
+    #[test]
+    fn transition_is_applied_at_the_next_update() {
+        let mut world = new_world();
+        world.set_state(GameState::InGame);
+
+        assert_eq!(world.state::<GameState>(), Some(&GameState::MainMenu));
+        assert_eq!(
+            world.borrow_resource::<State<GameState>>().unwrap().next(),
+            Some(&GameState::InGame)
+        );
+
+        world.update();
+        assert_eq!(world.state::<GameState>(), Some(&GameState::InGame));
+        assert_eq!(
+            world.borrow_resource::<State<GameState>>().unwrap().next(),
+            None
+        );
+    }
+
+    #[test]
+    fn enter_and_exit_systems_run_once_per_transition() {
+        let mut world = new_world();
+        world
+            .register_system_on_enter(GameState::MainMenu, Record("enter menu"))
+            .register_system_on_exit(GameState::MainMenu, Record("exit menu"))
+            .register_system_on_enter(GameState::InGame, Record("enter game"))
+            .register_system_on_exit(GameState::InGame, Record("exit game"));
+
+        // the initial state is entered at the first update
+        world.update();
+        assert_eq!(take_log(&mut world), vec!["enter menu"]);
+        world.update();
+        assert!(take_log(&mut world).is_empty());
+
+        world.set_state(GameState::InGame);
+        world.update();
+        assert_eq!(take_log(&mut world), vec!["exit menu", "enter game"]);
+
+        // requesting the current state does nothing
+        world.set_state(GameState::InGame);
+        world.update();
+        assert!(take_log(&mut world).is_empty());
+    }
+
+    #[test]
+    fn systems_can_request_transitions() {
+        let mut world = new_world();
+        world
+            .register_system_in_state(GameState::MainMenu, PauseSystem)
+            .register_system_on_enter(GameState::Paused, Record("paused"));
+
+        world.update();
+        assert_eq!(world.state::<GameState>(), Some(&GameState::MainMenu));
+        world.update();
+        assert_eq!(world.state::<GameState>(), Some(&GameState::Paused));
+        assert_eq!(take_log(&mut world), vec!["paused"]);
+    }
+
+    #[test]
+    fn restricted_systems_keep_their_name() {
+        let mut world = new_world();
+        world.register_system_in_state(GameState::InGame, PauseSystem);
+
+        assert_eq!(
+            world.dump().systems,
+            vec![std::any::type_name::<PauseSystem>().to_string()]
+        );
+    }
+
+    #[test]
+    fn plugins_register_state_systems() {
+        struct GamePlugin;
+
+        impl Plugin for GamePlugin {
+            fn build(&self, app: &mut App) {
+                app.insert_resource(Log::default())
+                    .init_state(GameState::InGame)
+                    .register_system_on_enter(GameState::InGame, Record("enter game"))
+                    .register_system_in_state(GameState::InGame, Record("simulation"));
+            }
+        }
+
+        let mut app = App::new();
+        app.add_plugin(GamePlugin).unwrap();
+        app.update();
+
+        assert_eq!(take_log(app.world_mut()), vec!["enter game", "simulation"]);
+    }
+}
@@ -0,0 +1,103 @@
+mod common;
+
+use common::Position;
+use ecs::world::World;
+use ecs_macros::Component;
+
+#[derive(Component, Debug)]
+enum Machine {
+    Idle,
+    Crafting { recipe: String, progress: f32 },
+}
+
+fn new_world() -> World {
+    let mut world = World::new();
+    world.register_component::<Position>();
+    world.register_component::<Machine>();
+    for _ in 0..3 {
+        world.create_entity();
+    }
+    world
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn insertion_order_does_not_change_the_hash() {
+        let mut first = new_world();
+        first.add_component_to_entity(0, Position { x: 0.0, y: 0.0 });
+        first.add_component_to_entity(1, Position { x: 1.0, y: 1.0 });
+        first.add_component_to_entity(2, Machine::Idle);
+
+        let mut second = new_world();
+        second.add_component_to_entity(2, Machine::Idle);
+        second.add_component_to_entity(1, Position { x: 1.0, y: 1.0 });
+        second.add_component_to_entity(0, Position { x: 0.0, y: 0.0 });
+
+        assert_eq!(first.state_hash(), second.state_hash());
+        assert!(
+            first
+                .state_hash()
+                .mismatches(&second.state_hash())
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn mismatch_is_narrowed_to_the_component_type() {
+        let mut first = new_world();
+        first.add_component_to_entity(0, Position { x: 0.0, y: 0.0 });
+        first.add_component_to_entity(
+            1,
+            Machine::Crafting {
+                recipe: "iron-gear".to_string(),
+                progress: 0.5,
+            },
+        );
+
+        let mut second = new_world();
+        second.add_component_to_entity(0, Position { x: 0.0, y: 0.0 });
+        second.add_component_to_entity(
+            1,
+            Machine::Crafting {
+                recipe: "iron-gear".to_string(),
+                progress: 0.75,
+            },
+        );
+
+        let first_hash = first.state_hash();
+        let second_hash = second.state_hash();
+        assert_ne!(first_hash.hash, second_hash.hash);
+        assert_eq!(
+            first_hash.mismatches(&second_hash),
+            vec![std::any::type_name::<Machine>().to_string()]
+        );
+    }
+
+    #[test]
+    fn same_component_on_another_entity_changes_the_hash() {
+        let mut first = new_world();
+        first.add_component_to_entity(0, Position { x: 0.0, y: 0.0 });
+
+        let mut second = new_world();
+        second.add_component_to_entity(1, Position { x: 0.0, y: 0.0 });
+
+        assert_eq!(
+            first.state_hash().mismatches(&second.state_hash()),
+            vec![std::any::type_name::<Position>().to_string()]
+        );
+    }
+
+    #[test]
+    fn entity_changes_are_detected() {
+        let first = new_world();
+        let mut second = new_world();
+        second.remove_entity(2);
+
+        assert_eq!(
+            first.state_hash().mismatches(&second.state_hash()),
+            vec!["entities".to_string()]
+        );
+    }
+}
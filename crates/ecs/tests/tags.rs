@@ -0,0 +1,205 @@
+use ecs::world::World;
+use ecs::{entity_manager::EntityManager, system::System};
+use ecs_macros::Component;
+use serde::{Deserialize, Serialize};
+
+#[derive(Component, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[component(serializable, clone)]
+struct Crafter {
+    speed: f32,
+}
+
+#[derive(Component, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[component(serializable, clone)]
+struct Powered;
+
+#[derive(Component)]
+struct Selected;
+
+struct PowerSystem;
+
+impl System for PowerSystem {
+    fn update(&mut self, _delta_time: f32, entity_manager: &mut EntityManager) {
+        for entity in entity_manager
+            .query_entities_pair::<Crafter, Powered>()
+            .unwrap()
+        {
+            entity_manager
+                .borrow_components_for_entity::<Crafter>(entity)
+                .unwrap()
+                .speed += 1.0;
+        }
+    }
+}
+
+fn new_world() -> World {
+    let mut world = World::new();
+    world.register_component::<Crafter>();
+    world.register_component::<Powered>();
+    world.register_component::<Selected>();
+    world
+}
+
+fn machine(world: &mut World, powered: bool) -> usize {
+    let entity = world.create_entity();
+    world.add_component_to_entity(entity, Crafter { speed: 1.0 });
+    if powered {
+        world.add_component_to_entity(entity, Powered);
+    }
+    entity
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zero_sized_components_are_tags() {
+        let mut world = new_world();
+        let entity = machine(&mut world, true);
+
+        assert!(world.has_component::<Powered>(entity));
+        assert!(!world.has_component::<Selected>(entity));
+        // tags have no storage
+        assert!(
+            world
+                .borrow_component_from_entity::<Powered>(entity)
+                .is_none()
+        );
+        assert!(
+            world
+                .borrow_component_from_entity::<Crafter>(entity)
+                .is_some()
+        );
+    }
+
+    #[test]
+    fn toggle_tags() {
+        let mut world = new_world();
+        world.register_system(PowerSystem);
+        let powered = machine(&mut world, true);
+        let unpowered = machine(&mut world, false);
+
+        world.update();
+        world.remove_component_from_entity::<Powered>(powered);
+        world.add_component_to_entity(unpowered, Powered);
+        world.update();
+
+        assert_eq!(
+            world.borrow_component_from_entity::<Crafter>(powered),
+            Some(&Crafter { speed: 2.0 })
+        );
+        assert_eq!(
+            world.borrow_component_from_entity::<Crafter>(unpowered),
+            Some(&Crafter { speed: 2.0 })
+        );
+        assert!(!world.has_component::<Powered>(powered));
+    }
+
+    #[test]
+    fn tags_are_saved() {
+        let mut world = new_world();
+        let powered = machine(&mut world, true);
+        let unpowered = machine(&mut world, false);
+        world.add_component_to_entity(unpowered, Selected);
+        let bytes = world.save().unwrap();
+
+        let mut loaded = new_world();
+        loaded.load(&bytes).unwrap();
+        assert!(loaded.has_component::<Powered>(powered));
+        assert!(!loaded.has_component::<Powered>(unpowered));
+        // Selected is not serializable
+        assert!(!loaded.has_component::<Selected>(unpowered));
+        assert!(loaded.has_component::<Crafter>(unpowered));
+    }
+
+    #[test]
+    fn tags_change_the_state_hash() {
+        let mut world = new_world();
+        let entity = machine(&mut world, false);
+        let hash = world.state_hash();
+
+        world.add_component_to_entity(entity, Powered);
+        assert_ne!(world.state_hash(), hash);
+        world.remove_component_from_entity::<Powered>(entity);
+        assert_eq!(world.state_hash(), hash);
+    }
+
+    #[test]
+    fn restore_tags() {
+        let mut world = new_world();
+        let entity = machine(&mut world, true);
+        let snapshot = world.snapshot().unwrap();
+
+        world.remove_component_from_entity::<Powered>(entity);
+        world.restore(&snapshot).unwrap();
+        assert!(world.has_component::<Powered>(entity));
+    }
+
+    #[test]
+    fn tags_are_moved_with_their_entity() {
+        let mut world = new_world();
+        let mut target = new_world();
+        let entity = machine(&mut world, true);
+
+        let moved = world.move_entity(entity, &mut target).unwrap();
+        assert!(target.has_component::<Powered>(moved));
+        assert_eq!(
+            target.borrow_component_from_entity::<Crafter>(moved),
+            Some(&Crafter { speed: 1.0 })
+        );
+    }
+
+    #[test]
+    fn spawn_batch_with_tags() {
+        let mut world = new_world();
+        let entities = world.spawn_batch((0..3).map(|_| (Crafter { speed: 1.0 }, Powered)));
+
+        assert!(
+            entities
+                .iter()
+                .all(|entity| world.has_component::<Powered>(*entity))
+        );
+        assert_eq!(
+            world.dump().entity(entities[0]).unwrap().components.len(),
+            2
+        );
+    }
+
+    #[test]
+    fn tags_in_prefabs() {
+        let mut world = new_world();
+        world
+            .load_prefabs(
+                r#"{
+                    "assembler": {
+                        "components": { "Crafter": { "speed": 1.0 }, "Powered": {} }
+                    }
+                }"#,
+            )
+            .unwrap();
+        // the value of a tag is ignored
+        let entity = world.spawn_prefab("assembler").unwrap();
+        assert!(world.has_component::<Crafter>(entity));
+        assert!(world.has_component::<Powered>(entity));
+    }
+
+    #[test]
+    fn tags_have_no_components_to_borrow() {
+        let mut entity_manager = EntityManager::new();
+        entity_manager.register_component::<Crafter>();
+        entity_manager.register_component::<Powered>();
+        let entity = entity_manager.create_entity();
+        entity_manager.add_component_to_entity(entity, Crafter { speed: 1.0 });
+        entity_manager.add_component_to_entity(entity, Powered);
+
+        assert!(entity_manager.borrow_components::<Powered>().is_empty());
+        assert!(entity_manager.borrow_components_mut::<Powered>().is_empty());
+        assert!(entity_manager.component_entities::<Powered>().is_empty());
+        assert_eq!(entity_manager.iter_components::<Powered>().count(), 0);
+        assert_eq!(entity_manager.iter_components_mut::<Powered>().count(), 0);
+        assert_eq!(
+            entity_manager.query_entities::<Powered>(),
+            Some(vec![entity])
+        );
+    }
+}
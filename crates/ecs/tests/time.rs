@@ -0,0 +1,129 @@
+use std::time::Duration;
+
+use ecs::entity_manager::EntityManager;
+use ecs::system::System;
+use ecs::time::{MAX_SPEED, MIN_SPEED, Time};
+use ecs::world::World;
+use ecs_macros::Resource;
+
+#[derive(Resource, Default)]
+struct Ticks {
+    ticks: Vec<u64>,
+    seconds: f32,
+}
+
+// record the tick number and the game time seen by the systems
+struct ClockSystem;
+
+impl System for ClockSystem {
+    fn update(&mut self, delta_time: f32, entity_manager: &mut EntityManager) {
+        let tick = entity_manager.borrow_resource::<Time>().unwrap().tick();
+        let ticks = entity_manager.borrow_resource_mut::<Ticks>().unwrap();
+        ticks.ticks.push(tick);
+        ticks.seconds += delta_time;
+    }
+}
+
+fn new_world() -> World {
+    let mut world = World::new();
+    world.insert_resource(Ticks::default());
+    world.register_system(ClockSystem);
+    world
+}
+
+fn time(world: &mut World) -> &mut Time {
+    world.borrow_resource_mut::<Time>().unwrap()
+}
+
+fn frame() -> Duration {
+    Duration::from_secs_f64(1.0 / 60.0)
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn systems_get_the_fixed_delta_and_the_tick() {
+        let mut world = new_world();
+        time(&mut world).set_delta(0.1);
+
+        world.update();
+        world.update();
+
+        let ticks = world.borrow_resource::<Ticks>().unwrap();
+        assert_eq!(ticks.ticks, vec![0, 1]);
+        assert!((ticks.seconds - 0.2).abs() < 1e-6);
+        let time = world.borrow_resource::<Time>().unwrap();
+        assert_eq!(time.tick(), 2);
+        assert!((time.elapsed().as_secs_f64() - 0.2).abs() < 1e-6);
+    }
+
+    #[test]
+    fn one_tick_per_frame_at_normal_speed() {
+        let mut world = new_world();
+
+        for _ in 0..60 {
+            assert_eq!(world.run_frame(frame()), 1);
+        }
+
+        let time = world.borrow_resource::<Time>().unwrap();
+        assert_eq!(time.tick(), 60);
+        assert!((time.real_elapsed().as_secs_f64() - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn fast_forward_runs_more_ticks_per_frame() {
+        let mut world = new_world();
+        time(&mut world).set_speed(8.0);
+
+        assert_eq!(world.run_frame(frame()), 8);
+        assert_eq!(world.borrow_resource::<Ticks>().unwrap().ticks.len(), 8);
+    }
+
+    #[test]
+    fn slow_motion_runs_a_tick_every_few_frames() {
+        let mut world = new_world();
+        time(&mut world).set_speed(0.25);
+
+        let ticks: Vec<u32> = (0..8).map(|_| world.run_frame(frame())).collect();
+
+        assert_eq!(ticks, vec![0, 0, 0, 1, 0, 0, 0, 1]);
+    }
+
+    #[test]
+    fn speed_is_clamped() {
+        let mut world = new_world();
+        time(&mut world).set_speed(1000.0);
+        assert_eq!(time(&mut world).speed(), MAX_SPEED);
+        time(&mut world).set_speed(0.0);
+        assert_eq!(time(&mut world).speed(), MIN_SPEED);
+    }
+
+    #[test]
+    fn paused_frames_run_no_tick() {
+        let mut world = new_world();
+        time(&mut world).set_paused(true);
+
+        assert_eq!(world.run_frame(frame()), 0);
+        assert!(time(&mut world).is_paused());
+        assert_eq!(time(&mut world).tick(), 0);
+        assert!(time(&mut world).real_elapsed() > Duration::ZERO);
+
+        // step by step while paused
+        world.update();
+        assert_eq!(time(&mut world).tick(), 1);
+
+        time(&mut world).set_paused(false);
+        assert_eq!(world.run_frame(frame()), 1);
+    }
+
+    #[test]
+    fn long_frames_are_capped() {
+        let mut world = new_world();
+        time(&mut world).set_max_ticks_per_frame(10);
+
+        assert_eq!(world.run_frame(Duration::from_secs(1)), 10);
+        // the time left over is dropped
+        assert_eq!(world.run_frame(frame()), 1);
+    }
+}
@@ -0,0 +1,224 @@
+mod common;
+
+use common::{Position, spawn_at};
+use ecs::transfer::{NO_ENTITY, TransferError};
+use ecs::world::World;
+use ecs::{entity_manager::EntityManager, system::System};
+use ecs_macros::{Component, Resource};
+
+// not clonable, moving does not need to copy the component
+#[derive(Component, Debug, PartialEq)]
+struct Inventory {
+    items: Vec<String>,
+}
+
+// an inserter takes items from a machine and drops them into another
+#[derive(Component, Debug, PartialEq)]
+struct Inserter {
+    #[component(entity)]
+    from: usize,
+    #[component(entity)]
+    to: Vec<usize>,
+}
+
+// a pole connected to another, the hook records the connections it sees
+#[derive(Component, Debug, PartialEq)]
+#[component(on_add = record_wire)]
+struct Wire {
+    #[component(entity)]
+    to: usize,
+}
+
+#[derive(Resource, Default)]
+struct Wires {
+    connections: Vec<(usize, usize)>,
+}
+
+fn record_wire(entity_manager: &mut EntityManager, entity_id: usize) {
+    let to = entity_manager
+        .borrow_component_for_entity::<Wire>(entity_id)
+        .unwrap()
+        .to;
+    if let Some(wires) = entity_manager.borrow_resource_mut::<Wires>() {
+        wires.connections.push((entity_id, to));
+    }
+}
+
+struct DriftSystem;
+
+impl System for DriftSystem {
+    fn update(&mut self, _delta_time: f32, entity_manager: &mut EntityManager) {
+        for entity in entity_manager.query_entities::<Position>().unwrap() {
+            entity_manager
+                .borrow_components_for_entity::<Position>(entity)
+                .unwrap()
+                .x += 1.0;
+        }
+    }
+}
+
+fn new_world() -> World {
+    let mut world = World::new();
+    world.register_component::<Position>();
+    world.register_component::<Inventory>();
+    world.register_component::<Inserter>();
+    world.register_component::<Wire>();
+    world
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn move_entity_with_its_components() {
+        let mut surface = new_world();
+        let mut platform = new_world();
+        // fill the platform so the ids differ
+        platform.create_entity();
+
+        let chest = spawn_at(&mut surface, 1.0, 2.0);
+        surface.add_component_to_entity(
+            chest,
+            Inventory {
+                items: vec!["iron-plate".to_string()],
+            },
+        );
+
+        let moved = surface.move_entity(chest, &mut platform).unwrap();
+
+        assert!(!surface.is_alive(chest));
+        assert!(
+            surface
+                .borrow_component_from_entity::<Position>(chest)
+                .is_none()
+        );
+        assert_eq!(
+            platform.borrow_component_from_entity::<Position>(moved),
+            Some(&Position { x: 1.0, y: 2.0 })
+        );
+        assert_eq!(
+            platform.borrow_component_from_entity::<Inventory>(moved),
+            Some(&Inventory {
+                items: vec!["iron-plate".to_string()]
+            })
+        );
+    }
+
+    #[test]
+    fn move_entities_remaps_relations() {
+        let mut surface = new_world();
+        let mut platform = new_world();
+        for _ in 0..3 {
+            platform.create_entity();
+        }
+
+        let furnace = surface.create_entity();
+        let chest = surface.create_entity();
+        let inserter = surface.create_entity();
+        surface.add_component_to_entity(
+            inserter,
+            Inserter {
+                from: furnace,
+                to: vec![chest],
+            },
+        );
+        // the relation to an entity left behind is cut, the id would point to
+        // an unrelated entity of the target
+        let belt = surface.create_entity();
+        surface.add_component_to_entity(
+            belt,
+            Inserter {
+                from: furnace,
+                to: vec![42],
+            },
+        );
+
+        let entity_map = surface
+            .move_entities(&[furnace, chest, inserter, belt], &mut platform)
+            .unwrap();
+
+        assert_eq!(entity_map.len(), 4);
+        assert_eq!(
+            platform.borrow_component_from_entity::<Inserter>(entity_map[&inserter]),
+            Some(&Inserter {
+                from: entity_map[&furnace],
+                to: vec![entity_map[&chest]],
+            })
+        );
+        assert_eq!(
+            platform.borrow_component_from_entity::<Inserter>(entity_map[&belt]),
+            Some(&Inserter {
+                from: entity_map[&furnace],
+                to: vec![NO_ENTITY],
+            })
+        );
+    }
+
+    #[test]
+    fn moved_entities_are_queried_in_the_target() {
+        let mut surface = new_world();
+        let mut preview = World::new();
+        preview.register_component::<Position>();
+        preview.register_system(DriftSystem);
+
+        let ghost = spawn_at(&mut surface, 0.0, 0.0);
+        let ghost = surface.move_entity(ghost, &mut preview).unwrap();
+
+        preview.update();
+        assert_eq!(
+            preview.borrow_component_from_entity::<Position>(ghost),
+            Some(&Position { x: 1.0, y: 0.0 })
+        );
+    }
+
+    #[test]
+    fn move_to_a_world_missing_a_component() {
+        let mut surface = new_world();
+        let mut preview = World::new();
+        preview.register_component::<Position>();
+
+        let chest = spawn_at(&mut surface, 0.0, 0.0);
+        surface.add_component_to_entity(chest, Inventory { items: Vec::new() });
+
+        assert_eq!(
+            surface.move_entity(chest, &mut preview),
+            Err(TransferError::UnregisteredComponent("Inventory"))
+        );
+        // nothing moved
+        assert!(surface.is_alive(chest));
+        assert!(
+            surface
+                .borrow_component_from_entity::<Position>(chest)
+                .is_some()
+        );
+        assert!(!preview.is_alive(0));
+
+        assert_eq!(
+            surface.move_entity(7, &mut preview),
+            Err(TransferError::UnknownEntity(7))
+        );
+    }
+
+    #[test]
+    fn hooks_see_the_remapped_relations() {
+        let mut surface = new_world();
+        let mut platform = new_world();
+        platform.insert_resource(Wires::default());
+        // fill the platform so the ids differ
+        platform.create_entity();
+        platform.create_entity();
+
+        let pole = surface.create_entity();
+        let other = surface.create_entity();
+        surface.add_component_to_entity(pole, Wire { to: other });
+        surface.add_component_to_entity(other, Wire { to: pole });
+
+        let map = surface
+            .move_entities(&[pole, other], &mut platform)
+            .unwrap();
+        assert_eq!(
+            platform.borrow_resource::<Wires>().unwrap().connections,
+            vec![(map[&pole], map[&other]), (map[&other], map[&pole])]
+        );
+    }
+}